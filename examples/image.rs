@@ -1,5 +1,6 @@
-use posify::img;
+use posify::img::{self, RasterMode};
 use posify::printer::{Printer, SupportedPrinters};
+use posify::text::Alignment;
 
 fn main() -> Result<(), posify::printer::Error> {
     let logo = image::open("rust.png").expect("File not found!").resize(
@@ -16,8 +17,8 @@ fn main() -> Result<(), posify::printer::Error> {
 
     let _ = printer
         .chain_hwinit()?
-        .chain_align("ct")?
-        .chain_raster(&logo, None)?
+        .chain_align(Alignment::Center)?
+        .chain_raster(&logo, RasterMode::Normal)?
         .chain_feed(1)?
         .chain_partial_cut()?
         .flush();
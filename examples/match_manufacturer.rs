@@ -1,5 +1,6 @@
 use posify::barcode::{BarcodeType, Font, TextPosition};
 use posify::printer::{self, Printer};
+use posify::text::{Alignment, Underline};
 
 fn main() -> Result<(), printer::Error> {
     let (mfg, vid, pid) = Printer::get_mfg_info().unwrap();
@@ -9,10 +10,10 @@ fn main() -> Result<(), printer::Error> {
 
     let _ = printer
         .chain_hwinit()?
-        .chain_align("ct")?
-        .chain_underline_mode(Some("thick"))?
+        .chain_align(Alignment::Center)?
+        .chain_underline_mode(Underline::Thick)?
         .chain_text("Underlined Text")?
-        .chain_underline_mode(Some("off"))?
+        .chain_underline_mode(Underline::Off)?
         .chain_text("The quick brown fox jumps over the lazy dog")?
         .chain_feed(1)?
         .chain_barcode(
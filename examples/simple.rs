@@ -2,6 +2,7 @@ use std::error::Error;
 
 use posify::barcode::{BarcodeType, Font, TextPosition};
 use posify::printer::{Printer, SupportedPrinters};
+use posify::text::{Alignment, Underline};
 
 fn main() -> Result<(), Box<dyn Error>> {
     let vid: u16 = 0x154f;
@@ -11,10 +12,10 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let _ = printer
         .chain_hwinit()?
-        .chain_align("ct")?
-        .chain_underline_mode(Some("thick"))?
+        .chain_align(Alignment::Center)?
+        .chain_underline_mode(Underline::Thick)?
         .chain_text("Code128")?
-        .chain_underline_mode(Some("off"))?
+        .chain_underline_mode(Underline::Off)?
         .chain_feed(1)?
         .chain_barcode(
             "0123456",
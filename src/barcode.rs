@@ -1,7 +1,9 @@
-use crate::printer::SupportedPrinters;
+use crate::code128;
+use crate::printer::{Error, SupportedPrinters};
 use std::io;
+use std::str::FromStr;
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum BarcodeType {
     UPCA = 0,   // or 65?
     UPCE = 1,   // or 66?
@@ -18,6 +20,98 @@ pub enum BarcodeType {
     GS1 = 13,      // or 78?
 }
 
+impl FromStr for BarcodeType {
+    type Err = Error;
+
+    /// Accepts the symbology names with or without dashes, e.g. `EAN-13`,
+    /// `ean13` or `CODE128`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().replace(['-', '_', ' '], "").as_ref() {
+            "UPCA" => Ok(BarcodeType::UPCA),
+            "UPCE" => Ok(BarcodeType::UPCE),
+            "EAN13" | "JAN13" => Ok(BarcodeType::EAN13),
+            "EAN8" | "JAN8" => Ok(BarcodeType::EAN8),
+            "CODE39" => Ok(BarcodeType::CODE39),
+            "ITF" => Ok(BarcodeType::ITF),
+            "CODE93" => Ok(BarcodeType::Code93),
+            "CODABAR" | "NW7" => Ok(BarcodeType::Codabar),
+            "CODE128" => Ok(BarcodeType::Code128),
+            "PDF417" => Ok(BarcodeType::PDF417),
+            "QRCODE" | "QR" => Ok(BarcodeType::QRCode),
+            "MAXICODE" => Ok(BarcodeType::Maxicode),
+            "GS1" => Ok(BarcodeType::GS1),
+            _ => Err(Error::InvalidArgument),
+        }
+    }
+}
+
+impl BarcodeType {
+    /// The value of `m` in GS k m d1 ... dk NUL, `None` if the symbology
+    /// can only be sent length-prefixed
+    pub fn function_a(&self) -> Option<u8> {
+        match self {
+            BarcodeType::UPCA => Some(0),
+            BarcodeType::UPCE => Some(1),
+            BarcodeType::EAN13 => Some(2),
+            BarcodeType::EAN8 => Some(3),
+            BarcodeType::CODE39 => Some(4),
+            BarcodeType::ITF => Some(5),
+            BarcodeType::Codabar => Some(6),
+            _ => None,
+        }
+    }
+
+    /// The value of `m` in GS k m n d1 ... dn, `None` for the 2D symbologies,
+    /// which aren't printed with GS k
+    pub fn function_b(&self) -> Option<u8> {
+        match self {
+            BarcodeType::UPCA => Some(65),
+            BarcodeType::UPCE => Some(66),
+            BarcodeType::EAN13 => Some(67),
+            BarcodeType::EAN8 => Some(68),
+            BarcodeType::CODE39 => Some(69),
+            BarcodeType::ITF => Some(70),
+            BarcodeType::Codabar => Some(71),
+            BarcodeType::Code93 => Some(72),
+            // GS1-128 is printed as Code128 starting with FNC1, which works
+            // on printers without their own GS1-128 support
+            BarcodeType::Code128 | BarcodeType::GS1 => Some(73),
+            BarcodeType::PDF417 | BarcodeType::QRCode | BarcodeType::Maxicode => None,
+        }
+    }
+
+    /// Function A where the symbology has one, function B otherwise
+    pub fn default_function(&self) -> BarcodeFunction {
+        match self.function_a() {
+            Some(_) => BarcodeFunction::A,
+            None => BarcodeFunction::B,
+        }
+    }
+}
+
+/// The two forms of GS k
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum BarcodeFunction {
+    /// Data ends with a NUL, only for the original symbologies
+    #[default]
+    A,
+    /// Data comes after its length, for every symbology
+    B,
+}
+
+impl FromStr for BarcodeFunction {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_ref() {
+            "A" => Ok(BarcodeFunction::A),
+            "B" => Ok(BarcodeFunction::B),
+            _ => Err(Error::InvalidArgument),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum TextPosition {
     Off = 0x00,
     Above = 0x01,
@@ -25,6 +119,21 @@ pub enum TextPosition {
     Both = 0x03,
 }
 
+impl FromStr for TextPosition {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_ref() {
+            "OFF" | "NONE" => Ok(TextPosition::Off),
+            "ABOVE" => Ok(TextPosition::Above),
+            "BELOW" => Ok(TextPosition::Below),
+            "BOTH" => Ok(TextPosition::Both),
+            _ => Err(Error::InvalidArgument),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Font {
     Standard,   // As defined in SNBC printer docs
     Compressed, // As defined in SNBC printer docs
@@ -32,14 +141,305 @@ pub enum Font {
     FontB,      // As defined in P3 printer docs
 }
 
-#[derive(thiserror::Error, Debug, PartialEq)]
-pub enum CodeCError {
+impl FromStr for Font {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_ref() {
+            "STANDARD" => Ok(Font::Standard),
+            "COMPRESSED" => Ok(Font::Compressed),
+            "A" | "FONTA" => Ok(Font::FontA),
+            "B" | "FONTB" => Ok(Font::FontB),
+            _ => Err(Error::InvalidArgument),
+        }
+    }
+}
+
+/// Why barcode data can't be printed
+#[derive(thiserror::Error, Clone, Debug, PartialEq, Eq)]
+pub enum BarcodeError {
     #[error("Not a Number")]
     NotANumber,
     #[error("Length not divisible by two")]
     InvalidLength,
+    #[error("No barcode data")]
+    Empty,
+    #[error("{kind:?} can't encode {character:?} at position {position}")]
+    InvalidCharacter {
+        kind: BarcodeType,
+        character: char,
+        position: usize,
+    },
+    #[error("{kind:?} takes {expected}, not {found}")]
+    WrongLength {
+        kind: BarcodeType,
+        expected: &'static str,
+        found: usize,
+    },
+    #[error("Check digit is {found:?} but should be {expected:?}")]
+    CheckDigit { expected: char, found: char },
+    #[error("{0:?} doesn't have a check digit")]
+    NoCheckDigit(BarcodeType),
+    #[error("UPC-A code can't be shortened to UPC-E")]
+    NotCompressible,
+    #[error("GS1 application identifier ({ai}) {message}")]
+    Gs1 { ai: String, message: &'static str },
+}
+
+/// The name [BarcodeError] had when it only covered Code Set C
+pub type CodeCError = BarcodeError;
+
+/// Characters CODE39 can encode, in the order of their check digit values
+const CODE39_CHARS: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ-. $/+%";
+
+/// Codabar characters between the start and stop characters
+const CODABAR_CHARS: &str = "0123456789-$:/.+";
+
+/// Checks `code` against the character set and length rules of `kind`, and
+/// any check digit it already includes. The 2D symbologies aren't checked.
+///
+/// Lengths that leave out the check digit are fine, the printer works it out.
+/// UPC-E also takes the full 11 or 12 digit UPC-A number, as long as it can
+/// be shortened.
+pub fn validate(kind: BarcodeType, code: &str) -> Result<(), BarcodeError> {
+    if code.is_empty() {
+        return Err(BarcodeError::Empty);
+    }
+    let length = code.chars().count();
+    let wrong_length = |expected| BarcodeError::WrongLength {
+        kind,
+        expected,
+        found: length,
+    };
+    match kind {
+        BarcodeType::EAN13 | BarcodeType::EAN8 | BarcodeType::UPCA => {
+            let (data, expected) = match kind {
+                BarcodeType::EAN13 => (12, "12 or 13 digits"),
+                BarcodeType::EAN8 => (7, "7 or 8 digits"),
+                _ => (11, "11 or 12 digits"),
+            };
+            only_chars(kind, code, "0123456789")?;
+            if length == data + 1 {
+                verify(&code[..data], code, mod10)
+            } else if length == data {
+                Ok(())
+            } else {
+                Err(wrong_length(expected))
+            }
+        }
+        BarcodeType::UPCE => {
+            only_chars(kind, code, "0123456789")?;
+            match length {
+                6 => Ok(()),
+                7 | 8 => {
+                    if !code.starts_with('0') {
+                        return Err(invalid_char(kind, code, 0));
+                    }
+                    if length == 8 {
+                        verify(&expand_upce(&code[..7]), code, mod10)?;
+                    }
+                    Ok(())
+                }
+                11 | 12 => {
+                    compress_upca(&code[..11]).ok_or(BarcodeError::NotCompressible)?;
+                    if length == 12 {
+                        verify(&code[..11], code, mod10)?;
+                    }
+                    Ok(())
+                }
+                _ => Err(wrong_length("6, 7, 8, 11 or 12 digits")),
+            }
+        }
+        BarcodeType::ITF => {
+            only_chars(kind, code, "0123456789")?;
+            if length % 2 != 0 {
+                return Err(wrong_length("an even number of digits"));
+            }
+            Ok(())
+        }
+        BarcodeType::CODE39 => {
+            // The printer adds the * start and stop characters if they're
+            // left out, but if one is there the other has to be too
+            let inner = match (code.strip_prefix('*'), code.ends_with('*')) {
+                (Some(rest), true) if length > 1 => &rest[..rest.len() - 1],
+                (None, false) => code,
+                (Some(_), _) => return Err(invalid_char(kind, code, 0)),
+                (None, true) => return Err(invalid_char(kind, code, length - 1)),
+            };
+            if inner.is_empty() {
+                return Err(BarcodeError::Empty);
+            }
+            let offset = usize::from(inner.len() != code.len());
+            only_chars(kind, inner, CODE39_CHARS).map_err(|e| shift(e, offset))
+        }
+        BarcodeType::Codabar => {
+            let is_start_stop = |c: char| matches!(c.to_ascii_uppercase(), 'A'..='D');
+            if length < 2 {
+                return Err(wrong_length("start and stop characters"));
+            }
+            if !code.starts_with(is_start_stop) {
+                return Err(invalid_char(kind, code, 0));
+            }
+            if !code.ends_with(is_start_stop) {
+                return Err(invalid_char(kind, code, length - 1));
+            }
+            only_chars(kind, &code[1..code.len() - 1], CODABAR_CHARS).map_err(|e| shift(e, 1))
+        }
+        BarcodeType::Code93 => match code.chars().position(|c| !c.is_ascii()) {
+            Some(position) => Err(invalid_char(kind, code, position)),
+            None => Ok(()),
+        },
+        BarcodeType::Code128 => code128::parse(code).map(|_| ()),
+        BarcodeType::GS1 => code128::gs1(code).map(|_| ()),
+        BarcodeType::PDF417 | BarcodeType::QRCode | BarcodeType::Maxicode => Ok(()),
+    }
 }
 
+/// Works out the check digit for `code`, which must not already have one
+pub fn check_digit(kind: BarcodeType, code: &str) -> Result<char, BarcodeError> {
+    let (digits, expected) = match kind {
+        BarcodeType::EAN13 => (Some(12), "12 digits"),
+        BarcodeType::EAN8 => (Some(7), "7 digits"),
+        BarcodeType::UPCA => (Some(11), "11 digits"),
+        BarcodeType::UPCE => (Some(7), "7 digits"),
+        BarcodeType::ITF => (None, "digits"),
+        BarcodeType::CODE39 => {
+            let inner = code.trim_matches('*');
+            validate(kind, inner)?;
+            return Ok(mod43(inner));
+        }
+        _ => return Err(BarcodeError::NoCheckDigit(kind)),
+    };
+    if code.is_empty() {
+        return Err(BarcodeError::Empty);
+    }
+    only_chars(kind, code, "0123456789")?;
+    let length = code.len();
+    if digits.is_some_and(|digits| digits != length) {
+        return Err(BarcodeError::WrongLength {
+            kind,
+            expected,
+            found: length,
+        });
+    }
+    match kind {
+        BarcodeType::UPCE if !code.starts_with('0') => Err(invalid_char(kind, code, 0)),
+        BarcodeType::UPCE => Ok(mod10(&expand_upce(code))),
+        _ => Ok(mod10(code)),
+    }
+}
+
+/// `code` with its check digit added. CODE39 codes between `*` start and
+/// stop characters keep them at the ends.
+pub fn with_check_digit(kind: BarcodeType, code: &str) -> Result<String, BarcodeError> {
+    let digit = check_digit(kind, code)?;
+    match code.strip_suffix('*') {
+        Some(inner) if kind == BarcodeType::CODE39 => Ok(format!("{}{}*", inner, digit)),
+        _ => Ok(format!("{}{}", code, digit)),
+    }
+}
+
+fn invalid_char(kind: BarcodeType, code: &str, position: usize) -> BarcodeError {
+    BarcodeError::InvalidCharacter {
+        kind,
+        character: code.chars().nth(position).unwrap_or_default(),
+        position,
+    }
+}
+
+/// Moves the position of an invalid character on by `offset`, for errors
+/// found in part of a code
+fn shift(error: BarcodeError, offset: usize) -> BarcodeError {
+    match error {
+        BarcodeError::InvalidCharacter {
+            kind,
+            character,
+            position,
+        } => BarcodeError::InvalidCharacter {
+            kind,
+            character,
+            position: position + offset,
+        },
+        other => other,
+    }
+}
+
+fn only_chars(kind: BarcodeType, code: &str, allowed: &str) -> Result<(), BarcodeError> {
+    match code.chars().position(|c| !allowed.contains(c)) {
+        Some(position) => Err(invalid_char(kind, code, position)),
+        None => Ok(()),
+    }
+}
+
+/// Compares the last character of `code` with the check digit of `data`
+fn verify(data: &str, code: &str, check: fn(&str) -> char) -> Result<(), BarcodeError> {
+    let expected = check(data);
+    let found = code.chars().last().unwrap_or_default();
+    if found == expected {
+        Ok(())
+    } else {
+        Err(BarcodeError::CheckDigit { expected, found })
+    }
+}
+
+/// The EAN, UPC and ITF check digit: weights of 3 and 1 from the right
+fn mod10(digits: &str) -> char {
+    let sum: u32 = digits
+        .bytes()
+        .rev()
+        .enumerate()
+        .map(|(i, d)| (d - b'0') as u32 * if i % 2 == 0 { 3 } else { 1 })
+        .sum();
+    char::from(b'0' + ((10 - sum % 10) % 10) as u8)
+}
+
+/// The optional CODE39 check digit
+fn mod43(code: &str) -> char {
+    let sum: usize = code.chars().filter_map(|c| CODE39_CHARS.find(c)).sum();
+    CODE39_CHARS.as_bytes()[sum % 43] as char
+}
+
+/// Expands a number system digit and six UPC-E digits to the 11 digit
+/// UPC-A number the check digit is worked out from
+fn expand_upce(code: &str) -> String {
+    let (ns, d) = code.split_at(1);
+    let d = d.as_bytes();
+    let s = |range: std::ops::Range<usize>| std::str::from_utf8(&d[range]).unwrap_or_default();
+    let (manufacturer, product) = match d[5] {
+        b'0'..=b'2' => (
+            format!("{}{}00", s(0..2), s(5..6)),
+            format!("00{}", s(2..5)),
+        ),
+        b'3' => (format!("{}00", s(0..3)), format!("000{}", s(3..5))),
+        b'4' => (format!("{}0", s(0..4)), format!("0000{}", s(4..5))),
+        _ => (s(0..5).to_string(), format!("0000{}", s(5..6))),
+    };
+    format!("{}{}{}", ns, manufacturer, product)
+}
+
+/// Shortens an 11 digit UPC-A number to its number system digit and six
+/// UPC-E digits, `None` if it has too few zeros in the right places
+fn compress_upca(code: &str) -> Option<String> {
+    let (ns, rest) = code.split_at(1);
+    if ns != "0" {
+        return None;
+    }
+    let (m, p) = rest.split_at(5);
+    let upce = if matches!(&m[2..], "000" | "100" | "200") && p.starts_with("00") {
+        format!("{}{}{}", &m[..2], &p[2..], &m[2..3])
+    } else if m.ends_with("00") && p.starts_with("000") {
+        format!("{}{}3", &m[..3], &p[3..])
+    } else if m.ends_with('0') && p.starts_with("0000") {
+        format!("{}{}4", &m[..4], &p[4..])
+    } else if p.starts_with("0000") && p.as_bytes()[4] >= b'5' {
+        format!("{}{}", m, &p[4..])
+    } else {
+        return None;
+    };
+    Some(format!("{}{}", ns, upce))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Barcode {
     pub printer: SupportedPrinters,
     pub width: u8,  // 2 <= n <= 6
@@ -48,6 +448,10 @@ pub struct Barcode {
     // pub code: &str,
     pub kind: BarcodeType,
     pub position: TextPosition,
+    pub function: BarcodeFunction,
+    /// Adds the check digit to the code before printing, for the
+    /// symbologies [check_digit] works for
+    pub check_digit: bool,
 }
 
 impl Barcode {
@@ -120,19 +524,53 @@ impl Barcode {
         }
     }
 
-    pub fn set_barcode_type(&mut self) -> [u8; 3] {
-        match self.kind {
-            BarcodeType::EAN13 => [0x1d, 0x6b, 0x02],
-            BarcodeType::Code128 => {
-                if self.printer == SupportedPrinters::SNBC {
-                    [0x1d, 0x6b, 0x49]
-                } else {
-                    [0x1d, 0x6b, 0x08]
+    /// GS k m - Selects the symbology, in the form given by `function`.
+    ///
+    /// Code93, Code128 and GS1-128 only exist in function B and the 2D
+    /// symbologies aren't printed with GS k at all.
+    pub fn set_barcode_type(&mut self) -> Result<[u8; 3], Error> {
+        let m = match self.function {
+            BarcodeFunction::A => self.kind.function_a(),
+            BarcodeFunction::B => self.kind.function_b(),
+        };
+        match (self.printer, m) {
+            (SupportedPrinters::Unknown, _) | (_, None) => Err(Error::Unsupported),
+            (_, Some(m)) => Ok([0x1d, 0x6b, m]),
+        }
+    }
+
+    /// The data that follows GS k m: `code` ending in a NUL for function A,
+    /// or after its length for function B. Code128 and GS1-128 data is
+    /// encoded with [code128::encode].
+    ///
+    /// `code` is checked with [validate] first, after adding its check digit
+    /// if [Barcode::check_digit] is set.
+    pub fn data(&self, code: &str) -> Result<Vec<u8>, Error> {
+        let code = if self.check_digit {
+            with_check_digit(self.kind, code)?
+        } else {
+            code.to_string()
+        };
+        validate(self.kind, &code)?;
+        let code = code.as_str();
+        let mut data = match self.kind {
+            BarcodeType::Code128 => code128::encode(&code128::parse(code)?)?,
+            BarcodeType::GS1 => code128::encode(&code128::gs1(code)?)?,
+            _ => code.as_bytes().to_vec(),
+        };
+        match self.function {
+            BarcodeFunction::A => {
+                if data.contains(&0x00) {
+                    return Err(Error::InvalidArgument);
                 }
+                data.push(0x00); // Need to send NULL to finish
+            }
+            BarcodeFunction::B => {
+                let count = u8::try_from(data.len()).map_err(|_| Error::InvalidArgument)?;
+                data.insert(0, count);
             }
-            // TODO: Add more barcode types
-            _ => [0x1d, 0x6b, 0x02], // Default to EAN13?
         }
+        Ok(data)
     }
 
     // to_codeset_c converts a string of numbers to the u8 value
@@ -191,4 +629,145 @@ mod tests {
         let resp = Barcode::to_codeset_c("1234".to_string()).unwrap();
         assert_eq!(resp, vec![0x0c_u8, 0x22]);
     }
+
+    fn barcode(kind: BarcodeType, function: BarcodeFunction) -> Barcode {
+        Barcode {
+            printer: SupportedPrinters::P3,
+            width: 2,
+            height: 80,
+            font: Font::FontA,
+            kind,
+            position: TextPosition::Below,
+            function,
+            check_digit: false,
+        }
+    }
+
+    #[test]
+    fn barcode_types() {
+        let types = [
+            (BarcodeType::UPCA, Some(0x00), Some(0x41)),
+            (BarcodeType::UPCE, Some(0x01), Some(0x42)),
+            (BarcodeType::EAN13, Some(0x02), Some(0x43)),
+            (BarcodeType::EAN8, Some(0x03), Some(0x44)),
+            (BarcodeType::CODE39, Some(0x04), Some(0x45)),
+            (BarcodeType::ITF, Some(0x05), Some(0x46)),
+            (BarcodeType::Codabar, Some(0x06), Some(0x47)),
+            (BarcodeType::Code93, None, Some(0x48)),
+            (BarcodeType::Code128, None, Some(0x49)),
+            (BarcodeType::GS1, None, Some(0x49)),
+            (BarcodeType::QRCode, None, None),
+        ];
+        for (kind, a, b) in types {
+            let a_type = barcode(kind, BarcodeFunction::A).set_barcode_type().ok();
+            let b_type = barcode(kind, BarcodeFunction::B).set_barcode_type().ok();
+            assert_eq!(a_type, a.map(|m| [0x1d, 0x6b, m]), "{:?}", kind);
+            assert_eq!(b_type, b.map(|m| [0x1d, 0x6b, m]), "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn barcode_data() {
+        let a = barcode(BarcodeType::CODE39, BarcodeFunction::A);
+        assert_eq!(a.data("AB-1").unwrap(), b"AB-1\x00");
+        assert!(a.data("A\0B").is_err());
+        let b = barcode(BarcodeType::CODE39, BarcodeFunction::B);
+        assert_eq!(b.data("AB-1").unwrap(), b"\x04AB-1");
+        assert!(b.data(&"1".repeat(256)).is_err());
+        let code128 = barcode(BarcodeType::Code128, BarcodeFunction::B);
+        assert_eq!(code128.data("1234").unwrap(), b"\x04{C\x0c\x22");
+        assert_eq!(code128.data("Ab1").unwrap(), b"\x05{BAb1");
+
+        let mut ean = barcode(BarcodeType::EAN13, BarcodeFunction::A);
+        assert!(ean.data("4006381333932").is_err());
+        ean.check_digit = true;
+        assert_eq!(ean.data("400638133393").unwrap(), b"4006381333931\x00");
+    }
+
+    #[test]
+    fn validation() {
+        use BarcodeType::*;
+        assert_eq!(validate(EAN13, "4006381333931"), Ok(()));
+        assert_eq!(validate(EAN13, "400638133393"), Ok(()));
+        assert_eq!(
+            validate(EAN13, "4006381333932"),
+            Err(BarcodeError::CheckDigit {
+                expected: '1',
+                found: '2'
+            })
+        );
+        assert_eq!(validate(EAN8, "9638507"), Ok(()),);
+        assert_eq!(validate(EAN8, "96385074"), Ok(()));
+        assert_eq!(
+            validate(EAN8, "963850"),
+            Err(BarcodeError::WrongLength {
+                kind: EAN8,
+                expected: "7 or 8 digits",
+                found: 6
+            })
+        );
+        assert_eq!(validate(UPCA, "036000291452"), Ok(()));
+        assert_eq!(
+            validate(UPCA, "03600029145X"),
+            Err(BarcodeError::InvalidCharacter {
+                kind: UPCA,
+                character: 'X',
+                position: 11
+            })
+        );
+        assert_eq!(validate(UPCE, "04252614"), Ok(()));
+        assert_eq!(validate(UPCE, "042100005264"), Ok(()));
+        assert_eq!(
+            validate(UPCE, "036000291452"),
+            Err(BarcodeError::NotCompressible)
+        );
+        assert!(validate(UPCE, "14252614").is_err());
+        assert_eq!(validate(ITF, "1234"), Ok(()));
+        assert!(validate(ITF, "123").is_err());
+        assert_eq!(validate(CODE39, "*CODE-39*"), Ok(()));
+        assert_eq!(
+            validate(CODE39, "*code39"),
+            Err(BarcodeError::InvalidCharacter {
+                kind: CODE39,
+                character: '*',
+                position: 0
+            })
+        );
+        assert_eq!(
+            validate(CODE39, "CODE_39"),
+            Err(BarcodeError::InvalidCharacter {
+                kind: CODE39,
+                character: '_',
+                position: 4
+            })
+        );
+        assert_eq!(validate(Codabar, "A40156B"), Ok(()));
+        assert!(validate(Codabar, "40156").is_err());
+        assert_eq!(
+            validate(Codabar, "A40X56B"),
+            Err(BarcodeError::InvalidCharacter {
+                kind: Codabar,
+                character: 'X',
+                position: 3
+            })
+        );
+        assert!(validate(Code128, "Caf€").is_err());
+        assert_eq!(validate(Code93, ""), Err(BarcodeError::Empty));
+    }
+
+    #[test]
+    fn check_digits() {
+        use BarcodeType::*;
+        assert_eq!(check_digit(EAN13, "400638133393"), Ok('1'));
+        assert_eq!(check_digit(EAN8, "9638507"), Ok('4'));
+        assert_eq!(check_digit(UPCA, "03600029145"), Ok('2'));
+        assert_eq!(check_digit(UPCE, "0425261"), Ok('4'));
+        assert_eq!(with_check_digit(ITF, "123").unwrap(), "1236");
+        assert_eq!(with_check_digit(CODE39, "*CODE39*").unwrap(), "*CODE39W*");
+        assert_eq!(
+            check_digit(Code128, "abc"),
+            Err(BarcodeError::NoCheckDigit(Code128))
+        );
+        assert!(check_digit(EAN13, "4006381333931").is_err());
+    }
 }
@@ -0,0 +1,143 @@
+//! Double-byte text for Chinese, Japanese and Korean printers
+//!
+//! Printers with CJK fonts print ideographs, kana and hangul in Kanji mode
+//! (FS &), where each character is sent as two bytes of the encoding picked
+//! with FS C. Once an encoding has been selected with
+//! [crate::printer::Printer::cjk_encoding], [crate::printer::Printer::print]
+//! switches in and out of Kanji mode on its own around runs of CJK text.
+
+use std::str::FromStr;
+
+use encoding::all::{BIG5_2003, GB18030, WINDOWS_31J, WINDOWS_949};
+use encoding::types::EncodingRef;
+
+use crate::printer::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CjkEncoding {
+    /// Simplified Chinese
+    Gb18030,
+    /// Traditional Chinese
+    Big5,
+    /// Japanese
+    ShiftJis,
+    /// Korean, sent as the KS C 5601 superset CP949
+    Ksc5601,
+}
+
+impl CjkEncoding {
+    /// The encoder for text printed in Kanji mode with this encoding
+    pub fn encoding(&self) -> EncodingRef {
+        match self {
+            CjkEncoding::Gb18030 => GB18030,
+            CjkEncoding::Big5 => BIG5_2003,
+            CjkEncoding::ShiftJis => WINDOWS_31J,
+            CjkEncoding::Ksc5601 => WINDOWS_949,
+        }
+    }
+}
+
+impl FromStr for CjkEncoding {
+    type Err = Error;
+
+    /// Accepts the usual names, e.g. `GB18030`, `Big5`, `Shift_JIS`, `SJIS`,
+    /// `KSC5601` or `EUC-KR`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name: String = s
+            .to_uppercase()
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .collect();
+        match name.as_ref() {
+            "GB18030" | "GBK" | "GB2312" => Ok(CjkEncoding::Gb18030),
+            "BIG5" => Ok(CjkEncoding::Big5),
+            "SHIFTJIS" | "SJIS" | "CP932" => Ok(CjkEncoding::ShiftJis),
+            "KSC5601" | "EUCKR" | "CP949" => Ok(CjkEncoding::Ksc5601),
+            _ => Err(Error::InvalidArgument),
+        }
+    }
+}
+
+/// Double-byte encodings and their FS C numbers. `None` stands for an
+/// encoding that's fixed in the firmware of the printers sold in that market,
+/// which don't take FS C at all.
+pub const EPSON_CJK_ENCODINGS: &[(CjkEncoding, Option<u8>)] = &[
+    (CjkEncoding::ShiftJis, Some(1)),
+    (CjkEncoding::Gb18030, None),
+    (CjkEncoding::Big5, None),
+    (CjkEncoding::Ksc5601, None),
+];
+
+/// Whether `c` belongs to a CJK script and is printed in Kanji mode.
+/// Halfwidth katakana and Hangul are single bytes and left to the code page.
+pub fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x1100..=0x11ff     // Hangul Jamo
+        | 0x2e80..=0x2fdf   // CJK and Kangxi radicals
+        | 0x3000..=0x33ff   // CJK punctuation, kana, bopomofo, enclosed
+        | 0x3400..=0x4dbf   // CJK extension A
+        | 0x4e00..=0x9fff   // CJK unified ideographs
+        | 0xa960..=0xa97f   // Hangul Jamo extended A
+        | 0xac00..=0xd7af   // Hangul syllables
+        | 0xf900..=0xfaff   // CJK compatibility ideographs
+        | 0xfe30..=0xfe4f   // CJK compatibility forms
+        | 0xff00..=0xff60   // Fullwidth ASCII and brackets
+        | 0xffe0..=0xffe6   // Fullwidth signs
+        | 0x20000..=0x2fa1f // CJK extensions B-F, compatibility supplement
+    )
+}
+
+/// Splits `content` into runs of single-byte and CJK text, returned with
+/// `true` for the CJK ones. ASCII prints the same in either mode, so it
+/// joins the run next to it rather than starting one of its own.
+pub(crate) fn runs(content: &str) -> Vec<(bool, &str)> {
+    let mut runs: Vec<(bool, &str)> = Vec::new();
+    let mut start = 0;
+    let mut current: Option<bool> = None;
+    for (i, c) in content.char_indices() {
+        if c.is_ascii() {
+            continue;
+        }
+        let cjk = is_cjk(c);
+        match current {
+            Some(mode) if mode != cjk => {
+                runs.push((mode, &content[start..i]));
+                start = i;
+            }
+            _ => (),
+        }
+        current = Some(cjk);
+    }
+    if start < content.len() {
+        runs.push((current.unwrap_or(false), &content[start..]));
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_runs() {
+        assert_eq!(
+            runs("Total 合计: 5€ 謝謝"),
+            vec![(true, "Total 合计: 5"), (false, "€ "), (true, "謝謝")]
+        );
+        assert_eq!(runs("ASCII only"), vec![(false, "ASCII only")]);
+        assert!(runs("").is_empty());
+        assert_eq!(
+            runs("ｶﾀｶﾅ ＡＢＣ"),
+            vec![(false, "ｶﾀｶﾅ "), (true, "ＡＢＣ")]
+        );
+        assert!(!is_cjk('ﾟ') && !is_cjk('ﾡ') && is_cjk('￥'));
+        assert_eq!(
+            "sjis".parse::<CjkEncoding>().unwrap(),
+            CjkEncoding::ShiftJis
+        );
+        assert_eq!(
+            "EUC-KR".parse::<CjkEncoding>().unwrap(),
+            CjkEncoding::Ksc5601
+        );
+    }
+}
@@ -0,0 +1,359 @@
+//! Code128 encoding with the narrowest mix of code sets
+//!
+//! Code128 has three code sets: A for upper case and control characters, B
+//! for printable ASCII and C for pairs of digits. [encode] works out where
+//! to switch between them, or shift a single character into the other of A
+//! and B, so the symbol has as few characters, and so as few bars, as it can.
+//! The result is GS k function B data for the printer, with the code set
+//! changes spelled out the ESC/POS way: `{A`, `{B`, `{C`, `{S` for a shift,
+//! `{1` to `{4` for FNC1 to FNC4 and `{{` for a `{`.
+//!
+//! Text passed to [crate::printer::Printer::barcode] is read with [parse],
+//! which takes the same `{1` to `{4` and `{{` escapes, and GS1-128 text with
+//! [gs1], from application identifiers in brackets.
+//!
+//! # Example
+//! ```rust
+//! use posify::code128;
+//!
+//! let data = code128::encode(&code128::parse("ABC123456789").unwrap()).unwrap();
+//! assert_eq!(data, b"{BABC1{C\x17\x2d\x43\x59");
+//!
+//! let label = code128::gs1("(01)09501101530003(10)AB12(17)250101").unwrap();
+//! assert_eq!(
+//!     code128::encode(&label).unwrap(),
+//!     b"{C{1\x01\x09\x32\x0b\x01\x35\x00\x03\x0a{BAB{C\x0c{1\x11\x19\x01\x01"
+//! );
+//! ```
+
+use crate::barcode::{BarcodeError, BarcodeType};
+
+/// A character in Code128 data
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Code128Char {
+    /// A byte of data. Bytes from 128 up are sent as FNC4 and the byte less
+    /// 128, which scanners read as ISO 8859-1.
+    Char(u8),
+    Fnc1,
+    Fnc2,
+    Fnc3,
+    Fnc4,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CodeSet {
+    A,
+    B,
+    C,
+}
+
+/// In the order they're picked when they'd come out the same width
+const CODE_SETS: [CodeSet; 3] = [CodeSet::C, CodeSet::B, CodeSet::A];
+
+impl CodeSet {
+    fn escape(&self) -> &'static [u8] {
+        match self {
+            CodeSet::A => b"{A",
+            CodeSet::B => b"{B",
+            CodeSet::C => b"{C",
+        }
+    }
+
+    /// Whether `byte` is in the code set, leaving out FNC4
+    fn has(&self, byte: u8) -> bool {
+        match self {
+            CodeSet::A => byte < 96,
+            CodeSet::B => (32..128).contains(&byte),
+            CodeSet::C => false,
+        }
+    }
+
+    /// The other code set a single character can be shifted into
+    fn shifted(&self) -> Option<CodeSet> {
+        match self {
+            CodeSet::A => Some(CodeSet::B),
+            CodeSet::B => Some(CodeSet::A),
+            CodeSet::C => None,
+        }
+    }
+}
+
+/// How one step of the encoding is done
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Step {
+    /// The next character, or pair of digits in C, in the current code set
+    Direct,
+    /// The next character shifted into the other of A and B
+    Shift,
+    /// Switch to another code set first
+    Switch(CodeSet),
+}
+
+/// Reads Code128 text, where `{1` to `{4` stand for FNC1 to FNC4 and `{{`
+/// for a `{`. Characters from U+0080 to U+00FF are encoded with FNC4.
+pub fn parse(code: &str) -> Result<Vec<Code128Char>, BarcodeError> {
+    let invalid = |character, position| BarcodeError::InvalidCharacter {
+        kind: BarcodeType::Code128,
+        character,
+        position,
+    };
+    let mut chars = Vec::with_capacity(code.len());
+    let mut iter = code.chars().enumerate();
+    while let Some((position, c)) = iter.next() {
+        if c != '{' {
+            let byte = u8::try_from(u32::from(c)).map_err(|_| invalid(c, position))?;
+            chars.push(Code128Char::Char(byte));
+            continue;
+        }
+        chars.push(match iter.next() {
+            Some((_, '{')) => Code128Char::Char(b'{'),
+            Some((_, '1')) => Code128Char::Fnc1,
+            Some((_, '2')) => Code128Char::Fnc2,
+            Some((_, '3')) => Code128Char::Fnc3,
+            Some((_, '4')) => Code128Char::Fnc4,
+            _ => return Err(invalid('{', position)),
+        });
+    }
+    if chars.is_empty() {
+        return Err(BarcodeError::Empty);
+    }
+    Ok(chars)
+}
+
+/// Length of the data for the application identifiers that have a fixed
+/// one, by their first two digits, as listed in the GS1 General
+/// Specifications. These are never followed by an FNC1 separator.
+fn gs1_fixed_length(ai: &str) -> Option<usize> {
+    match &ai[..2] {
+        "00" => Some(18),
+        "01" | "02" | "03" => Some(14),
+        "04" => Some(16),
+        "11" | "12" | "13" | "14" | "15" | "16" | "17" | "18" | "19" => Some(6),
+        "20" => Some(2),
+        "31" | "32" | "33" | "34" | "35" | "36" => Some(6),
+        "41" => Some(13),
+        _ => None,
+    }
+}
+
+/// Reads GS1-128 element strings written the way they're printed under the
+/// barcode, each application identifier in brackets followed by its data,
+/// e.g. `(01)09501101530003(17)250101`. The result starts with FNC1 and has
+/// another after each variable length field that isn't the last.
+pub fn gs1(text: &str) -> Result<Vec<Code128Char>, BarcodeError> {
+    let error = |ai: &str, message| BarcodeError::Gs1 {
+        ai: ai.to_string(),
+        message,
+    };
+    if text.is_empty() {
+        return Err(BarcodeError::Empty);
+    }
+    let mut chars = vec![Code128Char::Fnc1];
+    let mut rest = text;
+    while !rest.is_empty() {
+        let inner = rest
+            .strip_prefix('(')
+            .ok_or_else(|| error("", "expected ( before an application identifier"))?;
+        let (ai, after) = inner
+            .split_once(')')
+            .ok_or_else(|| error("", "missing ) after an application identifier"))?;
+        if !(2..=4).contains(&ai.len()) || !ai.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(error(ai, "must be 2 to 4 digits"));
+        }
+        let end = after.find('(').unwrap_or(after.len());
+        let (data, next) = after.split_at(end);
+        if data.is_empty() {
+            return Err(error(ai, "has no data"));
+        }
+        if !data.bytes().all(|b| b.is_ascii_graphic()) {
+            return Err(error(ai, "data can only be printable ASCII"));
+        }
+        let fixed = gs1_fixed_length(ai);
+        if fixed.is_some_and(|length| length != data.len()) {
+            return Err(error(ai, "data is the wrong length"));
+        }
+        chars.extend(ai.bytes().chain(data.bytes()).map(Code128Char::Char));
+        if fixed.is_none() && !next.is_empty() {
+            chars.push(Code128Char::Fnc1);
+        }
+        rest = next;
+    }
+    Ok(chars)
+}
+
+/// Number of symbol characters it takes to encode `chars[i]` in `set`, and
+/// how many characters that uses up, `None` if it can't be
+fn direct(chars: &[Code128Char], i: usize, set: CodeSet) -> Option<(usize, usize)> {
+    let is_digit = |n: usize| matches!(chars.get(n), Some(Code128Char::Char(b'0'..=b'9')));
+    match (set, chars[i]) {
+        (CodeSet::C, Code128Char::Fnc1) => Some((1, 1)),
+        (CodeSet::C, _) if is_digit(i) && is_digit(i + 1) => Some((1, 2)),
+        (CodeSet::C, _) => None,
+        (_, Code128Char::Char(byte)) if set.has(byte) => Some((1, 1)),
+        // Extended characters take an FNC4 first
+        (_, Code128Char::Char(byte)) if byte >= 128 && set.has(byte - 128) => Some((2, 1)),
+        (_, Code128Char::Char(_)) => None,
+        _ => Some((1, 1)),
+    }
+}
+
+/// Encodes `chars` with the fewest symbol characters, as GS k function B
+/// data for Code128
+pub fn encode(chars: &[Code128Char]) -> Result<Vec<u8>, BarcodeError> {
+    if chars.is_empty() {
+        return Err(BarcodeError::Empty);
+    }
+    let n = chars.len();
+    let index = |set: CodeSet| set as usize;
+    // cost[i][set] is the fewest symbol characters for chars[i..] starting
+    // out in `set`, and step[i][set] how to get it
+    let mut cost = vec![[usize::MAX; 3]; n + 1];
+    let mut step = vec![[Step::Direct; 3]; n + 1];
+    cost[n] = [0; 3];
+    for i in (0..n).rev() {
+        for set in CODE_SETS {
+            if let Some((symbols, used)) = direct(chars, i, set) {
+                cost[i][index(set)] = symbols + cost[i + used][index(set)];
+            }
+            let shifted = match (set.shifted(), chars[i]) {
+                (Some(other), Code128Char::Char(byte)) if other.has(byte) => {
+                    Some(2 + cost[i + 1][index(set)])
+                }
+                _ => None,
+            };
+            if let Some(shifted) = shifted.filter(|&c| c < cost[i][index(set)]) {
+                cost[i][index(set)] = shifted;
+                step[i][index(set)] = Step::Shift;
+            }
+        }
+        // Switching twice in a row never helps, so one pass is enough
+        let stay = cost[i];
+        for set in CODE_SETS {
+            for other in CODE_SETS {
+                let switched = stay[index(other)].saturating_add(1);
+                if switched < cost[i][index(set)] {
+                    cost[i][index(set)] = switched;
+                    step[i][index(set)] = Step::Switch(other);
+                }
+            }
+        }
+    }
+
+    // The start character picks the first code set for free, so the
+    // switches worked out for the first character don't count
+    let mut set = CODE_SETS
+        .into_iter()
+        .min_by_key(|&set| match step[0][index(set)] {
+            Step::Switch(_) => usize::MAX,
+            _ => cost[0][index(set)],
+        })
+        .unwrap_or(CodeSet::B);
+    let mut data = set.escape().to_vec();
+    let mut i = 0;
+    while i < n {
+        match step[i][index(set)] {
+            Step::Switch(other) => {
+                data.extend_from_slice(other.escape());
+                set = other;
+            }
+            Step::Shift => {
+                data.extend_from_slice(b"{S");
+                push_char(&mut data, chars[i]);
+                i += 1;
+            }
+            Step::Direct => match (set, chars[i]) {
+                (CodeSet::C, Code128Char::Char(tens)) => {
+                    let units = match chars[i + 1] {
+                        Code128Char::Char(units) => units,
+                        _ => unreachable!("Code Set C only takes pairs of digits"),
+                    };
+                    data.push((tens - b'0') * 10 + (units - b'0'));
+                    i += 2;
+                }
+                (_, c) => {
+                    push_char(&mut data, c);
+                    i += 1;
+                }
+            },
+        }
+    }
+    Ok(data)
+}
+
+/// Adds a character in code set A or B, escaping it the ESC/POS way
+fn push_char(data: &mut Vec<u8>, c: Code128Char) {
+    match c {
+        Code128Char::Char(byte) => {
+            let byte = if byte >= 128 {
+                data.extend_from_slice(b"{4");
+                byte - 128
+            } else {
+                byte
+            };
+            if byte == b'{' {
+                data.push(b'{');
+            }
+            data.push(byte);
+        }
+        Code128Char::Fnc1 => data.extend_from_slice(b"{1"),
+        Code128Char::Fnc2 => data.extend_from_slice(b"{2"),
+        Code128Char::Fnc3 => data.extend_from_slice(b"{3"),
+        Code128Char::Fnc4 => data.extend_from_slice(b"{4"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded(code: &str) -> Vec<u8> {
+        encode(&parse(code).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn code_sets() {
+        assert_eq!(encoded("1234"), b"{C\x0c\x22");
+        assert_eq!(encoded("Ab1"), b"{BAb1");
+        // Odd length numbers use C for all but one digit
+        assert_eq!(encoded("12345"), b"{C\x0c\x22{B5");
+        // Too few digits to be worth switching for
+        assert_eq!(encoded("AB12CD"), b"{BAB12CD");
+        assert_eq!(encoded("\tTAB"), b"{A\tTAB");
+        assert_eq!(encoded("\t\tA\nb\t"), b"{A\t\tA\n{Sb\t");
+        assert_eq!(encoded("{{x}"), b"{B{{x}");
+        assert_eq!(encoded("é"), b"{B{4i");
+    }
+
+    #[test]
+    fn function_characters() {
+        assert_eq!(encoded("{1123456"), b"{C{1\x0c\x22\x38");
+        assert_eq!(encoded("ab{3{4c"), b"{Bab{3{4c");
+        assert!(parse("ab{x").is_err());
+        assert!(parse("€").is_err());
+        assert_eq!(parse(""), Err(BarcodeError::Empty));
+    }
+
+    #[test]
+    fn gs1_element_strings() {
+        use Code128Char::*;
+        assert_eq!(
+            gs1("(10)A1(20)01").unwrap(),
+            vec![
+                Fnc1,
+                Char(b'1'),
+                Char(b'0'),
+                Char(b'A'),
+                Char(b'1'),
+                Fnc1,
+                Char(b'2'),
+                Char(b'0'),
+                Char(b'0'),
+                Char(b'1'),
+            ]
+        );
+        assert!(gs1("(01)123").is_err());
+        assert!(gs1("01)123").is_err());
+        assert!(gs1("(1)123").is_err());
+        assert!(gs1("(10)").is_err());
+    }
+}
@@ -0,0 +1,642 @@
+//! Options for the 2D symbols the printer draws itself
+//!
+//! The printer works out the modules of a 2D symbol from the data it's sent,
+//! so nothing has to be rendered on this end. Most printers take the
+//! symbology functions of GS ( k, some older ones only ESC Z;
+//! [crate::profile::Profile::code2d] says which a printer wants and
+//! [crate::printer::Printer::qrcode] sends the right one. PDF417,
+//! DataMatrix, MaxiCode and Aztec symbols are only printed with GS ( k.
+//!
+//! # Example
+//! ```rust
+//! use posify::code2d::{DataMatrixOptions, Pdf417Options, QrErrorCorrection, QrOptions};
+//! use posify::document::Document;
+//! use posify::printer::SupportedPrinters;
+//!
+//! let options = QrOptions::default()
+//!     .module_size(6)
+//!     .error_correction(QrErrorCorrection::Q);
+//! let mut doc = Document::new(SupportedPrinters::P3);
+//! doc.qrcode("https://example.com/receipt/1234", &options).unwrap();
+//!
+//! let boarding_pass = Pdf417Options::default().columns(6).module_width(2);
+//! doc.pdf417("M1DOE/JANE            EABC123 LHRJFKBA 0117 123Y012A0001 100", &boarding_pass)
+//!     .unwrap()
+//!     .datamatrix("01095011015300031725010110AB12", &DataMatrixOptions::default())
+//!     .unwrap();
+//! ```
+
+use std::str::FromStr;
+
+use encoding::types::EncoderTrap;
+
+use crate::cjk::CjkEncoding;
+use crate::printer::Error;
+
+/// How a printer is told to print 2D symbols
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Code2dCommand {
+    /// GS ( k, one function to set up each option and then store and print
+    /// the data
+    GsParenK,
+    /// GS Z to pick the symbology and ESC Z with the options and data
+    EscZ,
+}
+
+/// GS ( k <fn 165> - QR Code model
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum QrModel {
+    Model1,
+    #[default]
+    Model2,
+    Micro,
+}
+
+impl QrModel {
+    /// The value of `n1` in function 165
+    pub fn value(&self) -> u8 {
+        match self {
+            QrModel::Model1 => 49,
+            QrModel::Model2 => 50,
+            QrModel::Micro => 51,
+        }
+    }
+}
+
+impl FromStr for QrModel {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().replace(['-', '_', ' '], "").as_ref() {
+            "1" | "MODEL1" => Ok(QrModel::Model1),
+            "2" | "MODEL2" => Ok(QrModel::Model2),
+            "MICRO" => Ok(QrModel::Micro),
+            _ => Err(Error::InvalidArgument),
+        }
+    }
+}
+
+/// GS ( k <fn 169> - QR Code error correction level, from about 7% of the
+/// symbol recoverable for L to 30% for H
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum QrErrorCorrection {
+    L,
+    #[default]
+    M,
+    Q,
+    H,
+}
+
+impl QrErrorCorrection {
+    /// The value of `n` in function 169
+    pub fn value(&self) -> u8 {
+        match self {
+            QrErrorCorrection::L => 48,
+            QrErrorCorrection::M => 49,
+            QrErrorCorrection::Q => 50,
+            QrErrorCorrection::H => 51,
+        }
+    }
+
+    /// The level letter ESC Z takes
+    fn letter(&self) -> u8 {
+        match self {
+            QrErrorCorrection::L => b'L',
+            QrErrorCorrection::M => b'M',
+            QrErrorCorrection::Q => b'Q',
+            QrErrorCorrection::H => b'H',
+        }
+    }
+}
+
+impl FromStr for QrErrorCorrection {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_ref() {
+            "L" => Ok(QrErrorCorrection::L),
+            "M" => Ok(QrErrorCorrection::M),
+            "Q" => Ok(QrErrorCorrection::Q),
+            "H" => Ok(QrErrorCorrection::H),
+            _ => Err(Error::InvalidArgument),
+        }
+    }
+}
+
+/// How the text of a QR Code is sent. The printer picks the QR encoding
+/// mode from the bytes it gets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum QrMode {
+    /// The text as UTF-8, stored in byte mode
+    #[default]
+    Byte,
+    /// The text in Shift JIS, so Japanese characters are stored in the more
+    /// compact kanji mode
+    Kanji,
+}
+
+impl FromStr for QrMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_ref() {
+            "BYTE" => Ok(QrMode::Byte),
+            "KANJI" => Ok(QrMode::Kanji),
+            _ => Err(Error::InvalidArgument),
+        }
+    }
+}
+
+/// Most a QR Code can hold, 7089 digits in numeric mode
+pub const QR_MAX_DATA: usize = 7089;
+/// Most digits, upper case letters, spaces and `$%*+-./:` a QR Code can hold
+pub const QR_MAX_ALPHANUMERIC: usize = 4296;
+/// Most bytes of any other text a QR Code can hold
+pub const QR_MAX_BYTES: usize = 2953;
+/// Most kanji a QR Code can hold
+pub const QR_MAX_KANJI: usize = 1817;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QrOptions {
+    pub model: QrModel,
+    /// Width of a module in dots, 1-16
+    pub module_size: u8,
+    pub error_correction: QrErrorCorrection,
+    pub mode: QrMode,
+    /// Symbol version for ESC Z, 1-40. `None` sends version 3, which ESC Z
+    /// printers have always been sent. GS ( k always picks the version
+    /// itself, so printers that take it fail with [Error::Unsupported] if
+    /// one is given.
+    pub version: Option<u8>,
+}
+
+impl Default for QrOptions {
+    fn default() -> Self {
+        QrOptions {
+            model: QrModel::Model2,
+            module_size: 3,
+            error_correction: QrErrorCorrection::M,
+            mode: QrMode::Byte,
+            version: None,
+        }
+    }
+}
+
+impl QrOptions {
+    pub fn model(mut self, model: QrModel) -> QrOptions {
+        self.model = model;
+        self
+    }
+
+    pub fn module_size(mut self, module_size: u8) -> QrOptions {
+        self.module_size = module_size;
+        self
+    }
+
+    pub fn error_correction(mut self, error_correction: QrErrorCorrection) -> QrOptions {
+        self.error_correction = error_correction;
+        self
+    }
+
+    pub fn mode(mut self, mode: QrMode) -> QrOptions {
+        self.mode = mode;
+        self
+    }
+
+    pub fn version(mut self, version: Option<u8>) -> QrOptions {
+        self.version = version;
+        self
+    }
+
+    /// `code` as the bytes the printer is sent, checked against the most a
+    /// model 2 QR Code can hold in the mode the printer will pick for it, at
+    /// the lowest error correction level
+    pub(crate) fn data(&self, code: &str) -> Result<Vec<u8>, Error> {
+        if !(1..=16).contains(&self.module_size)
+            || self.version.is_some_and(|v| !(1..=40).contains(&v))
+        {
+            return Err(Error::InvalidArgument);
+        }
+        let data = match self.mode {
+            QrMode::Byte => code.as_bytes().to_vec(),
+            QrMode::Kanji => CjkEncoding::ShiftJis
+                .encoding()
+                .encode(code, EncoderTrap::Strict)
+                .map_err(|_| Error::InvalidArgument)?,
+        };
+        let fits = match self.mode {
+            QrMode::Byte if data.iter().all(u8::is_ascii_digit) => data.len() <= QR_MAX_DATA,
+            QrMode::Byte if data.iter().all(|b| is_qr_alphanumeric(*b)) => {
+                data.len() <= QR_MAX_ALPHANUMERIC
+            }
+            QrMode::Byte => data.len() <= QR_MAX_BYTES,
+            QrMode::Kanji => {
+                // Every character is one or two bytes in Shift JIS. Kanji
+                // take 13 bits and everything else a byte, and a symbol of
+                // only kanji holds about as many bits as one of only bytes.
+                let chars = code.chars().count();
+                let kanji = data.len() - chars;
+                kanji * 13 + (chars - kanji) * 8 <= QR_MAX_BYTES * 8
+            }
+        };
+        if data.is_empty() || !fits {
+            return Err(Error::InvalidArgument);
+        }
+        Ok(data)
+    }
+
+    /// The ESC Z parameters before the data length: version, level and
+    /// module size
+    pub(crate) fn esc_z(&self) -> [u8; 3] {
+        [
+            self.version.unwrap_or(3),
+            self.error_correction.letter(),
+            self.module_size,
+        ]
+    }
+}
+
+/// Whether `byte` is in the QR Code alphanumeric mode character set
+fn is_qr_alphanumeric(byte: u8) -> bool {
+    byte.is_ascii_digit() || byte.is_ascii_uppercase() || b" $%*+-./:".contains(&byte)
+}
+
+/// Checks `code` isn't empty or longer than the most a symbol holds of its
+/// kind of data: `digits` if it is all digits, `text` if every byte passes
+/// `is_text` and `bytes` otherwise
+fn symbol_data(
+    code: &str,
+    digits: usize,
+    (text, is_text): (usize, fn(&u8) -> bool),
+    bytes: usize,
+) -> Result<Vec<u8>, Error> {
+    let data = code.as_bytes();
+    let max = if data.iter().all(u8::is_ascii_digit) {
+        digits
+    } else if data.iter().all(is_text) {
+        text
+    } else {
+        bytes
+    };
+    if data.is_empty() || data.len() > max {
+        return Err(Error::InvalidArgument);
+    }
+    Ok(data.to_vec())
+}
+
+/// Whether `byte` is an upper case letter or a space, the characters packed
+/// tightest in PDF417 text compaction and the Aztec upper mode
+fn is_upper_text(byte: &u8) -> bool {
+    byte.is_ascii_uppercase() || *byte == b' '
+}
+
+/// Most a PDF417 symbol can hold, 2710 digits in numeric compaction
+pub const PDF417_MAX_DATA: usize = 2710;
+/// Most upper case letters and spaces a PDF417 symbol can hold
+pub const PDF417_MAX_TEXT: usize = 1850;
+/// Most bytes of any other text a PDF417 symbol can hold
+pub const PDF417_MAX_BYTES: usize = 1108;
+
+/// GS ( k <fn 069> - PDF417 error correction level
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pdf417ErrorCorrection {
+    /// A fixed level, 0-8
+    Level(u8),
+    /// A share of the data codewords in tens of percent, 1-40
+    Ratio(u8),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pdf417Options {
+    /// Data columns, 1-30, or 0 to fit the paper
+    pub columns: u8,
+    /// Rows, 3-90, or 0 to fit the data
+    pub rows: u8,
+    /// Width of a module in dots, 2-8
+    pub module_width: u8,
+    /// Height of a row in module widths, 2-8
+    pub row_height: u8,
+    pub error_correction: Pdf417ErrorCorrection,
+    /// Leaves off the right row indicators and stop patterns, making the
+    /// symbol narrower
+    pub truncated: bool,
+}
+
+impl Default for Pdf417Options {
+    fn default() -> Self {
+        Pdf417Options {
+            columns: 0,
+            rows: 0,
+            module_width: 3,
+            row_height: 3,
+            error_correction: Pdf417ErrorCorrection::Ratio(1),
+            truncated: false,
+        }
+    }
+}
+
+impl Pdf417Options {
+    pub fn columns(mut self, columns: u8) -> Pdf417Options {
+        self.columns = columns;
+        self
+    }
+
+    pub fn rows(mut self, rows: u8) -> Pdf417Options {
+        self.rows = rows;
+        self
+    }
+
+    pub fn module_width(mut self, module_width: u8) -> Pdf417Options {
+        self.module_width = module_width;
+        self
+    }
+
+    pub fn row_height(mut self, row_height: u8) -> Pdf417Options {
+        self.row_height = row_height;
+        self
+    }
+
+    pub fn error_correction(mut self, error_correction: Pdf417ErrorCorrection) -> Pdf417Options {
+        self.error_correction = error_correction;
+        self
+    }
+
+    pub fn truncated(mut self, truncated: bool) -> Pdf417Options {
+        self.truncated = truncated;
+        self
+    }
+
+    /// The GS ( k functions that set the symbol up, checking each value
+    pub(crate) fn setup(&self) -> Result<Vec<(u8, Vec<u8>)>, Error> {
+        let ecc = match self.error_correction {
+            Pdf417ErrorCorrection::Level(level @ 0..=8) => [0x30, 0x30 + level],
+            Pdf417ErrorCorrection::Ratio(ratio @ 1..=40) => [0x31, ratio],
+            _ => return Err(Error::InvalidArgument),
+        };
+        if self.columns > 30
+            || !(self.rows == 0 || (3..=90).contains(&self.rows))
+            || !(2..=8).contains(&self.module_width)
+            || !(2..=8).contains(&self.row_height)
+        {
+            return Err(Error::InvalidArgument);
+        }
+        Ok(vec![
+            (0x41, vec![self.columns]),
+            (0x42, vec![self.rows]),
+            (0x43, vec![self.module_width]),
+            (0x44, vec![self.row_height]),
+            (0x45, ecc.to_vec()),
+            (0x46, vec![u8::from(self.truncated)]),
+        ])
+    }
+
+    pub(crate) fn data(&self, code: &str) -> Result<Vec<u8>, Error> {
+        symbol_data(
+            code,
+            PDF417_MAX_DATA,
+            (PDF417_MAX_TEXT, is_upper_text),
+            PDF417_MAX_BYTES,
+        )
+    }
+}
+
+/// Most a DataMatrix symbol can hold, 3116 digits
+pub const DATAMATRIX_MAX_DATA: usize = 3116;
+/// Most digits, upper case letters and spaces a DataMatrix symbol can hold
+pub const DATAMATRIX_MAX_ALPHANUMERIC: usize = 2335;
+/// Most bytes of any other text a DataMatrix symbol can hold
+pub const DATAMATRIX_MAX_BYTES: usize = 1556;
+
+/// GS ( k <fn 166> - DataMatrix symbol size
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum DataMatrixSize {
+    /// The smallest square that fits the data
+    #[default]
+    Square,
+    /// A square with this many rows and columns, one of the ECC 200 sizes
+    /// from 10 to 144
+    SquareOf(u8),
+    /// A rectangle of 8 rows by 18 or 32 columns, 12 by 26 or 36 or 16 by 36
+    /// or 48, with as many columns as the data needs if the second value is
+    /// 0
+    Rectangle(u8, u8),
+}
+
+/// Rows and columns of the ECC 200 square symbols
+const DATAMATRIX_SQUARES: &[u8] = &[
+    10, 12, 14, 16, 18, 20, 22, 24, 26, 32, 36, 40, 44, 48, 52, 64, 72, 80, 88, 96, 104, 120, 132,
+    144,
+];
+
+/// Rows and columns of the ECC 200 rectangular symbols
+const DATAMATRIX_RECTANGLES: &[(u8, u8)] =
+    &[(8, 18), (8, 32), (12, 26), (12, 36), (16, 36), (16, 48)];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DataMatrixOptions {
+    pub size: DataMatrixSize,
+    /// Width of a module in dots, 2-16
+    pub module_size: u8,
+}
+
+impl Default for DataMatrixOptions {
+    fn default() -> Self {
+        DataMatrixOptions {
+            size: DataMatrixSize::Square,
+            module_size: 3,
+        }
+    }
+}
+
+impl DataMatrixOptions {
+    pub fn size(mut self, size: DataMatrixSize) -> DataMatrixOptions {
+        self.size = size;
+        self
+    }
+
+    pub fn module_size(mut self, module_size: u8) -> DataMatrixOptions {
+        self.module_size = module_size;
+        self
+    }
+
+    pub(crate) fn setup(&self) -> Result<Vec<(u8, Vec<u8>)>, Error> {
+        let size = match self.size {
+            DataMatrixSize::Square => [0x00, 0x00, 0x00],
+            DataMatrixSize::SquareOf(n) if DATAMATRIX_SQUARES.contains(&n) => [0x00, n, 0x00],
+            DataMatrixSize::Rectangle(rows @ (8 | 12 | 16), 0) => [0x01, rows, 0x00],
+            DataMatrixSize::Rectangle(rows, columns)
+                if DATAMATRIX_RECTANGLES.contains(&(rows, columns)) =>
+            {
+                [0x01, rows, columns]
+            }
+            _ => return Err(Error::InvalidArgument),
+        };
+        if !(2..=16).contains(&self.module_size) {
+            return Err(Error::InvalidArgument);
+        }
+        Ok(vec![(0x42, size.to_vec()), (0x43, vec![self.module_size])])
+    }
+
+    pub(crate) fn data(&self, code: &str) -> Result<Vec<u8>, Error> {
+        symbol_data(
+            code,
+            DATAMATRIX_MAX_DATA,
+            (DATAMATRIX_MAX_ALPHANUMERIC, |b| {
+                b.is_ascii_digit() || is_upper_text(b)
+            }),
+            DATAMATRIX_MAX_BYTES,
+        )
+    }
+}
+
+/// Most a MaxiCode symbol can hold, 138 digits
+pub const MAXICODE_MAX_DATA: usize = 138;
+/// Most characters of any other text a MaxiCode symbol can hold
+pub const MAXICODE_MAX_TEXT: usize = 93;
+
+/// GS ( k <fn 265> - MaxiCode mode
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum MaxiCodeMode {
+    /// Structured carrier message with a numeric postal code, mode 2
+    #[default]
+    NumericPostcode,
+    /// Structured carrier message with an alphanumeric postal code, mode 3
+    AlphanumericPostcode,
+    /// Standard symbol, mode 4
+    Standard,
+    /// Full error correction, mode 5
+    FullErrorCorrection,
+    /// Reader programming, mode 6
+    ReaderProgramming,
+}
+
+impl MaxiCodeMode {
+    /// The value of `n` in function 265
+    pub fn value(&self) -> u8 {
+        match self {
+            MaxiCodeMode::NumericPostcode => 50,
+            MaxiCodeMode::AlphanumericPostcode => 51,
+            MaxiCodeMode::Standard => 52,
+            MaxiCodeMode::FullErrorCorrection => 53,
+            MaxiCodeMode::ReaderProgramming => 54,
+        }
+    }
+}
+
+impl FromStr for MaxiCodeMode {
+    type Err = Error;
+
+    /// Accepts the mode numbers, `2`-`6`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "2" => Ok(MaxiCodeMode::NumericPostcode),
+            "3" => Ok(MaxiCodeMode::AlphanumericPostcode),
+            "4" => Ok(MaxiCodeMode::Standard),
+            "5" => Ok(MaxiCodeMode::FullErrorCorrection),
+            "6" => Ok(MaxiCodeMode::ReaderProgramming),
+            _ => Err(Error::InvalidArgument),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct MaxiCodeOptions {
+    pub mode: MaxiCodeMode,
+}
+
+impl MaxiCodeOptions {
+    pub fn mode(mut self, mode: MaxiCodeMode) -> MaxiCodeOptions {
+        self.mode = mode;
+        self
+    }
+
+    pub(crate) fn setup(&self) -> Result<Vec<(u8, Vec<u8>)>, Error> {
+        Ok(vec![(0x41, vec![self.mode.value()])])
+    }
+
+    pub(crate) fn data(&self, code: &str) -> Result<Vec<u8>, Error> {
+        symbol_data(
+            code,
+            MAXICODE_MAX_DATA,
+            (MAXICODE_MAX_TEXT, |_| true),
+            MAXICODE_MAX_TEXT,
+        )
+    }
+}
+
+/// Most an Aztec symbol can hold, 3832 digits
+pub const AZTEC_MAX_DATA: usize = 3832;
+/// Most upper case letters and spaces an Aztec symbol can hold
+pub const AZTEC_MAX_TEXT: usize = 3067;
+/// Most bytes of any other text an Aztec symbol can hold
+pub const AZTEC_MAX_BYTES: usize = 1914;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AztecOptions {
+    /// Compact symbols have at most 4 layers, full range ones 32
+    pub compact: bool,
+    /// Data layers, 1-4 for compact symbols and 4-32 for full range ones,
+    /// or 0 for as few as fit the data
+    pub layers: u8,
+    /// Width of a module in dots, 2-16
+    pub module_size: u8,
+    /// Share of the symbol given to error correction in percent, 5-95
+    pub error_correction: u8,
+}
+
+impl Default for AztecOptions {
+    fn default() -> Self {
+        AztecOptions {
+            compact: false,
+            layers: 0,
+            module_size: 3,
+            error_correction: 23,
+        }
+    }
+}
+
+impl AztecOptions {
+    pub fn compact(mut self, compact: bool) -> AztecOptions {
+        self.compact = compact;
+        self
+    }
+
+    pub fn layers(mut self, layers: u8) -> AztecOptions {
+        self.layers = layers;
+        self
+    }
+
+    pub fn module_size(mut self, module_size: u8) -> AztecOptions {
+        self.module_size = module_size;
+        self
+    }
+
+    pub fn error_correction(mut self, error_correction: u8) -> AztecOptions {
+        self.error_correction = error_correction;
+        self
+    }
+
+    pub(crate) fn setup(&self) -> Result<Vec<(u8, Vec<u8>)>, Error> {
+        let layers = if self.compact { 1..=4 } else { 4..=32 };
+        if (self.layers != 0 && !layers.contains(&self.layers))
+            || !(2..=16).contains(&self.module_size)
+            || !(5..=95).contains(&self.error_correction)
+        {
+            return Err(Error::InvalidArgument);
+        }
+        Ok(vec![
+            (0x42, vec![u8::from(self.compact), self.layers]),
+            (0x43, vec![self.module_size]),
+            (0x45, vec![self.error_correction]),
+        ])
+    }
+
+    pub(crate) fn data(&self, code: &str) -> Result<Vec<u8>, Error> {
+        symbol_data(
+            code,
+            AZTEC_MAX_DATA,
+            (AZTEC_MAX_TEXT, is_upper_text),
+            AZTEC_MAX_BYTES,
+        )
+    }
+}
@@ -0,0 +1,643 @@
+//! Character code tables selected with ESC t
+//!
+//! Printers that don't understand UTF-8 print text from one of their
+//! built-in code pages. Once a page has been selected with
+//! [crate::printer::Printer::code_page], text is encoded for it and the
+//! printer is switched to another page from its [crate::profile::Profile]
+//! whenever a character isn't in the current one.
+//!
+//! Vendors number their code pages differently, so no profile comes with a
+//! table. Give the printer the one from its manual with
+//! [crate::printer::Printer::set_code_pages], or [EPSON_CODE_PAGES] for
+//! printers that follow Epson's numbering.
+
+use std::collections::HashMap;
+use std::io;
+use std::str::FromStr;
+
+use encoding::all::{
+    IBM866, ISO_8859_15, WINDOWS_1250, WINDOWS_1251, WINDOWS_1252, WINDOWS_1253, WINDOWS_1254,
+    WINDOWS_1257,
+};
+use encoding::codec::singlebyte::SingleByteEncoding;
+use encoding::types::{EncoderTrap, EncodingRef};
+
+use crate::printer::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CodePage {
+    /// USA, Standard Europe
+    PC437,
+    /// Multilingual Latin 1
+    PC850,
+    /// Latin 2
+    PC852,
+    /// PC850 with the Euro sign
+    PC858,
+    /// Portuguese
+    PC860,
+    /// Canadian-French
+    PC863,
+    /// Nordic
+    PC865,
+    /// Cyrillic
+    PC866,
+    /// Windows Central Europe
+    WPC1250,
+    /// Windows Cyrillic
+    WPC1251,
+    /// Windows Latin 1
+    WPC1252,
+    /// Windows Greek
+    WPC1253,
+    /// Windows Turkish
+    WPC1254,
+    /// Windows Baltic
+    WPC1257,
+    /// Latin 9
+    ISO8859_15,
+}
+
+impl CodePage {
+    /// The encoder for text printed with this code page
+    pub fn encoding(&self) -> EncodingRef {
+        match self {
+            CodePage::PC437 => &PC437_ENCODING,
+            CodePage::PC850 => &PC850_ENCODING,
+            CodePage::PC852 => &PC852_ENCODING,
+            CodePage::PC858 => &PC858_ENCODING,
+            CodePage::PC860 => &PC860_ENCODING,
+            CodePage::PC863 => &PC863_ENCODING,
+            CodePage::PC865 => &PC865_ENCODING,
+            CodePage::PC866 => IBM866,
+            CodePage::WPC1250 => WINDOWS_1250,
+            CodePage::WPC1251 => WINDOWS_1251,
+            CodePage::WPC1252 => WINDOWS_1252,
+            CodePage::WPC1253 => WINDOWS_1253,
+            CodePage::WPC1254 => WINDOWS_1254,
+            CodePage::WPC1257 => WINDOWS_1257,
+            CodePage::ISO8859_15 => ISO_8859_15,
+        }
+    }
+
+    /// Returns the byte for `c` in this code page, `None` if it isn't in it
+    pub fn encode_char(&self, c: char) -> Option<u8> {
+        if c.is_ascii() {
+            return Some(c as u8);
+        }
+        let mut buf = [0_u8; 4];
+        match self
+            .encoding()
+            .encode(c.encode_utf8(&mut buf), EncoderTrap::Strict)
+        {
+            Ok(bytes) if bytes.len() == 1 => Some(bytes[0]),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for CodePage {
+    type Err = Error;
+
+    /// Accepts the names used here as well as the `CP` and `WINDOWS-` forms,
+    /// e.g. `PC850`, `CP850`, `WPC1252` or `WINDOWS-1252`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let upper = s.to_uppercase().replace(['-', '_'], "");
+        let number = upper
+            .trim_start_matches("WINDOWS")
+            .trim_start_matches("WPC")
+            .trim_start_matches("CP")
+            .trim_start_matches("PC");
+        match number {
+            "437" => Ok(CodePage::PC437),
+            "850" => Ok(CodePage::PC850),
+            "852" => Ok(CodePage::PC852),
+            "858" => Ok(CodePage::PC858),
+            "860" => Ok(CodePage::PC860),
+            "863" => Ok(CodePage::PC863),
+            "865" => Ok(CodePage::PC865),
+            "866" => Ok(CodePage::PC866),
+            "1250" => Ok(CodePage::WPC1250),
+            "1251" => Ok(CodePage::WPC1251),
+            "1252" => Ok(CodePage::WPC1252),
+            "1253" => Ok(CodePage::WPC1253),
+            "1254" => Ok(CodePage::WPC1254),
+            "1257" => Ok(CodePage::WPC1257),
+            "ISO885915" => Ok(CodePage::ISO8859_15),
+            _ => Err(Error::InvalidArgument),
+        }
+    }
+}
+
+/// ESC t numbers for the code pages in the standard Epson table. Other
+/// vendors number some of these differently or not at all.
+pub const EPSON_CODE_PAGES: &[(CodePage, u8)] = &[
+    (CodePage::PC437, 0),
+    (CodePage::PC850, 2),
+    (CodePage::PC860, 3),
+    (CodePage::PC863, 4),
+    (CodePage::PC865, 5),
+    (CodePage::WPC1252, 16),
+    (CodePage::PC866, 17),
+    (CodePage::PC852, 18),
+    (CodePage::PC858, 19),
+    (CodePage::ISO8859_15, 40),
+    (CodePage::WPC1250, 45),
+    (CodePage::WPC1251, 46),
+    (CodePage::WPC1253, 47),
+    (CodePage::WPC1254, 48),
+    (CodePage::WPC1257, 51),
+];
+
+/// What to do with characters the current code page doesn't have. Each
+/// step is tried in turn until one of them can print the character.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fallback {
+    /// Switch to another of the printer's code pages that has the character
+    pub switch_pages: bool,
+    /// Print a look-alike from the transliteration table, e.g. `"` for `“`
+    pub transliterate: bool,
+    /// Extra transliterations, looked up before the built-in table
+    pub table: HashMap<char, String>,
+    /// Printed in place of anything that's left when the printer's
+    /// `EncoderTrap` is `Replace`
+    pub replacement: String,
+}
+
+impl Default for Fallback {
+    fn default() -> Self {
+        Fallback {
+            switch_pages: true,
+            transliterate: true,
+            table: HashMap::new(),
+            replacement: "?".to_string(),
+        }
+    }
+}
+
+/// Encodes `content` for a printer with the code pages in `pages`.
+///
+/// Characters go out in the `current` page when it has them. Otherwise the
+/// steps in `fallback` are tried, which may select another page with ESC t
+/// and make it the current page. Characters nothing can handle are logged
+/// and dealt with according to `trap`.
+pub(crate) fn encode(
+    content: &str,
+    pages: &[(CodePage, u8)],
+    current: &mut CodePage,
+    fallback: &Fallback,
+    trap: EncoderTrap,
+) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::with_capacity(content.len());
+    for c in content.chars() {
+        if encode_char(c, pages, current, fallback, &mut out) {
+            continue;
+        }
+        if is_invisible(c) {
+            continue;
+        }
+        if fallback.transliterate {
+            let replacement = fallback
+                .table
+                .get(&c)
+                .map(String::as_str)
+                .or_else(|| transliterate(c));
+            if let Some(replacement) = replacement {
+                let mark = out.len();
+                let saved = *current;
+                if replacement
+                    .chars()
+                    .all(|r| encode_char(r, pages, current, fallback, &mut out))
+                {
+                    continue;
+                }
+                // Any page switch went out with the bytes
+                out.truncate(mark);
+                *current = saved;
+            }
+        }
+        log::warn!("No code page has {:?} (U+{:04X})", c, c as u32);
+        match trap {
+            EncoderTrap::Strict => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Unrepresentable character {:?}", c),
+                )
+                .into())
+            }
+            EncoderTrap::Ignore => (),
+            EncoderTrap::NcrEscape => out.extend(format!("&#{};", c as u32).bytes()),
+            _ => {
+                for r in fallback.replacement.chars() {
+                    encode_char(r, pages, current, fallback, &mut out);
+                }
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Appends `c` in the current code page, or in the first other page that has
+/// it when `fallback` allows switching. Returns false if neither could.
+fn encode_char(
+    c: char,
+    pages: &[(CodePage, u8)],
+    current: &mut CodePage,
+    fallback: &Fallback,
+    out: &mut Vec<u8>,
+) -> bool {
+    if let Some(b) = current.encode_char(c) {
+        out.push(b);
+        return true;
+    }
+    if !fallback.switch_pages {
+        return false;
+    }
+    let found = pages
+        .iter()
+        .find_map(|(page, n)| page.encode_char(c).map(|b| (*page, *n, b)));
+    match found {
+        Some((page, n, b)) => {
+            out.extend_from_slice(&[0x1b, 0x74, n, b]);
+            *current = page;
+            true
+        }
+        None => false,
+    }
+}
+
+/// Characters that only change how their neighbours look: combining accents,
+/// zero width joiners, variation selectors and emoji skin tones. Dropping
+/// them leaves the base character to be printed on its own.
+fn is_invisible(c: char) -> bool {
+    matches!(c,
+        '\u{0300}'..='\u{036f}'
+        | '\u{200b}'..='\u{200f}'
+        | '\u{fe00}'..='\u{fe0f}'
+        | '\u{1f3fb}'..='\u{1f3ff}'
+    )
+}
+
+/// Built-in table of plain ASCII stand-ins for characters that are often
+/// missing from code pages
+pub fn transliterate(c: char) -> Option<&'static str> {
+    let s = match c {
+        '\u{2018}' | '\u{2019}' | '\u{201a}' | '\u{201b}' | '\u{2032}' | '\u{00b4}' => "'",
+        '\u{201c}' | '\u{201d}' | '\u{201e}' | '\u{201f}' | '\u{2033}' | '\u{00ab}'
+        | '\u{00bb}' => "\"",
+        '\u{2039}' => "<",
+        '\u{203a}' => ">",
+        '\u{2010}'..='\u{2015}' | '\u{2212}' => "-",
+        '\u{2026}' => "...",
+        '\u{2022}' | '\u{00b7}' => "*",
+        '\u{00a0}' | '\u{2002}'..='\u{200a}' | '\u{202f}' => " ",
+        '\u{20ac}' => "EUR",
+        '\u{00a3}' => "GBP",
+        '\u{00a5}' => "JPY",
+        '\u{20bd}' => "RUB",
+        '\u{20b9}' => "INR",
+        '\u{2122}' => "TM",
+        '\u{00a9}' => "(C)",
+        '\u{00ae}' => "(R)",
+        '\u{00b0}' => "o",
+        '\u{00d7}' => "x",
+        '\u{00f7}' => "/",
+        '\u{00bd}' => "1/2",
+        '\u{00bc}' => "1/4",
+        '\u{00be}' => "3/4",
+        '\u{00df}' => "ss",
+        '\u{1e9e}' => "SS",
+        '\u{00c6}' => "AE",
+        '\u{00e6}' => "ae",
+        '\u{0152}' => "OE",
+        '\u{0153}' => "oe",
+        '\u{00de}' => "TH",
+        '\u{00fe}' => "th",
+        '\u{00c0}'..='\u{00c5}' | '\u{0100}' | '\u{0102}' | '\u{0104}' => "A",
+        '\u{00e0}'..='\u{00e5}' | '\u{0101}' | '\u{0103}' | '\u{0105}' => "a",
+        '\u{00c7}' | '\u{0106}' | '\u{0108}' | '\u{010a}' | '\u{010c}' => "C",
+        '\u{00e7}' | '\u{0107}' | '\u{0109}' | '\u{010b}' | '\u{010d}' => "c",
+        '\u{00d0}' | '\u{010e}' | '\u{0110}' => "D",
+        '\u{00f0}' | '\u{010f}' | '\u{0111}' => "d",
+        '\u{00c8}'..='\u{00cb}' | '\u{0112}' | '\u{0116}' | '\u{0118}' | '\u{011a}' => "E",
+        '\u{00e8}'..='\u{00eb}' | '\u{0113}' | '\u{0117}' | '\u{0119}' | '\u{011b}' => "e",
+        '\u{011e}' | '\u{0122}' => "G",
+        '\u{011f}' | '\u{0123}' => "g",
+        '\u{00cc}'..='\u{00cf}' | '\u{012a}' | '\u{012e}' | '\u{0130}' => "I",
+        '\u{00ec}'..='\u{00ef}' | '\u{012b}' | '\u{012f}' | '\u{0131}' => "i",
+        '\u{0136}' => "K",
+        '\u{0137}' => "k",
+        '\u{0139}' | '\u{013b}' | '\u{013d}' | '\u{0141}' => "L",
+        '\u{013a}' | '\u{013c}' | '\u{013e}' | '\u{0142}' => "l",
+        '\u{00d1}' | '\u{0143}' | '\u{0145}' | '\u{0147}' => "N",
+        '\u{00f1}' | '\u{0144}' | '\u{0146}' | '\u{0148}' => "n",
+        '\u{00d2}'..='\u{00d6}' | '\u{00d8}' | '\u{014c}' | '\u{0150}' => "O",
+        '\u{00f2}'..='\u{00f6}' | '\u{00f8}' | '\u{014d}' | '\u{0151}' => "o",
+        '\u{0154}' | '\u{0158}' => "R",
+        '\u{0155}' | '\u{0159}' => "r",
+        '\u{015a}' | '\u{015e}' | '\u{0160}' | '\u{0218}' => "S",
+        '\u{015b}' | '\u{015f}' | '\u{0161}' | '\u{0219}' => "s",
+        '\u{0162}' | '\u{0164}' | '\u{021a}' => "T",
+        '\u{0163}' | '\u{0165}' | '\u{021b}' => "t",
+        '\u{00d9}'..='\u{00dc}' | '\u{016a}' | '\u{016e}' | '\u{0170}' | '\u{0172}' => "U",
+        '\u{00f9}'..='\u{00fc}' | '\u{016b}' | '\u{016f}' | '\u{0171}' | '\u{0173}' => "u",
+        '\u{00dd}' | '\u{0178}' => "Y",
+        '\u{00fd}' | '\u{00ff}' => "y",
+        '\u{0179}' | '\u{017b}' | '\u{017d}' => "Z",
+        '\u{017a}' | '\u{017c}' | '\u{017e}' => "z",
+        _ => return None,
+    };
+    Some(s)
+}
+
+macro_rules! single_byte_encoding {
+    ($encoding:ident, $name:expr, $table:ident, $forward:ident, $backward:ident) => {
+        fn $forward(code: u8) -> u16 {
+            $table[code as usize] as u16
+        }
+
+        fn $backward(code: u32) -> u8 {
+            match $table.iter().position(|&c| c as u32 == code) {
+                Some(i) => 0x80 + i as u8,
+                None => 0,
+            }
+        }
+
+        static $encoding: SingleByteEncoding = SingleByteEncoding {
+            name: $name,
+            whatwg_name: None,
+            index_forward: $forward,
+            index_backward: $backward,
+        };
+    };
+}
+
+single_byte_encoding!(
+    PC437_ENCODING,
+    "cp437",
+    CP437,
+    cp437_forward,
+    cp437_backward
+);
+single_byte_encoding!(
+    PC850_ENCODING,
+    "cp850",
+    CP850,
+    cp850_forward,
+    cp850_backward
+);
+single_byte_encoding!(
+    PC852_ENCODING,
+    "cp852",
+    CP852,
+    cp852_forward,
+    cp852_backward
+);
+single_byte_encoding!(
+    PC858_ENCODING,
+    "cp858",
+    CP858,
+    cp858_forward,
+    cp858_backward
+);
+single_byte_encoding!(
+    PC860_ENCODING,
+    "cp860",
+    CP860,
+    cp860_forward,
+    cp860_backward
+);
+single_byte_encoding!(
+    PC863_ENCODING,
+    "cp863",
+    CP863,
+    cp863_forward,
+    cp863_backward
+);
+single_byte_encoding!(
+    PC865_ENCODING,
+    "cp865",
+    CP865,
+    cp865_forward,
+    cp865_backward
+);
+
+// Upper halves (0x80-0xFF) of the DOS code pages the encoding crate doesn't
+// have
+
+const CP437: [char; 128] = [
+    '\u{00c7}', '\u{00fc}', '\u{00e9}', '\u{00e2}', '\u{00e4}', '\u{00e0}', '\u{00e5}', '\u{00e7}',
+    '\u{00ea}', '\u{00eb}', '\u{00e8}', '\u{00ef}', '\u{00ee}', '\u{00ec}', '\u{00c4}', '\u{00c5}',
+    '\u{00c9}', '\u{00e6}', '\u{00c6}', '\u{00f4}', '\u{00f6}', '\u{00f2}', '\u{00fb}', '\u{00f9}',
+    '\u{00ff}', '\u{00d6}', '\u{00dc}', '\u{00a2}', '\u{00a3}', '\u{00a5}', '\u{20a7}', '\u{0192}',
+    '\u{00e1}', '\u{00ed}', '\u{00f3}', '\u{00fa}', '\u{00f1}', '\u{00d1}', '\u{00aa}', '\u{00ba}',
+    '\u{00bf}', '\u{2310}', '\u{00ac}', '\u{00bd}', '\u{00bc}', '\u{00a1}', '\u{00ab}', '\u{00bb}',
+    '\u{2591}', '\u{2592}', '\u{2593}', '\u{2502}', '\u{2524}', '\u{2561}', '\u{2562}', '\u{2556}',
+    '\u{2555}', '\u{2563}', '\u{2551}', '\u{2557}', '\u{255d}', '\u{255c}', '\u{255b}', '\u{2510}',
+    '\u{2514}', '\u{2534}', '\u{252c}', '\u{251c}', '\u{2500}', '\u{253c}', '\u{255e}', '\u{255f}',
+    '\u{255a}', '\u{2554}', '\u{2569}', '\u{2566}', '\u{2560}', '\u{2550}', '\u{256c}', '\u{2567}',
+    '\u{2568}', '\u{2564}', '\u{2565}', '\u{2559}', '\u{2558}', '\u{2552}', '\u{2553}', '\u{256b}',
+    '\u{256a}', '\u{2518}', '\u{250c}', '\u{2588}', '\u{2584}', '\u{258c}', '\u{2590}', '\u{2580}',
+    '\u{03b1}', '\u{00df}', '\u{0393}', '\u{03c0}', '\u{03a3}', '\u{03c3}', '\u{00b5}', '\u{03c4}',
+    '\u{03a6}', '\u{0398}', '\u{03a9}', '\u{03b4}', '\u{221e}', '\u{03c6}', '\u{03b5}', '\u{2229}',
+    '\u{2261}', '\u{00b1}', '\u{2265}', '\u{2264}', '\u{2320}', '\u{2321}', '\u{00f7}', '\u{2248}',
+    '\u{00b0}', '\u{2219}', '\u{00b7}', '\u{221a}', '\u{207f}', '\u{00b2}', '\u{25a0}', '\u{00a0}',
+];
+
+const CP850: [char; 128] = [
+    '\u{00c7}', '\u{00fc}', '\u{00e9}', '\u{00e2}', '\u{00e4}', '\u{00e0}', '\u{00e5}', '\u{00e7}',
+    '\u{00ea}', '\u{00eb}', '\u{00e8}', '\u{00ef}', '\u{00ee}', '\u{00ec}', '\u{00c4}', '\u{00c5}',
+    '\u{00c9}', '\u{00e6}', '\u{00c6}', '\u{00f4}', '\u{00f6}', '\u{00f2}', '\u{00fb}', '\u{00f9}',
+    '\u{00ff}', '\u{00d6}', '\u{00dc}', '\u{00f8}', '\u{00a3}', '\u{00d8}', '\u{00d7}', '\u{0192}',
+    '\u{00e1}', '\u{00ed}', '\u{00f3}', '\u{00fa}', '\u{00f1}', '\u{00d1}', '\u{00aa}', '\u{00ba}',
+    '\u{00bf}', '\u{00ae}', '\u{00ac}', '\u{00bd}', '\u{00bc}', '\u{00a1}', '\u{00ab}', '\u{00bb}',
+    '\u{2591}', '\u{2592}', '\u{2593}', '\u{2502}', '\u{2524}', '\u{00c1}', '\u{00c2}', '\u{00c0}',
+    '\u{00a9}', '\u{2563}', '\u{2551}', '\u{2557}', '\u{255d}', '\u{00a2}', '\u{00a5}', '\u{2510}',
+    '\u{2514}', '\u{2534}', '\u{252c}', '\u{251c}', '\u{2500}', '\u{253c}', '\u{00e3}', '\u{00c3}',
+    '\u{255a}', '\u{2554}', '\u{2569}', '\u{2566}', '\u{2560}', '\u{2550}', '\u{256c}', '\u{00a4}',
+    '\u{00f0}', '\u{00d0}', '\u{00ca}', '\u{00cb}', '\u{00c8}', '\u{0131}', '\u{00cd}', '\u{00ce}',
+    '\u{00cf}', '\u{2518}', '\u{250c}', '\u{2588}', '\u{2584}', '\u{00a6}', '\u{00cc}', '\u{2580}',
+    '\u{00d3}', '\u{00df}', '\u{00d4}', '\u{00d2}', '\u{00f5}', '\u{00d5}', '\u{00b5}', '\u{00fe}',
+    '\u{00de}', '\u{00da}', '\u{00db}', '\u{00d9}', '\u{00fd}', '\u{00dd}', '\u{00af}', '\u{00b4}',
+    '\u{00ad}', '\u{00b1}', '\u{2017}', '\u{00be}', '\u{00b6}', '\u{00a7}', '\u{00f7}', '\u{00b8}',
+    '\u{00b0}', '\u{00a8}', '\u{00b7}', '\u{00b9}', '\u{00b3}', '\u{00b2}', '\u{25a0}', '\u{00a0}',
+];
+
+const CP852: [char; 128] = [
+    '\u{00c7}', '\u{00fc}', '\u{00e9}', '\u{00e2}', '\u{00e4}', '\u{016f}', '\u{0107}', '\u{00e7}',
+    '\u{0142}', '\u{00eb}', '\u{0150}', '\u{0151}', '\u{00ee}', '\u{0179}', '\u{00c4}', '\u{0106}',
+    '\u{00c9}', '\u{0139}', '\u{013a}', '\u{00f4}', '\u{00f6}', '\u{013d}', '\u{013e}', '\u{015a}',
+    '\u{015b}', '\u{00d6}', '\u{00dc}', '\u{0164}', '\u{0165}', '\u{0141}', '\u{00d7}', '\u{010d}',
+    '\u{00e1}', '\u{00ed}', '\u{00f3}', '\u{00fa}', '\u{0104}', '\u{0105}', '\u{017d}', '\u{017e}',
+    '\u{0118}', '\u{0119}', '\u{00ac}', '\u{017a}', '\u{010c}', '\u{015f}', '\u{00ab}', '\u{00bb}',
+    '\u{2591}', '\u{2592}', '\u{2593}', '\u{2502}', '\u{2524}', '\u{00c1}', '\u{00c2}', '\u{011a}',
+    '\u{015e}', '\u{2563}', '\u{2551}', '\u{2557}', '\u{255d}', '\u{017b}', '\u{017c}', '\u{2510}',
+    '\u{2514}', '\u{2534}', '\u{252c}', '\u{251c}', '\u{2500}', '\u{253c}', '\u{0102}', '\u{0103}',
+    '\u{255a}', '\u{2554}', '\u{2569}', '\u{2566}', '\u{2560}', '\u{2550}', '\u{256c}', '\u{00a4}',
+    '\u{0111}', '\u{0110}', '\u{010e}', '\u{00cb}', '\u{010f}', '\u{0147}', '\u{00cd}', '\u{00ce}',
+    '\u{011b}', '\u{2518}', '\u{250c}', '\u{2588}', '\u{2584}', '\u{0162}', '\u{016e}', '\u{2580}',
+    '\u{00d3}', '\u{00df}', '\u{00d4}', '\u{0143}', '\u{0144}', '\u{0148}', '\u{0160}', '\u{0161}',
+    '\u{0154}', '\u{00da}', '\u{0155}', '\u{0170}', '\u{00fd}', '\u{00dd}', '\u{0163}', '\u{00b4}',
+    '\u{00ad}', '\u{02dd}', '\u{02db}', '\u{02c7}', '\u{02d8}', '\u{00a7}', '\u{00f7}', '\u{00b8}',
+    '\u{00b0}', '\u{00a8}', '\u{02d9}', '\u{0171}', '\u{0158}', '\u{0159}', '\u{25a0}', '\u{00a0}',
+];
+
+const CP858: [char; 128] = [
+    '\u{00c7}', '\u{00fc}', '\u{00e9}', '\u{00e2}', '\u{00e4}', '\u{00e0}', '\u{00e5}', '\u{00e7}',
+    '\u{00ea}', '\u{00eb}', '\u{00e8}', '\u{00ef}', '\u{00ee}', '\u{00ec}', '\u{00c4}', '\u{00c5}',
+    '\u{00c9}', '\u{00e6}', '\u{00c6}', '\u{00f4}', '\u{00f6}', '\u{00f2}', '\u{00fb}', '\u{00f9}',
+    '\u{00ff}', '\u{00d6}', '\u{00dc}', '\u{00f8}', '\u{00a3}', '\u{00d8}', '\u{00d7}', '\u{0192}',
+    '\u{00e1}', '\u{00ed}', '\u{00f3}', '\u{00fa}', '\u{00f1}', '\u{00d1}', '\u{00aa}', '\u{00ba}',
+    '\u{00bf}', '\u{00ae}', '\u{00ac}', '\u{00bd}', '\u{00bc}', '\u{00a1}', '\u{00ab}', '\u{00bb}',
+    '\u{2591}', '\u{2592}', '\u{2593}', '\u{2502}', '\u{2524}', '\u{00c1}', '\u{00c2}', '\u{00c0}',
+    '\u{00a9}', '\u{2563}', '\u{2551}', '\u{2557}', '\u{255d}', '\u{00a2}', '\u{00a5}', '\u{2510}',
+    '\u{2514}', '\u{2534}', '\u{252c}', '\u{251c}', '\u{2500}', '\u{253c}', '\u{00e3}', '\u{00c3}',
+    '\u{255a}', '\u{2554}', '\u{2569}', '\u{2566}', '\u{2560}', '\u{2550}', '\u{256c}', '\u{00a4}',
+    '\u{00f0}', '\u{00d0}', '\u{00ca}', '\u{00cb}', '\u{00c8}', '\u{20ac}', '\u{00cd}', '\u{00ce}',
+    '\u{00cf}', '\u{2518}', '\u{250c}', '\u{2588}', '\u{2584}', '\u{00a6}', '\u{00cc}', '\u{2580}',
+    '\u{00d3}', '\u{00df}', '\u{00d4}', '\u{00d2}', '\u{00f5}', '\u{00d5}', '\u{00b5}', '\u{00fe}',
+    '\u{00de}', '\u{00da}', '\u{00db}', '\u{00d9}', '\u{00fd}', '\u{00dd}', '\u{00af}', '\u{00b4}',
+    '\u{00ad}', '\u{00b1}', '\u{2017}', '\u{00be}', '\u{00b6}', '\u{00a7}', '\u{00f7}', '\u{00b8}',
+    '\u{00b0}', '\u{00a8}', '\u{00b7}', '\u{00b9}', '\u{00b3}', '\u{00b2}', '\u{25a0}', '\u{00a0}',
+];
+
+const CP860: [char; 128] = [
+    '\u{00c7}', '\u{00fc}', '\u{00e9}', '\u{00e2}', '\u{00e3}', '\u{00e0}', '\u{00c1}', '\u{00e7}',
+    '\u{00ea}', '\u{00ca}', '\u{00e8}', '\u{00cd}', '\u{00d4}', '\u{00ec}', '\u{00c3}', '\u{00c2}',
+    '\u{00c9}', '\u{00c0}', '\u{00c8}', '\u{00f4}', '\u{00f5}', '\u{00f2}', '\u{00da}', '\u{00f9}',
+    '\u{00cc}', '\u{00d5}', '\u{00dc}', '\u{00a2}', '\u{00a3}', '\u{00d9}', '\u{20a7}', '\u{00d3}',
+    '\u{00e1}', '\u{00ed}', '\u{00f3}', '\u{00fa}', '\u{00f1}', '\u{00d1}', '\u{00aa}', '\u{00ba}',
+    '\u{00bf}', '\u{00d2}', '\u{00ac}', '\u{00bd}', '\u{00bc}', '\u{00a1}', '\u{00ab}', '\u{00bb}',
+    '\u{2591}', '\u{2592}', '\u{2593}', '\u{2502}', '\u{2524}', '\u{2561}', '\u{2562}', '\u{2556}',
+    '\u{2555}', '\u{2563}', '\u{2551}', '\u{2557}', '\u{255d}', '\u{255c}', '\u{255b}', '\u{2510}',
+    '\u{2514}', '\u{2534}', '\u{252c}', '\u{251c}', '\u{2500}', '\u{253c}', '\u{255e}', '\u{255f}',
+    '\u{255a}', '\u{2554}', '\u{2569}', '\u{2566}', '\u{2560}', '\u{2550}', '\u{256c}', '\u{2567}',
+    '\u{2568}', '\u{2564}', '\u{2565}', '\u{2559}', '\u{2558}', '\u{2552}', '\u{2553}', '\u{256b}',
+    '\u{256a}', '\u{2518}', '\u{250c}', '\u{2588}', '\u{2584}', '\u{258c}', '\u{2590}', '\u{2580}',
+    '\u{03b1}', '\u{00df}', '\u{0393}', '\u{03c0}', '\u{03a3}', '\u{03c3}', '\u{00b5}', '\u{03c4}',
+    '\u{03a6}', '\u{0398}', '\u{03a9}', '\u{03b4}', '\u{221e}', '\u{03c6}', '\u{03b5}', '\u{2229}',
+    '\u{2261}', '\u{00b1}', '\u{2265}', '\u{2264}', '\u{2320}', '\u{2321}', '\u{00f7}', '\u{2248}',
+    '\u{00b0}', '\u{2219}', '\u{00b7}', '\u{221a}', '\u{207f}', '\u{00b2}', '\u{25a0}', '\u{00a0}',
+];
+
+const CP863: [char; 128] = [
+    '\u{00c7}', '\u{00fc}', '\u{00e9}', '\u{00e2}', '\u{00c2}', '\u{00e0}', '\u{00b6}', '\u{00e7}',
+    '\u{00ea}', '\u{00eb}', '\u{00e8}', '\u{00ef}', '\u{00ee}', '\u{2017}', '\u{00c0}', '\u{00a7}',
+    '\u{00c9}', '\u{00c8}', '\u{00ca}', '\u{00f4}', '\u{00cb}', '\u{00cf}', '\u{00fb}', '\u{00f9}',
+    '\u{00a4}', '\u{00d4}', '\u{00dc}', '\u{00a2}', '\u{00a3}', '\u{00d9}', '\u{00db}', '\u{0192}',
+    '\u{00a6}', '\u{00b4}', '\u{00f3}', '\u{00fa}', '\u{00a8}', '\u{00b8}', '\u{00b3}', '\u{00af}',
+    '\u{00ce}', '\u{2310}', '\u{00ac}', '\u{00bd}', '\u{00bc}', '\u{00be}', '\u{00ab}', '\u{00bb}',
+    '\u{2591}', '\u{2592}', '\u{2593}', '\u{2502}', '\u{2524}', '\u{2561}', '\u{2562}', '\u{2556}',
+    '\u{2555}', '\u{2563}', '\u{2551}', '\u{2557}', '\u{255d}', '\u{255c}', '\u{255b}', '\u{2510}',
+    '\u{2514}', '\u{2534}', '\u{252c}', '\u{251c}', '\u{2500}', '\u{253c}', '\u{255e}', '\u{255f}',
+    '\u{255a}', '\u{2554}', '\u{2569}', '\u{2566}', '\u{2560}', '\u{2550}', '\u{256c}', '\u{2567}',
+    '\u{2568}', '\u{2564}', '\u{2565}', '\u{2559}', '\u{2558}', '\u{2552}', '\u{2553}', '\u{256b}',
+    '\u{256a}', '\u{2518}', '\u{250c}', '\u{2588}', '\u{2584}', '\u{258c}', '\u{2590}', '\u{2580}',
+    '\u{03b1}', '\u{00df}', '\u{0393}', '\u{03c0}', '\u{03a3}', '\u{03c3}', '\u{00b5}', '\u{03c4}',
+    '\u{03a6}', '\u{0398}', '\u{03a9}', '\u{03b4}', '\u{221e}', '\u{03c6}', '\u{03b5}', '\u{2229}',
+    '\u{2261}', '\u{00b1}', '\u{2265}', '\u{2264}', '\u{2320}', '\u{2321}', '\u{00f7}', '\u{2248}',
+    '\u{00b0}', '\u{2219}', '\u{00b7}', '\u{221a}', '\u{207f}', '\u{00b2}', '\u{25a0}', '\u{00a0}',
+];
+
+const CP865: [char; 128] = [
+    '\u{00c7}', '\u{00fc}', '\u{00e9}', '\u{00e2}', '\u{00e4}', '\u{00e0}', '\u{00e5}', '\u{00e7}',
+    '\u{00ea}', '\u{00eb}', '\u{00e8}', '\u{00ef}', '\u{00ee}', '\u{00ec}', '\u{00c4}', '\u{00c5}',
+    '\u{00c9}', '\u{00e6}', '\u{00c6}', '\u{00f4}', '\u{00f6}', '\u{00f2}', '\u{00fb}', '\u{00f9}',
+    '\u{00ff}', '\u{00d6}', '\u{00dc}', '\u{00f8}', '\u{00a3}', '\u{00d8}', '\u{20a7}', '\u{0192}',
+    '\u{00e1}', '\u{00ed}', '\u{00f3}', '\u{00fa}', '\u{00f1}', '\u{00d1}', '\u{00aa}', '\u{00ba}',
+    '\u{00bf}', '\u{2310}', '\u{00ac}', '\u{00bd}', '\u{00bc}', '\u{00a1}', '\u{00ab}', '\u{00a4}',
+    '\u{2591}', '\u{2592}', '\u{2593}', '\u{2502}', '\u{2524}', '\u{2561}', '\u{2562}', '\u{2556}',
+    '\u{2555}', '\u{2563}', '\u{2551}', '\u{2557}', '\u{255d}', '\u{255c}', '\u{255b}', '\u{2510}',
+    '\u{2514}', '\u{2534}', '\u{252c}', '\u{251c}', '\u{2500}', '\u{253c}', '\u{255e}', '\u{255f}',
+    '\u{255a}', '\u{2554}', '\u{2569}', '\u{2566}', '\u{2560}', '\u{2550}', '\u{256c}', '\u{2567}',
+    '\u{2568}', '\u{2564}', '\u{2565}', '\u{2559}', '\u{2558}', '\u{2552}', '\u{2553}', '\u{256b}',
+    '\u{256a}', '\u{2518}', '\u{250c}', '\u{2588}', '\u{2584}', '\u{258c}', '\u{2590}', '\u{2580}',
+    '\u{03b1}', '\u{00df}', '\u{0393}', '\u{03c0}', '\u{03a3}', '\u{03c3}', '\u{00b5}', '\u{03c4}',
+    '\u{03a6}', '\u{0398}', '\u{03a9}', '\u{03b4}', '\u{221e}', '\u{03c6}', '\u{03b5}', '\u{2229}',
+    '\u{2261}', '\u{00b1}', '\u{2265}', '\u{2264}', '\u{2320}', '\u{2321}', '\u{00f7}', '\u{2248}',
+    '\u{00b0}', '\u{2219}', '\u{00b7}', '\u{221a}', '\u{207f}', '\u{00b2}', '\u{25a0}', '\u{00a0}',
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_char() {
+        assert_eq!(CodePage::PC437.encode_char('A'), Some(b'A'));
+        assert_eq!(CodePage::PC437.encode_char('é'), Some(0x82));
+        assert_eq!(CodePage::PC858.encode_char('€'), Some(0xd5));
+        assert_eq!(CodePage::PC850.encode_char('€'), None);
+        assert_eq!(CodePage::WPC1252.encode_char('€'), Some(0x80));
+        assert_eq!(CodePage::PC866.encode_char('Ж'), Some(0x86));
+        assert_eq!(
+            "windows-1252".parse::<CodePage>().unwrap(),
+            CodePage::WPC1252
+        );
+        assert_eq!("cp858".parse::<CodePage>().unwrap(), CodePage::PC858);
+    }
+
+    #[test]
+    fn switch_pages() {
+        let mut current = CodePage::PC437;
+        let bytes = encode(
+            "Grüße 5€ Жук",
+            EPSON_CODE_PAGES,
+            &mut current,
+            &Fallback::default(),
+            EncoderTrap::Replace,
+        )
+        .unwrap();
+        assert_eq!(
+            bytes,
+            b"Gr\x81\xe1e 5\x1b\x74\x10\x80 \x1b\x74\x11\x86\xe3\xaa".to_vec()
+        );
+        assert_eq!(current, CodePage::PC866);
+    }
+
+    #[test]
+    fn fallback_chain() {
+        let pages = &[(CodePage::PC437, 0)];
+        let mut fallback = Fallback::default();
+        let mut current = CodePage::PC437;
+
+        // Quotes and the Euro sign aren't in PC437, but ß is
+        let bytes = encode(
+            "“Weiß” 5€ 👍🏽",
+            pages,
+            &mut current,
+            &fallback,
+            EncoderTrap::Replace,
+        )
+        .unwrap();
+        assert_eq!(bytes, b"\"Wei\xe1\" 5EUR ?".to_vec());
+
+        fallback.table.insert('€', "E".to_string());
+        fallback.replacement = "[?]".to_string();
+        let bytes = encode(
+            "5€ 👍",
+            pages,
+            &mut current,
+            &fallback,
+            EncoderTrap::Replace,
+        )
+        .unwrap();
+        assert_eq!(bytes, b"5E [?]".to_vec());
+
+        let res = encode("👍", pages, &mut current, &fallback, EncoderTrap::Strict);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn failed_transliteration_keeps_page() {
+        let pages = &[(CodePage::PC437, 0), (CodePage::PC866, 17)];
+        let mut fallback = Fallback::default();
+        let mut current = CodePage::PC437;
+
+        // Ж needs PC866, 👍 can't be printed at all
+        fallback.table.insert('☃', "Ж👍".to_string());
+        let bytes = encode("☃é", pages, &mut current, &fallback, EncoderTrap::Replace).unwrap();
+        assert_eq!(bytes, b"?\x82".to_vec());
+        assert_eq!(current, CodePage::PC437);
+    }
+}
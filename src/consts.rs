@@ -1,6 +1,4 @@
-/**
- * ESC/POS Commands (Constants)
- */
+//! ESC/POS Commands (Constants)
 
 /**
  * [`FEED_CONTROL_SEQUENCES` Feed control sequences]
@@ -37,6 +35,17 @@ pub const TXT_UNDERL2_ON: &[u8] = b"\x1b\x2d\x02"; // Underline font 2-dot ON
 pub const TXT_BOLD_OFF: &[u8] = b"\x1b\x45\x00"; // Bold font OFF
 pub const TXT_BOLD_ON: &[u8] = b"\x1b\x45\x01"; // Bold font ON
 
+pub const TXT_DOUBLE_STRIKE_OFF: &[u8] = b"\x1b\x47\x00"; // Double-strike OFF
+pub const TXT_DOUBLE_STRIKE_ON: &[u8] = b"\x1b\x47\x01"; // Double-strike ON
+pub const TXT_REVERSE_OFF: &[u8] = b"\x1d\x42\x00"; // White on black OFF
+pub const TXT_REVERSE_ON: &[u8] = b"\x1d\x42\x01"; // White on black ON
+pub const TXT_UPSIDE_DOWN_OFF: &[u8] = b"\x1b\x7b\x00"; // Upside-down OFF
+pub const TXT_UPSIDE_DOWN_ON: &[u8] = b"\x1b\x7b\x01"; // Upside-down ON
+pub const TXT_ROTATE_OFF: &[u8] = b"\x1b\x56\x00"; // 90 degree rotation OFF
+pub const TXT_ROTATE_ON: &[u8] = b"\x1b\x56\x01"; // 90 degree clockwise rotation ON
+pub const TXT_SMOOTH_OFF: &[u8] = b"\x1d\x62\x00"; // Smoothing OFF
+pub const TXT_SMOOTH_ON: &[u8] = b"\x1d\x62\x01"; // Smoothing ON
+
 pub const TXT_FONT_A: &[u8] = b"\x1b\x4d\x00"; // Font type A
 pub const TXT_FONT_B: &[u8] = b"\x1b\x4d\x01"; // Font type B
 pub const TXT_FONT_C: &[u8] = b"\x1b\x4d\x02"; // Font type C
@@ -49,7 +58,6 @@ pub const TXT_ALIGN_RT: &[u8] = b"\x1b\x61\x02"; // Right justification
  * [`BARCODE_FORMAT` Barcode format]
  */
 // .BARCODE_FORMAT
-
 pub const BARCODE_FONT_A: &[u8] = b"\x1d\x66\x00"; // Font type A for HRI barcode chars
 pub const BARCODE_FONT_B: &[u8] = b"\x1d\x66\x01"; // Font type B for HRI barcode chars
 
@@ -1,27 +1,482 @@
 use std::fs;
 use std::io;
-use std::net;
+use std::io::{Read, Write};
+use std::net::{self, ToSocketAddrs};
 use std::path;
 
-pub struct Usb {}
-pub struct Serial {}
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
 
+use crate::printer::{Error, UsbInfo, TIMEOUT};
+
+/// Transport is the byte pipe a [crate::printer::Printer] uses to talk to the
+/// printer. Implement it to drive a printer over something other than the
+/// devices provided here.
+pub trait Transport {
+    /// Sends `buf` to the printer, returning the number of bytes transferred
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error>;
+
+    /// Reads a response from the printer into `buf`, waiting at most
+    /// `timeout` for it to arrive.
+    ///
+    /// Write-only transports return [Error::Unsupported].
+    fn read(&mut self, _buf: &mut [u8], _timeout: Duration) -> Result<usize, Error> {
+        Err(Error::Unsupported)
+    }
+
+    /// Returns the latest Automatic Status Back message pushed by the printer,
+    /// waiting at most `timeout` for one to arrive.
+    ///
+    /// By default this reads straight from the transport and treats the first
+    /// four bytes as the status. Shorter replies are padded with zeros, as
+    /// some printers only send the bytes that have anything set.
+    fn read_asb(&mut self, timeout: Duration) -> Result<Option<[u8; 4]>, Error> {
+        let mut buf = [0_u8; 16];
+        match self.read(&mut buf, timeout)? {
+            0 => Ok(None),
+            _ => Ok(Some([buf[0], buf[1], buf[2], buf[3]])),
+        }
+    }
+
+    /// Tells the transport whether the printer has been asked to push
+    /// Automatic Status Back messages, for transports that have to tell
+    /// them apart from query responses
+    fn set_asb(&mut self, _enabled: bool) {}
+
+    /// Pushes out anything the transport itself is holding on to
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Largest number of bytes to hand to [Transport::write] at once
+    fn max_packet_size(&self) -> usize {
+        4096
+    }
+
+    /// Gives the printer `duration` to finish a slow mechanical operation,
+    /// such as a cut, before more data is sent
+    fn pause(&mut self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+
+    /// Releases the underlying device. The transport shouldn't be used after
+    /// it has been closed.
+    fn close(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// USB printer talking over a pair of bulk endpoints
+pub struct Usb {
+    device: rusb::Device<rusb::GlobalContext>,
+    handle: rusb::DeviceHandle<rusb::GlobalContext>,
+    descriptor: rusb::DeviceDescriptor,
+    timeout: Duration,
+
+    /// USB Vendor ID
+    vid: u16,
+    /// USB Product ID
+    pid: u16,
+    /// USB Command Endpoint (output)
+    cmd_ep: u8,
+    /// USB Status Endpoint (input)
+    stat_ep: u8,
+    /// Max packet size of the command endpoint
+    max_packet_size: usize,
+}
+
+impl Usb {
+    /// Opens the first USB device matching `vid` and `pid` and claims its
+    /// bulk endpoints
+    pub fn new(vid: u16, pid: u16) -> Result<Usb, Error> {
+        // Iterate over the devices to find the printer
+        let mut matches: VecDeque<_> = rusb::devices()?
+            .iter()
+            // Filter out the devices that match the vendor_id and product_id (should only be 1)
+            .filter_map(|d| {
+                let desc = match d.device_descriptor() {
+                    Ok(d) => d,
+                    Err(_) => {
+                        return None;
+                    }
+                };
+                if desc.vendor_id() == vid && desc.product_id() == pid {
+                    Some((d, desc))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        let (device, descriptor) = match matches.pop_front() {
+            Some((device, descriptor)) => (device, descriptor),
+            None => return Err(Error::NotFound),
+        };
+
+        let handle = device.open()?;
+
+        let _ = handle.set_auto_detach_kernel_driver(true);
+        handle.claim_interface(0).expect("Cannot claim_interface");
+
+        let config_desc = match device.config_descriptor(0) {
+            Ok(v) => v,
+            Err(e) => {
+                return Err(e.into());
+            }
+        };
+
+        let interface = match config_desc.interfaces().next() {
+            Some(x) => x,
+            None => {
+                return Err(Error::InvalidEndpoints);
+            }
+        };
+
+        let (mut cmd_ep, mut stat_ep) = (None, None);
+        let mut max_packet_size = 64;
+
+        for interface_desc in interface.descriptors() {
+            for endpoint_desc in interface_desc.endpoint_descriptors() {
+                match (endpoint_desc.transfer_type(), endpoint_desc.direction()) {
+                    (rusb::TransferType::Bulk, rusb::Direction::In) => {
+                        stat_ep = Some(endpoint_desc.address())
+                    }
+                    (rusb::TransferType::Bulk, rusb::Direction::Out) => {
+                        cmd_ep = Some(endpoint_desc.address());
+                        max_packet_size = endpoint_desc.max_packet_size() as usize;
+                    }
+                    (_, _) => continue,
+                }
+            }
+        }
+
+        let (cmd_ep, stat_ep) = match (cmd_ep, stat_ep) {
+            (Some(cmd), Some(stat)) => (cmd, stat),
+            _ => {
+                return Err(Error::InvalidEndpoints);
+            }
+        };
+
+        match handle.kernel_driver_active(interface.number())? {
+            true => {
+                handle.detach_kernel_driver(interface.number())?;
+            }
+            false => {
+                log::trace!("Kernel driver inactive");
+            }
+        }
+        let _ = handle.claim_interface(interface.number());
+
+        Ok(Usb {
+            device,
+            handle,
+            descriptor,
+            timeout: Duration::from_millis(TIMEOUT),
+            vid,
+            pid,
+            cmd_ep,
+            stat_ep,
+            max_packet_size,
+        })
+    }
+
+    pub fn release(&mut self) -> Result<(), Error> {
+        let config_desc = match self.device.config_descriptor(0) {
+            Ok(v) => v,
+            Err(e) => {
+                return Err(e.into());
+            }
+        };
+
+        let interface = match config_desc.interfaces().next() {
+            Some(x) => x,
+            None => {
+                return Err(Error::InvalidEndpoints);
+            }
+        };
+        let _ = self.handle.release_interface(interface.number());
+        let _ = self.handle.release_interface(0);
+        Ok(())
+    }
+
+    pub fn info(&mut self) -> Result<UsbInfo, Error> {
+        let languages = self.handle.read_languages(self.timeout)?;
+        let language = languages[0];
+
+        let manufacturer = self
+            .handle
+            .read_manufacturer_string(language, &self.descriptor, self.timeout)
+            .unwrap_or("".to_string());
+        let product = self
+            .handle
+            .read_product_string(language, &self.descriptor, self.timeout)
+            .unwrap_or("".to_string());
+        Ok(UsbInfo {
+            vendor_id: self.vid,
+            product_id: self.pid,
+            manufacturer,
+            product,
+        })
+    }
+}
+
+impl Transport for Usb {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        Ok(self.handle.write_bulk(self.cmd_ep, buf, self.timeout)?)
+    }
+
+    fn read(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize, Error> {
+        Ok(self.handle.read_bulk(self.stat_ep, buf, timeout)?)
+    }
+
+    fn max_packet_size(&self) -> usize {
+        self.max_packet_size
+    }
+
+    fn close(&mut self) -> Result<(), Error> {
+        self.release()
+    }
+}
+
+pub use serialport::{DataBits, FlowControl, Parity, StopBits};
+
+/// Line settings for a [Serial] device
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SerialSettings {
+    pub baud_rate: u32,
+    pub data_bits: DataBits,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+    /// [FlowControl::Hardware] uses RTS/CTS, [FlowControl::Software] uses
+    /// XON/XOFF
+    pub flow_control: FlowControl,
+}
+
+impl Default for SerialSettings {
+    /// 9600 baud 8N1 without flow control
+    fn default() -> Self {
+        SerialSettings {
+            baud_rate: 9600,
+            data_bits: DataBits::Eight,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            flow_control: FlowControl::None,
+        }
+    }
+}
+
+/// RS-232 printer
+pub struct Serial {
+    port: Box<dyn serialport::SerialPort>,
+    /// Timeout for writes, which the port shares with reads
+    write_timeout: Duration,
+}
+
+impl Serial {
+    /// Opens the serial port at `path` (e.g. `/dev/ttyS0` or `COM1`) at
+    /// `baud_rate`, 8N1 without flow control
+    pub fn new(path: &str, baud_rate: u32) -> Result<Serial, Error> {
+        Serial::with_settings(
+            path,
+            SerialSettings {
+                baud_rate,
+                ..SerialSettings::default()
+            },
+        )
+    }
+
+    pub fn with_settings(path: &str, settings: SerialSettings) -> Result<Serial, Error> {
+        let port = serialport::new(path, settings.baud_rate)
+            .data_bits(settings.data_bits)
+            .parity(settings.parity)
+            .stop_bits(settings.stop_bits)
+            .flow_control(settings.flow_control)
+            .timeout(Duration::from_millis(TIMEOUT))
+            .open()?;
+        Ok(Serial::from_port(port))
+    }
+
+    /// Wraps a port that has already been opened and configured. Its
+    /// timeout is kept as the timeout for writes.
+    pub fn from_port(port: Box<dyn serialport::SerialPort>) -> Serial {
+        let write_timeout = port.timeout();
+        Serial {
+            port,
+            write_timeout,
+        }
+    }
+
+    pub fn settings(&self) -> Result<SerialSettings, Error> {
+        Ok(SerialSettings {
+            baud_rate: self.port.baud_rate()?,
+            data_bits: self.port.data_bits()?,
+            parity: self.port.parity()?,
+            stop_bits: self.port.stop_bits()?,
+            flow_control: self.port.flow_control()?,
+        })
+    }
+}
+
+impl Transport for Serial {
+    /// Writes as much of `buf` as the port takes before the timeout. With
+    /// flow control holding the port, that can be only part of it.
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        match self.port.write(buf) {
+            Ok(n) => Ok(n),
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => Err(Error::Timeout),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn read(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize, Error> {
+        self.port.set_timeout(timeout)?;
+        let res = self.port.read(buf);
+        self.port.set_timeout(self.write_timeout)?;
+        match res {
+            Ok(n) => Ok(n),
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => Err(Error::Timeout),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(self.port.flush()?)
+    }
+}
+
+/// Connection settings for a [Network] device
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NetworkSettings {
+    /// How long to wait for the printer to accept the connection, `None`
+    /// leaves it up to the OS
+    pub connect_timeout: Option<Duration>,
+    /// Idle time before TCP keepalive probes are sent, `None` disables
+    /// keepalive
+    pub keepalive: Option<Duration>,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        NetworkSettings {
+            connect_timeout: Some(Duration::from_secs(5)),
+            keepalive: Some(Duration::from_secs(60)),
+        }
+    }
+}
+
+/// Printer listening on a raw TCP port, usually 9100
+///
+/// Printers with Automatic Status Back enabled push 4 byte status messages
+/// down the same connection as query responses. Once ASB has been turned on
+/// with [crate::printer::Printer::asb], those messages are split out of what
+/// arrives while no response is being waited for, and can be picked up with
+/// [Transport::read_asb]. Everything that arrives while a response is
+/// awaited is taken to be the response.
 #[derive(Debug)]
 pub struct Network {
     _host: String,
     _port: u16,
     stream: net::TcpStream,
+    /// Received bytes that haven't been sorted into responses or ASB yet
+    incoming: VecDeque<u8>,
+    /// Response bytes waiting to be read
+    pending: VecDeque<u8>,
+    /// Most recent Automatic Status Back message
+    asb: Option<[u8; 4]>,
+    /// Whether the printer has been told to push ASB messages
+    asb_enabled: bool,
 }
 
 impl Network {
     pub fn new(host: &str, port: u16) -> io::Result<Network> {
-        let stream = net::TcpStream::connect((host, port))?;
+        Network::with_settings(host, port, NetworkSettings::default())
+    }
+
+    pub fn with_settings(host: &str, port: u16, settings: NetworkSettings) -> io::Result<Network> {
+        let stream = match settings.connect_timeout {
+            Some(timeout) => {
+                let mut last_err = None;
+                let mut stream = None;
+                for addr in (host, port).to_socket_addrs()? {
+                    match net::TcpStream::connect_timeout(&addr, timeout) {
+                        Ok(s) => {
+                            stream = Some(s);
+                            break;
+                        }
+                        Err(e) => last_err = Some(e),
+                    }
+                }
+                match stream {
+                    Some(s) => s,
+                    None => {
+                        return Err(last_err.unwrap_or_else(|| {
+                            io::Error::new(io::ErrorKind::NotFound, "No address for host")
+                        }))
+                    }
+                }
+            }
+            None => net::TcpStream::connect((host, port))?,
+        };
+        stream.set_nodelay(true)?;
+        if let Some(time) = settings.keepalive {
+            let keepalive = socket2::TcpKeepalive::new().with_time(time);
+            socket2::SockRef::from(&stream).set_tcp_keepalive(&keepalive)?;
+        }
         Ok(Network {
             _host: host.to_string(),
             _port: port,
             stream,
+            incoming: VecDeque::new(),
+            pending: VecDeque::new(),
+            asb: None,
+            asb_enabled: false,
         })
     }
+
+    /// Waits up to `timeout` for data from the printer and sorts it into
+    /// responses and ASB messages, or takes all of it as a response if
+    /// `response` is set. Returns false if nothing arrived.
+    fn receive(&mut self, timeout: Duration, response: bool) -> Result<bool, Error> {
+        // A zero timeout would mean blocking forever
+        let timeout = timeout.max(Duration::from_millis(1));
+        self.stream.set_read_timeout(Some(timeout))?;
+        let mut buf = [0_u8; 64];
+        let n = match self.stream.read(&mut buf) {
+            Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+            Ok(n) => n,
+            Err(e)
+                if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut =>
+            {
+                return Ok(false)
+            }
+            Err(e) => return Err(e.into()),
+        };
+        self.incoming.extend(&buf[..n]);
+        if response || !self.asb_enabled {
+            self.pending.extend(self.incoming.drain(..));
+            return Ok(true);
+        }
+
+        // ASB messages start with 0xx1xx00 followed by three 0xx0xxxx bytes
+        while let Some(&first) = self.incoming.front() {
+            if first & 0x93 == 0x10 {
+                if self.incoming.len() < 4 {
+                    break;
+                }
+                if self.incoming.iter().skip(1).take(3).all(|b| b & 0x90 == 0) {
+                    let mut status = [0_u8; 4];
+                    for b in status.iter_mut() {
+                        *b = self.incoming.pop_front().unwrap();
+                    }
+                    log::trace!("ASB: {:02x?}", status);
+                    self.asb = Some(status);
+                    continue;
+                }
+            }
+            self.pending.push_back(first);
+            self.incoming.pop_front();
+        }
+        Ok(true)
+    }
 }
 
 impl io::Write for Network {
@@ -34,8 +489,63 @@ impl io::Write for Network {
     }
 }
 
-/// File device that can be written to.
+impl Transport for Network {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        self.stream.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn read(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize, Error> {
+        let deadline = Instant::now() + timeout;
+        while self.pending.is_empty() {
+            let now = Instant::now();
+            if now >= deadline || !self.receive(deadline - now, true)? {
+                if self.incoming.is_empty() {
+                    return Err(Error::Timeout);
+                }
+                // Nothing else is coming to complete what looked like the
+                // start of an ASB message, so it must be a response
+                self.pending.extend(self.incoming.drain(..));
+            }
+        }
+        let n = buf.len().min(self.pending.len());
+        for (dst, src) in buf.iter_mut().zip(self.pending.drain(..n)) {
+            *dst = src;
+        }
+        Ok(n)
+    }
+
+    fn read_asb(&mut self, timeout: Duration) -> Result<Option<[u8; 4]>, Error> {
+        if !self.asb_enabled {
+            return Ok(self.asb.take());
+        }
+        // Drain whatever has been pushed since the last call, waiting only if
+        // nothing has been seen yet
+        let mut wait = if self.asb.is_some() {
+            Duration::from_millis(1)
+        } else {
+            timeout
+        };
+        while self.receive(wait, false)? {
+            wait = Duration::from_millis(1);
+        }
+        Ok(self.asb.take())
+    }
+
+    fn set_asb(&mut self, enabled: bool) {
+        self.asb_enabled = enabled;
+    }
 
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(self.stream.flush()?)
+    }
+
+    fn close(&mut self) -> Result<(), Error> {
+        Ok(self.stream.shutdown(net::Shutdown::Both)?)
+    }
+}
+
+/// File device that can be written to.
 #[derive(Debug)]
 pub struct File<W> {
     fobj: W,
@@ -46,6 +556,7 @@ impl<W: io::Write> File<W> {
         let fobj = fs::OpenOptions::new()
             .write(true)
             .create(true)
+            .truncate(false)
             .open(&path)?;
         Ok(File { fobj })
     }
@@ -63,6 +574,11 @@ impl<W: io::Write> File<W> {
     pub fn from(fobj: W) -> File<W> {
         File { fobj }
     }
+
+    /// Consumes the device, returning the wrapped writer
+    pub fn into_inner(self) -> W {
+        self.fobj
+    }
 }
 
 impl<W: io::Write> io::Write for File<W> {
@@ -74,3 +590,25 @@ impl<W: io::Write> io::Write for File<W> {
         self.fobj.flush()
     }
 }
+
+impl<W: io::Write> Transport for File<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        self.fobj.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(self.fobj.flush()?)
+    }
+}
+
+/// In-memory transport that collects the commands instead of sending them
+/// anywhere. See [crate::document::Document].
+impl Transport for Vec<u8> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        self.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn pause(&mut self, _duration: Duration) {}
+}
@@ -0,0 +1,888 @@
+//! Receipts compiled to ESC/POS ahead of time
+//!
+//! A [Document] has the same operations as the `chain_*` methods on
+//! [Printer] but collects the commands in memory instead of sending them, so
+//! a receipt can be built, inspected or stored without a printer attached.
+//! Send it with [Printer::print_document].
+//!
+//! # Example
+//! ```rust
+//! use posify::document::Document;
+//! use posify::printer::SupportedPrinters;
+//! use posify::text::Alignment;
+//!
+//! let mut doc = Document::new(SupportedPrinters::SNBC);
+//! doc.hwinit()
+//!     .unwrap()
+//!     .align(Alignment::Center)
+//!     .unwrap()
+//!     .text("Thank you!")
+//!     .unwrap()
+//!     .partial_cut()
+//!     .unwrap();
+//! assert_eq!(&doc.as_bytes()[..4], &[0x1b, 0x40, 0x1b, 0x61]);
+//! ```
+
+use encoding::types::{EncoderTrap, EncodingRef};
+
+use crate::barcode::{Barcode, BarcodeType, Font, TextPosition};
+use crate::cjk::CjkEncoding;
+use crate::code2d::{AztecOptions, DataMatrixOptions, MaxiCodeOptions, Pdf417Options, QrOptions};
+use crate::codepage::{CodePage, Fallback};
+use crate::glyph::Glyph;
+use crate::img::{BitImageDensity, Image, RasterMode};
+use crate::markup::Markup;
+use crate::page::PrintDirection;
+use crate::printer::{Error, Printer, SupportedPrinters};
+use crate::render::TrueTypeFont;
+use crate::table::Table;
+use crate::template::Template;
+use crate::text::{Alignment, Control, Emphasis, TextFont, TextMode, Underline};
+use crate::units::Length;
+
+/// ESC/POS commands for a given printer, built without a device
+pub struct Document {
+    printer: Printer<Vec<u8>>,
+}
+
+impl Document {
+    /// Creates an empty document for `printer` that encodes text as UTF-8
+    pub fn new(printer: SupportedPrinters) -> Document {
+        Document::with_codec(None, None, printer)
+    }
+
+    pub fn with_codec(
+        codec: Option<EncodingRef>,
+        trap: Option<EncoderTrap>,
+        printer: SupportedPrinters,
+    ) -> Document {
+        let mut printer = Printer::with_transport(codec, trap, printer, Vec::new());
+        // Write straight through so the bytes are always available
+        let _ = printer.set_buffer_size(0);
+        Document { printer }
+    }
+
+    /// The printer profile the document is built for
+    pub fn printer(&self) -> SupportedPrinters {
+        self.printer.printer
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        self.printer.transport()
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        // Nothing is ever buffered, so there's nothing that could fail
+        self.printer.into_transport().unwrap_or_default()
+    }
+
+    /// Number of characters that fit on a line with the current font and
+    /// character width, see [Printer::chars_per_line]
+    pub fn chars_per_line(&self) -> Result<usize, Error> {
+        self.printer.chars_per_line()
+    }
+
+    pub fn len(&self) -> usize {
+        self.as_bytes().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.as_bytes().is_empty()
+    }
+
+    /// Appends raw bytes to the document
+    pub fn write(&mut self, buf: &[u8]) -> Result<&mut Self, Error> {
+        self.printer.write(buf).map(|_| self)
+    }
+
+    pub fn hwinit(&mut self) -> Result<&mut Self, Error> {
+        self.printer.hwinit().map(|_| self)
+    }
+
+    pub fn print(&mut self, content: &str) -> Result<&mut Self, Error> {
+        self.printer.print(content).map(|_| self)
+    }
+
+    pub fn println(&mut self, content: &str) -> Result<&mut Self, Error> {
+        self.printer.println(content).map(|_| self)
+    }
+
+    pub fn print_wrapped(&mut self, content: &str) -> Result<&mut Self, Error> {
+        self.printer.print_wrapped(content).map(|_| self)
+    }
+
+    pub fn print_row(&mut self, table: &Table, cells: &[&str]) -> Result<&mut Self, Error> {
+        self.printer.print_row(table, cells).map(|_| self)
+    }
+
+    pub fn print_justified(&mut self, left: &str, right: &str) -> Result<&mut Self, Error> {
+        self.printer.print_justified(left, right).map(|_| self)
+    }
+
+    pub fn print_markup(&mut self, markup: &Markup) -> Result<&mut Self, Error> {
+        self.printer.print_markup(markup).map(|_| self)
+    }
+
+    pub fn print_template(
+        &mut self,
+        template: &Template,
+        context: &serde_json::Value,
+    ) -> Result<&mut Self, Error> {
+        self.printer.print_template(template, context).map(|_| self)
+    }
+
+    pub fn text(&mut self, content: &str) -> Result<&mut Self, Error> {
+        self.printer.text(content).map(|_| self)
+    }
+
+    pub fn code_page(&mut self, page: CodePage) -> Result<&mut Self, Error> {
+        self.printer.code_page(page).map(|_| self)
+    }
+
+    pub fn print_rendered(
+        &mut self,
+        content: &str,
+        font: &TrueTypeFont,
+        size: f32,
+    ) -> Result<&mut Self, Error> {
+        self.printer
+            .print_rendered(content, font, size)
+            .map(|_| self)
+    }
+
+    pub fn cjk_encoding(&mut self, encoding: CjkEncoding) -> Result<&mut Self, Error> {
+        self.printer.cjk_encoding(encoding).map(|_| self)
+    }
+
+    pub fn kanji_mode(&mut self, on: bool) -> Result<&mut Self, Error> {
+        self.printer.kanji_mode(on).map(|_| self)
+    }
+
+    /// Sets the code pages the printer has, see [Printer::set_code_pages]
+    pub fn set_code_pages(&mut self, pages: &'static [(CodePage, u8)]) -> &mut Self {
+        self.printer.set_code_pages(pages);
+        self
+    }
+
+    /// Sets how characters missing from the current code page are printed
+    pub fn set_fallback(&mut self, fallback: Fallback) -> &mut Self {
+        self.printer.set_fallback(fallback);
+        self
+    }
+
+    pub fn hr(&mut self, width: usize) -> Result<&mut Self, Error> {
+        self.printer.hr(width).map(|_| self)
+    }
+
+    pub fn feed(&mut self, n: usize) -> Result<&mut Self, Error> {
+        self.printer.feed(n).map(|_| self)
+    }
+
+    pub fn line_space(&mut self, n: i32) -> Result<&mut Self, Error> {
+        self.printer.line_space(n).map(|_| self)
+    }
+
+    pub fn control(&mut self, ctrl: Control) -> Result<&mut Self, Error> {
+        self.printer.control(ctrl).map(|_| self)
+    }
+
+    pub fn align(&mut self, alignment: Alignment) -> Result<&mut Self, Error> {
+        self.printer.align(alignment).map(|_| self)
+    }
+
+    pub fn font(&mut self, family: TextFont) -> Result<&mut Self, Error> {
+        self.printer.font(family).map(|_| self)
+    }
+
+    pub fn style(&mut self, kind: Emphasis) -> Result<&mut Self, Error> {
+        self.printer.style(kind).map(|_| self)
+    }
+
+    pub fn underline_mode(&mut self, mode: Underline) -> Result<&mut Self, Error> {
+        self.printer.underline_mode(mode).map(|_| self)
+    }
+
+    pub fn text_mode(&mut self, mode: TextMode, on: bool) -> Result<&mut Self, Error> {
+        self.printer.text_mode(mode, on).map(|_| self)
+    }
+
+    pub fn reverse(&mut self, on: bool) -> Result<&mut Self, Error> {
+        self.printer.reverse(on).map(|_| self)
+    }
+
+    pub fn upside_down(&mut self, on: bool) -> Result<&mut Self, Error> {
+        self.printer.upside_down(on).map(|_| self)
+    }
+
+    pub fn rotate(&mut self, on: bool) -> Result<&mut Self, Error> {
+        self.printer.rotate(on).map(|_| self)
+    }
+
+    pub fn double_strike(&mut self, on: bool) -> Result<&mut Self, Error> {
+        self.printer.double_strike(on).map(|_| self)
+    }
+
+    pub fn smoothing(&mut self, on: bool) -> Result<&mut Self, Error> {
+        self.printer.smoothing(on).map(|_| self)
+    }
+
+    pub fn motion_units(&mut self, x: u8, y: u8) -> Result<&mut Self, Error> {
+        self.printer.motion_units(x, y).map(|_| self)
+    }
+
+    pub fn left_margin(&mut self, margin: Length) -> Result<&mut Self, Error> {
+        self.printer.left_margin(margin).map(|_| self)
+    }
+
+    pub fn print_width(&mut self, width: Length) -> Result<&mut Self, Error> {
+        self.printer.print_width(width).map(|_| self)
+    }
+
+    pub fn absolute_position(&mut self, position: Length) -> Result<&mut Self, Error> {
+        self.printer.absolute_position(position).map(|_| self)
+    }
+
+    pub fn relative_position(&mut self, offset: Length) -> Result<&mut Self, Error> {
+        self.printer.relative_position(offset).map(|_| self)
+    }
+
+    pub fn char_spacing(&mut self, spacing: Length) -> Result<&mut Self, Error> {
+        self.printer.char_spacing(spacing).map(|_| self)
+    }
+
+    pub fn tab_stops(&mut self, columns: &[u8]) -> Result<&mut Self, Error> {
+        self.printer.tab_stops(columns).map(|_| self)
+    }
+
+    pub fn page_mode(&mut self) -> Result<&mut Self, Error> {
+        self.printer.page_mode().map(|_| self)
+    }
+
+    pub fn standard_mode(&mut self) -> Result<&mut Self, Error> {
+        self.printer.standard_mode().map(|_| self)
+    }
+
+    pub fn page_area(
+        &mut self,
+        x: Length,
+        y: Length,
+        width: Length,
+        height: Length,
+    ) -> Result<&mut Self, Error> {
+        self.printer.page_area(x, y, width, height).map(|_| self)
+    }
+
+    pub fn page_direction(&mut self, direction: PrintDirection) -> Result<&mut Self, Error> {
+        self.printer.page_direction(direction).map(|_| self)
+    }
+
+    pub fn vertical_position(&mut self, position: Length) -> Result<&mut Self, Error> {
+        self.printer.vertical_position(position).map(|_| self)
+    }
+
+    pub fn page_position(&mut self, x: Length, y: Length) -> Result<&mut Self, Error> {
+        self.printer.page_position(x, y).map(|_| self)
+    }
+
+    pub fn print_page(&mut self) -> Result<&mut Self, Error> {
+        self.printer.print_page().map(|_| self)
+    }
+
+    pub fn end_page(&mut self) -> Result<&mut Self, Error> {
+        self.printer.end_page().map(|_| self)
+    }
+
+    pub fn cancel_page(&mut self) -> Result<&mut Self, Error> {
+        self.printer.cancel_page().map(|_| self)
+    }
+
+    pub fn define_chars(&mut self, first: u8, glyphs: &[Glyph]) -> Result<&mut Self, Error> {
+        self.printer.define_chars(first, glyphs).map(|_| self)
+    }
+
+    pub fn define_char(&mut self, code: u8, glyph: &Glyph) -> Result<&mut Self, Error> {
+        self.printer.define_char(code, glyph).map(|_| self)
+    }
+
+    pub fn cancel_char(&mut self, code: u8) -> Result<&mut Self, Error> {
+        self.printer.cancel_char(code).map(|_| self)
+    }
+
+    pub fn user_defined_chars(&mut self, on: bool) -> Result<&mut Self, Error> {
+        self.printer.user_defined_chars(on).map(|_| self)
+    }
+
+    /// Codes of the user-defined characters uploaded for `font`
+    pub fn defined_chars(&self, font: TextFont) -> Vec<u8> {
+        self.printer.defined_chars(font)
+    }
+
+    pub fn size(&mut self, width: u8, height: u8) -> Result<&mut Self, Error> {
+        self.printer.size(width, height).map(|_| self)
+    }
+
+    pub fn barcode(
+        &mut self,
+        code: &str,
+        kind: BarcodeType,
+        position: TextPosition,
+        font: Font,
+        width: u8,
+        height: u8,
+    ) -> Result<&mut Self, Error> {
+        self.printer
+            .barcode(code, kind, position, font, width, height)
+            .map(|_| self)
+    }
+
+    pub fn print_barcode(&mut self, barcode: &Barcode, code: &str) -> Result<&mut Self, Error> {
+        self.printer.print_barcode(barcode, code).map(|_| self)
+    }
+
+    pub fn qrcode(&mut self, code: &str, options: &QrOptions) -> Result<&mut Self, Error> {
+        self.printer.qrcode(code, options).map(|_| self)
+    }
+
+    pub fn pdf417(&mut self, code: &str, options: &Pdf417Options) -> Result<&mut Self, Error> {
+        self.printer.pdf417(code, options).map(|_| self)
+    }
+
+    pub fn maxicode(&mut self, code: &str, options: &MaxiCodeOptions) -> Result<&mut Self, Error> {
+        self.printer.maxicode(code, options).map(|_| self)
+    }
+
+    pub fn aztec(&mut self, code: &str, options: &AztecOptions) -> Result<&mut Self, Error> {
+        self.printer.aztec(code, options).map(|_| self)
+    }
+
+    pub fn datamatrix(
+        &mut self,
+        code: &str,
+        options: &DataMatrixOptions,
+    ) -> Result<&mut Self, Error> {
+        self.printer.datamatrix(code, options).map(|_| self)
+    }
+
+    pub fn bit_image(
+        &mut self,
+        image: &Image,
+        density: BitImageDensity,
+    ) -> Result<&mut Self, Error> {
+        self.printer.bit_image(image, density).map(|_| self)
+    }
+
+    pub fn raster(&mut self, image: &Image, mode: RasterMode) -> Result<&mut Self, Error> {
+        self.printer.raster(image, mode).map(|_| self)
+    }
+
+    pub fn full_cut(&mut self) -> Result<&mut Self, Error> {
+        self.printer.full_cut().map(|_| self)
+    }
+
+    pub fn partial_cut(&mut self) -> Result<&mut Self, Error> {
+        self.printer.partial_cut().map(|_| self)
+    }
+
+    pub fn cashdraw(&mut self, pin: i32) -> Result<&mut Self, Error> {
+        self.printer.cashdraw(pin).map(|_| self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::barcode::BarcodeFunction;
+    use crate::code2d::{
+        DataMatrixSize, MaxiCodeMode, Pdf417ErrorCorrection, QrErrorCorrection, QrMode,
+        AZTEC_MAX_BYTES, AZTEC_MAX_DATA, AZTEC_MAX_TEXT, DATAMATRIX_MAX_ALPHANUMERIC,
+        DATAMATRIX_MAX_BYTES, DATAMATRIX_MAX_DATA, MAXICODE_MAX_DATA, MAXICODE_MAX_TEXT,
+        PDF417_MAX_BYTES, PDF417_MAX_DATA, PDF417_MAX_TEXT,
+    };
+    use crate::codepage::EPSON_CODE_PAGES;
+
+    #[test]
+    fn document_bytes() {
+        let mut doc = Document::new(SupportedPrinters::P3);
+        doc.hwinit()
+            .unwrap()
+            .style(Emphasis::Bold)
+            .unwrap()
+            .println("TOTAL")
+            .unwrap()
+            .cashdraw(2)
+            .unwrap()
+            .partial_cut()
+            .unwrap();
+        assert_eq!(
+            doc.into_bytes(),
+            b"\x1b\x40\x1b\x45\x01\x1b\x2d\x00TOTAL\n\x1b\x70\x00\x0a\x0a\x0a\x1b\x6d".to_vec()
+        );
+    }
+
+    #[test]
+    fn raster_bands() {
+        // 8 bytes a row, so 1024 rows make two bands of 4096 bytes
+        let image = Image::from(image::DynamicImage::new_luma8(64, 1024));
+        let mut printer = Printer::with_transport(None, None, SupportedPrinters::SNBC, Vec::new());
+        printer.set_receive_buffer(4096);
+        printer.raster(&image, RasterMode::Normal).unwrap();
+
+        let bytes = printer.into_transport().unwrap();
+        assert_eq!(bytes.len(), 2 * (8 + 4096));
+        let header = [0x1d, 0x76, 0x30, 0x00, 0x08, 0x00, 0x00, 0x02];
+        assert_eq!(bytes[..8], header);
+        assert_eq!(bytes[8 + 4096..8 + 4096 + 8], header);
+
+        // Without a known receive buffer the image goes in one band
+        let mut doc = Document::new(SupportedPrinters::SNBC);
+        doc.raster(&image, RasterMode::Normal).unwrap();
+        assert_eq!(doc.len(), 8 + 8192);
+    }
+
+    #[test]
+    fn character_size() {
+        let mut doc = Document::new(SupportedPrinters::SNBC);
+        assert_eq!(doc.chars_per_line().unwrap(), 48);
+        doc.size(3, 8).unwrap();
+        assert_eq!(doc.as_bytes(), &[0x1d, 0x21, 0x27]);
+        assert_eq!(doc.chars_per_line().unwrap(), 16);
+
+        assert!(doc.size(0, 1).is_err());
+        assert!(doc.size(1, 9).is_err());
+
+        // Font C has no known size, so it's sent but lines can't be measured
+        doc.size(1, 1).unwrap().font(TextFont::C).unwrap();
+        assert_eq!(doc.as_bytes()[3..], [0x1d, 0x21, 0x00, 0x1b, 0x4d, 0x02]);
+        assert!(matches!(doc.chars_per_line(), Err(Error::Unsupported)));
+
+        // Nor is how far the P3 magnifies characters
+        let mut doc = Document::new(SupportedPrinters::P3);
+        assert!(matches!(doc.size(2, 2), Err(Error::Unsupported)));
+        assert!(doc.is_empty());
+    }
+
+    #[test]
+    fn wrapped_text() {
+        let mut doc = Document::new(SupportedPrinters::SNBC);
+        doc.size(4, 1)
+            .unwrap()
+            .print_wrapped("Thank you for shopping with us")
+            .unwrap();
+        assert_eq!(&doc.as_bytes()[3..], b"Thank you\nfor shopping\nwith us\n");
+    }
+
+    #[test]
+    fn table_rows() {
+        let mut doc = Document::new(SupportedPrinters::SNBC);
+        doc.font(TextFont::B)
+            .unwrap()
+            .size(2, 1)
+            .unwrap()
+            .print_justified("Total", "12.50")
+            .unwrap();
+        let line = format!("Total{}12.50\n", " ".repeat(22));
+        assert_eq!(&doc.as_bytes()[6..], line.as_bytes());
+    }
+
+    #[test]
+    fn text_modes() {
+        let mut doc = Document::new(SupportedPrinters::SNBC);
+        doc.reverse(true)
+            .unwrap()
+            .upside_down(true)
+            .unwrap()
+            .rotate(true)
+            .unwrap()
+            .double_strike(true)
+            .unwrap()
+            .smoothing(false)
+            .unwrap();
+        assert_eq!(
+            doc.as_bytes(),
+            b"\x1d\x42\x01\x1b\x7b\x01\x1b\x56\x01\x1b\x47\x01\x1d\x62\x00"
+        );
+
+        let mut printer = Printer::with_transport(None, None, SupportedPrinters::P3, Vec::new());
+        let mut profile = printer.profile().clone();
+        profile.text_modes = &[TextMode::Reverse];
+        printer.set_profile(profile);
+        assert!(printer.reverse(true).is_ok());
+        assert!(printer.smoothing(true).is_err());
+    }
+
+    #[test]
+    fn positioning() {
+        let mut doc = Document::new(SupportedPrinters::SNBC);
+        assert_eq!(doc.chars_per_line().unwrap(), 48);
+        doc.left_margin(Length::Mm(4.0))
+            .unwrap()
+            .print_width(Length::Mm(48.0))
+            .unwrap()
+            .absolute_position(Length::Dots(300))
+            .unwrap()
+            .relative_position(Length::Dots(-2))
+            .unwrap()
+            .char_spacing(Length::Dots(4))
+            .unwrap()
+            .tab_stops(&[8, 16, 24])
+            .unwrap();
+        assert_eq!(
+            doc.as_bytes(),
+            b"\x1d\x4c\x20\x00\x1d\x57\x80\x01\x1b\x24\x2c\x01\x1b\x5c\xfe\xff\
+              \x1b\x20\x04\x1b\x44\x08\x10\x18\x00"
+        );
+        // 384 dots wide, 12 + 4 dots per character
+        assert_eq!(doc.chars_per_line().unwrap(), 24);
+
+        let mut doc = Document::new(SupportedPrinters::SNBC);
+        doc.motion_units(180, 180)
+            .unwrap()
+            .left_margin(Length::Dots(203))
+            .unwrap();
+        assert_eq!(&doc.as_bytes()[4..], b"\x1d\x4c\xb4\x00");
+        assert!(doc.tab_stops(&[8, 8]).is_err());
+        assert!(doc.tab_stops(&[0, 8]).is_err());
+        assert!(doc.print_width(Length::Mm(80.0)).is_err());
+        assert!(doc.relative_position(Length::Dots(40000)).is_err());
+    }
+
+    #[test]
+    fn page_mode() {
+        let mut doc = Document::new(SupportedPrinters::SNBC);
+        assert!(doc.page_position(Length::Dots(0), Length::Dots(0)).is_err());
+        doc.page_mode()
+            .unwrap()
+            .motion_units(0, 100)
+            .unwrap()
+            .page_area(
+                Length::Dots(0),
+                Length::Dots(0),
+                Length::Dots(400),
+                Length::Dots(240),
+            )
+            .unwrap()
+            .page_direction(PrintDirection::BottomToTop)
+            .unwrap()
+            .page_position(Length::Dots(24), Length::Dots(48))
+            .unwrap()
+            .print("A")
+            .unwrap()
+            .print_page()
+            .unwrap()
+            .end_page()
+            .unwrap();
+        // 240 dots is 118 units of 1/100 inch, and going up the paper 24 dots
+        // along the line is 12 of them
+        assert_eq!(
+            doc.as_bytes(),
+            b"\x1b\x4c\x1d\x50\x00\x64\x1b\x57\x00\x00\x00\x00\x90\x01\x76\x00\
+              \x1b\x54\x01\x1b\x24\x0c\x00\x1d\x24\x30\x00A\x1b\x0c\x0c"
+        );
+        assert!(doc.end_page().is_err());
+    }
+
+    #[test]
+    fn user_defined_chars() {
+        let tick = Glyph::from_bitmap(&["..#", "##."]).unwrap();
+        let mut doc = Document::new(SupportedPrinters::SNBC);
+        doc.define_chars(b'A', &[tick.clone(), tick.clone()])
+            .unwrap()
+            .font(TextFont::B)
+            .unwrap()
+            .define_char(b'~', &tick)
+            .unwrap()
+            .cancel_char(b'~')
+            .unwrap()
+            .user_defined_chars(true)
+            .unwrap();
+        let column = [0x40, 0x00, 0x00, 0x40, 0x00, 0x00, 0x80, 0x00, 0x00];
+        let mut expected = vec![0x1b, 0x26, 0x03, b'A', b'B', 0x03];
+        expected.extend(column);
+        expected.push(0x03);
+        expected.extend(column);
+        expected.extend([0x1b, 0x4d, 0x01, 0x1b, 0x26, 0x03, b'~', b'~', 0x03]);
+        expected.extend(column);
+        expected.extend([0x1b, 0x3f, b'~', 0x1b, 0x25, 0x01]);
+        assert_eq!(doc.as_bytes(), expected.as_slice());
+        assert_eq!(doc.defined_chars(TextFont::A), vec![b'A', b'B']);
+        assert!(doc.defined_chars(TextFont::B).is_empty());
+
+        // Font B is only 9 dots wide
+        let wide = Glyph::from_bitmap(&["##########"]).unwrap();
+        assert!(doc.define_char(b'A', &wide).is_err());
+        assert!(doc.define_char(0x7f, &tick).is_err());
+        assert!(doc.define_chars(b'~', &[tick.clone(), tick]).is_err());
+        doc.hwinit().unwrap();
+        assert!(doc.defined_chars(TextFont::A).is_empty());
+    }
+
+    #[test]
+    fn barcodes() {
+        let mut doc = Document::new(SupportedPrinters::P3);
+        doc.barcode(
+            "12345678",
+            BarcodeType::ITF,
+            TextPosition::Below,
+            Font::FontA,
+            2,
+            80,
+        )
+        .unwrap()
+        .barcode(
+            "CODE-93",
+            BarcodeType::Code93,
+            TextPosition::Off,
+            Font::FontB,
+            3,
+            60,
+        )
+        .unwrap();
+        assert_eq!(
+            doc.as_bytes(),
+            b"\x1d\x77\x02\x1d\x68\x50\x1d\x48\x02\x1d\x66\x00\x1d\x6b\x0512345678\x00\
+              \x1d\x77\x03\x1d\x68\x3c\x1d\x48\x00\x1d\x66\x01\x1d\x6b\x48\x07CODE-93"
+        );
+
+        let mut doc = Document::new(SupportedPrinters::Epic);
+        let mut upc = Barcode {
+            printer: SupportedPrinters::Epic,
+            width: 2,
+            height: 80,
+            font: Font::FontA,
+            kind: BarcodeType::UPCA,
+            position: TextPosition::Below,
+            function: BarcodeFunction::B,
+            check_digit: false,
+        };
+        doc.print_barcode(&upc, "01234567890").unwrap();
+        assert_eq!(&doc.as_bytes()[12..], b"\x1d\x6b\x41\x0b01234567890");
+        upc.kind = BarcodeType::QRCode;
+        assert!(doc.print_barcode(&upc, "01234567890").is_err());
+        assert_eq!(doc.len(), 12 + 15);
+    }
+
+    #[test]
+    fn qr_codes() {
+        let options = QrOptions::default()
+            .module_size(4)
+            .error_correction(QrErrorCorrection::H);
+        let mut doc = Document::new(SupportedPrinters::P3);
+        doc.qrcode("posify", &options).unwrap();
+        assert_eq!(
+            doc.as_bytes(),
+            b"\x1d\x28\x6b\x04\x00\x31\x41\x32\x00\
+              \x1d\x28\x6b\x03\x00\x31\x43\x04\
+              \x1d\x28\x6b\x03\x00\x31\x45\x33\
+              \x1d\x28\x6b\x09\x00\x31\x50\x30posify\
+              \x1d\x28\x6b\x03\x00\x31\x51\x30"
+        );
+
+        // Longer than a single length byte can say
+        let mut doc = Document::new(SupportedPrinters::P3);
+        doc.qrcode(&"7".repeat(300), &options).unwrap();
+        assert_eq!(&doc.as_bytes()[25..33], b"\x1d\x28\x6b\x2f\x01\x31\x50\x30");
+
+        let mut doc = Document::new(SupportedPrinters::P3);
+        doc.qrcode("レシート", &options.mode(QrMode::Kanji))
+            .unwrap();
+        assert!(doc.as_bytes().ends_with(
+            b"\x83\x8c\x83\x56\x81\x5b\x83\x67\
+              \x1d\x28\x6b\x03\x00\x31\x51\x30"
+        ));
+        assert!(doc.qrcode("", &options).is_err());
+        assert!(doc.qrcode("x", &options.module_size(17)).is_err());
+        assert!(matches!(
+            doc.qrcode("x", &options.version(Some(5))),
+            Err(Error::Unsupported)
+        ));
+
+        // The limits depend on what the printer can pack the text into
+        let plain = QrOptions::default();
+        assert!(doc.qrcode(&"1".repeat(7089), &plain).is_ok());
+        assert!(doc.qrcode(&"A".repeat(4296), &plain).is_ok());
+        assert!(doc.qrcode(&"A".repeat(4297), &plain).is_err());
+        assert!(doc.qrcode(&"a".repeat(2953), &plain).is_ok());
+        assert!(doc.qrcode(&"a".repeat(2954), &plain).is_err());
+        let kanji = plain.mode(QrMode::Kanji);
+        assert!(doc.qrcode(&"漢".repeat(1817), &kanji).is_ok());
+        assert!(doc.qrcode(&"漢".repeat(1818), &kanji).is_err());
+
+        let mut doc = Document::new(SupportedPrinters::SNBC);
+        doc.qrcode("posify", &options).unwrap();
+        assert_eq!(
+            doc.as_bytes(),
+            b"\x1d\x5a\x02\x1b\x5a\x03H\x04\x06\x00posify"
+        );
+        let mut doc = Document::new(SupportedPrinters::SNBC);
+        doc.qrcode("posify", &options.version(Some(7))).unwrap();
+        assert_eq!(&doc.as_bytes()[3..6], b"\x1b\x5a\x07");
+    }
+
+    #[test]
+    fn symbols_2d() {
+        let mut doc = Document::new(SupportedPrinters::P3);
+        let pdf417 = Pdf417Options::default()
+            .columns(4)
+            .error_correction(Pdf417ErrorCorrection::Level(5))
+            .truncated(true);
+        doc.pdf417("TICKET", &pdf417).unwrap();
+        assert_eq!(
+            doc.as_bytes(),
+            b"\x1d\x28\x6b\x03\x00\x30\x41\x04\
+              \x1d\x28\x6b\x03\x00\x30\x42\x00\
+              \x1d\x28\x6b\x03\x00\x30\x43\x03\
+              \x1d\x28\x6b\x03\x00\x30\x44\x03\
+              \x1d\x28\x6b\x04\x00\x30\x45\x30\x35\
+              \x1d\x28\x6b\x03\x00\x30\x46\x01\
+              \x1d\x28\x6b\x09\x00\x30\x50\x30TICKET\
+              \x1d\x28\x6b\x03\x00\x30\x51\x30"
+        );
+
+        let mut doc = Document::new(SupportedPrinters::P3);
+        let datamatrix = DataMatrixOptions::default()
+            .size(DataMatrixSize::Rectangle(12, 0))
+            .module_size(4);
+        doc.datamatrix("AB", &datamatrix)
+            .unwrap()
+            .maxicode(
+                "CD",
+                &MaxiCodeOptions::default().mode(MaxiCodeMode::Standard),
+            )
+            .unwrap()
+            .aztec("EF", &AztecOptions::default().compact(true).layers(2))
+            .unwrap();
+        assert_eq!(
+            doc.as_bytes(),
+            b"\x1d\x28\x6b\x05\x00\x36\x42\x01\x0c\x00\
+              \x1d\x28\x6b\x03\x00\x36\x43\x04\
+              \x1d\x28\x6b\x05\x00\x36\x50\x30AB\
+              \x1d\x28\x6b\x03\x00\x36\x51\x30\
+              \x1d\x28\x6b\x03\x00\x32\x41\x34\
+              \x1d\x28\x6b\x05\x00\x32\x50\x30CD\
+              \x1d\x28\x6b\x03\x00\x32\x51\x30\
+              \x1d\x28\x6b\x04\x00\x35\x42\x01\x02\
+              \x1d\x28\x6b\x03\x00\x35\x43\x03\
+              \x1d\x28\x6b\x03\x00\x35\x45\x17\
+              \x1d\x28\x6b\x05\x00\x35\x50\x30EF\
+              \x1d\x28\x6b\x03\x00\x35\x51\x30"
+        );
+
+        let printed = doc.len();
+        assert!(doc
+            .pdf417(
+                "x",
+                &pdf417.error_correction(Pdf417ErrorCorrection::Level(9))
+            )
+            .is_err());
+        assert!(doc
+            .datamatrix("x", &datamatrix.size(DataMatrixSize::Rectangle(10, 0)))
+            .is_err());
+        assert!(doc
+            .aztec("x", &AztecOptions::default().compact(true).layers(5))
+            .is_err());
+        for size in [
+            DataMatrixSize::SquareOf(11),
+            DataMatrixSize::SquareOf(28),
+            DataMatrixSize::SquareOf(56),
+            DataMatrixSize::Rectangle(8, 20),
+            DataMatrixSize::Rectangle(16, 26),
+        ] {
+            assert!(doc.datamatrix("x", &datamatrix.size(size)).is_err());
+        }
+        assert!(doc.aztec("x", &AztecOptions::default().layers(3)).is_err());
+        assert!(doc
+            .maxicode(&"1".repeat(139), &MaxiCodeOptions::default())
+            .is_err());
+        assert_eq!(doc.len(), printed);
+        for size in [
+            DataMatrixSize::SquareOf(144),
+            DataMatrixSize::Rectangle(16, 48),
+        ] {
+            assert!(doc.datamatrix("x", &datamatrix.size(size)).is_ok());
+        }
+        assert!(doc.aztec("x", &AztecOptions::default().layers(4)).is_ok());
+
+        // Each symbology holds fewer letters than digits and fewer of any
+        // other byte than letters
+        type Symbol = fn(&mut Document, &str) -> Result<(), Error>;
+        let pdf417_symbol: Symbol =
+            |doc, code| doc.pdf417(code, &Pdf417Options::default()).map(drop);
+        let datamatrix_symbol: Symbol = |doc, code| {
+            doc.datamatrix(code, &DataMatrixOptions::default())
+                .map(drop)
+        };
+        let maxicode_symbol: Symbol =
+            |doc, code| doc.maxicode(code, &MaxiCodeOptions::default()).map(drop);
+        let aztec_symbol: Symbol = |doc, code| doc.aztec(code, &AztecOptions::default()).map(drop);
+        for (symbol, max, data) in [
+            (pdf417_symbol, PDF417_MAX_DATA, "1"),
+            (pdf417_symbol, PDF417_MAX_TEXT, "A"),
+            (pdf417_symbol, PDF417_MAX_BYTES, "a"),
+            (datamatrix_symbol, DATAMATRIX_MAX_DATA, "1"),
+            (datamatrix_symbol, DATAMATRIX_MAX_ALPHANUMERIC, "A1"),
+            (datamatrix_symbol, DATAMATRIX_MAX_BYTES, "a"),
+            (maxicode_symbol, MAXICODE_MAX_DATA, "1"),
+            (maxicode_symbol, MAXICODE_MAX_TEXT, "a"),
+            (aztec_symbol, AZTEC_MAX_DATA, "1"),
+            (aztec_symbol, AZTEC_MAX_TEXT, "A"),
+            (aztec_symbol, AZTEC_MAX_BYTES, "a"),
+        ] {
+            let mut doc = Document::new(SupportedPrinters::P3);
+            let code = data.repeat(max);
+            assert!(symbol(&mut doc, &code[..max]).is_ok());
+            assert!(symbol(&mut doc, &(code[..max].to_owned() + data)).is_err());
+        }
+
+        // ESC Z printers don't take these symbologies
+        let mut doc = Document::new(SupportedPrinters::SNBC);
+        assert!(doc.pdf417("TICKET", &pdf417).is_err());
+        assert!(doc.is_empty());
+    }
+
+    #[test]
+    fn code_pages() {
+        // No printer's numbering is assumed
+        let mut doc = Document::new(SupportedPrinters::SNBC);
+        assert!(matches!(
+            doc.code_page(CodePage::PC858),
+            Err(Error::Unsupported)
+        ));
+        assert!(doc.is_empty());
+
+        doc.set_code_pages(EPSON_CODE_PAGES)
+            .code_page(CodePage::PC858)
+            .unwrap()
+            .print("Prix: 5€, déjà")
+            .unwrap();
+        assert_eq!(doc.as_bytes(), b"\x1b\x74\x13Prix: 5\xd5, d\x82j\x85");
+    }
+
+    #[test]
+    fn cjk_text() {
+        let mut doc = Document::new(SupportedPrinters::SNBC);
+        doc.set_code_pages(EPSON_CODE_PAGES)
+            .cjk_encoding(CjkEncoding::Gb18030)
+            .unwrap()
+            .print("敏捷 fox")
+            .unwrap()
+            .code_page(CodePage::PC858)
+            .unwrap()
+            .print(" 5€ 狐")
+            .unwrap();
+        assert_eq!(
+            doc.as_bytes(),
+            b"\x1c\x26\xc3\xf4\xbd\xdd fox\x1b\x74\x13\x1c\x2e 5\xd5 \x1c\x26\xba\xfc"
+        );
+
+        let mut doc = Document::new(SupportedPrinters::SNBC);
+        doc.cjk_encoding(CjkEncoding::ShiftJis).unwrap();
+        assert_eq!(doc.as_bytes(), b"\x1c\x43\x01");
+    }
+
+    #[test]
+    fn document_unsupported_command() {
+        let mut doc = Document::new(SupportedPrinters::P3);
+        assert!(doc.full_cut().is_err());
+        assert!(doc.is_empty());
+    }
+}
@@ -0,0 +1,142 @@
+//! Bitmaps for user-defined characters
+//!
+//! A [Glyph] replaces one of the printable ASCII characters, `' '` to `'~'`,
+//! with a bitmap of its own, e.g. a store's currency sign, a checkbox or a
+//! small icon. Glyphs are uploaded for the current font with
+//! [crate::printer::Printer::define_char], can be no bigger than that font's
+//! character cell, and are only printed while
+//! [crate::printer::Printer::user_defined_chars] is on.
+//!
+//! # Example
+//! ```rust
+//! use posify::document::Document;
+//! use posify::glyph::Glyph;
+//! use posify::printer::SupportedPrinters;
+//!
+//! let checkbox = Glyph::from_bitmap(&[
+//!     "########",
+//!     "#......#",
+//!     "#.#..#.#",
+//!     "#..##..#",
+//!     "#..##..#",
+//!     "#.#..#.#",
+//!     "#......#",
+//!     "########",
+//! ])
+//! .unwrap();
+//! let mut doc = Document::new(SupportedPrinters::SNBC);
+//! doc.define_char(b'#', &checkbox)
+//!     .unwrap()
+//!     .user_defined_chars(true)
+//!     .unwrap()
+//!     .println("# Paid")
+//!     .unwrap();
+//! ```
+
+use crate::img::Image;
+use crate::printer::Error;
+
+/// First and last character codes that can be redefined
+pub const FIRST_CHAR: u8 = 0x20;
+pub const LAST_CHAR: u8 = 0x7e;
+
+/// A black and white bitmap for a user-defined character
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Glyph {
+    width: u8,
+    height: u8,
+    /// Printed dots, row by row
+    dots: Vec<bool>,
+}
+
+impl Glyph {
+    /// Builds a glyph from rows of text, `#` or `X` for a printed dot and
+    /// anything else for a blank one. Shorter rows are padded with blanks.
+    pub fn from_bitmap(rows: &[&str]) -> Result<Glyph, Error> {
+        let width = rows
+            .iter()
+            .map(|row| row.chars().count())
+            .max()
+            .unwrap_or(0);
+        let (width, height) = Glyph::size(width as u32, rows.len() as u32)?;
+        let mut dots = Vec::with_capacity(width as usize * height as usize);
+        for row in rows {
+            let mut chars = row.chars();
+            dots.extend(
+                (0..width).map(|_| matches!(chars.next(), Some('#') | Some('X') | Some('x'))),
+            );
+        }
+        Ok(Glyph {
+            width,
+            height,
+            dots,
+        })
+    }
+
+    /// Builds a glyph from an image, printing every pixel that isn't white
+    /// or transparent
+    pub fn from_image(image: &Image) -> Result<Glyph, Error> {
+        let (width, height) = Glyph::size(image.width, image.height)?;
+        let mut dots = Vec::with_capacity(width as usize * height as usize);
+        for y in 0..height as u32 {
+            dots.extend((0..width as u32).map(|x| !image.is_blank_pixel(x, y)));
+        }
+        Ok(Glyph {
+            width,
+            height,
+            dots,
+        })
+    }
+
+    fn size(width: u32, height: u32) -> Result<(u8, u8), Error> {
+        match (u8::try_from(width), u8::try_from(height)) {
+            (Ok(width), Ok(height)) if width > 0 && height > 0 => Ok((width, height)),
+            _ => Err(Error::InvalidArgument),
+        }
+    }
+
+    /// Width in dots
+    pub fn width(&self) -> u8 {
+        self.width
+    }
+
+    /// Height in dots
+    pub fn height(&self) -> u8 {
+        self.height
+    }
+
+    /// Whether the dot at `x`, `y` from the top left is printed
+    pub fn is_set(&self, x: usize, y: usize) -> bool {
+        x < self.width as usize
+            && y < self.height as usize
+            && self.dots[y * self.width as usize + x]
+    }
+
+    /// The glyph in ESC & column format: `rows` bytes per column from top
+    /// to bottom, the most significant bit at the top
+    pub(crate) fn columns(&self, rows: u8) -> Vec<u8> {
+        let mut data = Vec::with_capacity(self.width as usize * rows as usize);
+        for x in 0..self.width as usize {
+            for row in 0..rows as usize {
+                let byte = (0..8).fold(0u8, |byte, bit| {
+                    byte | (u8::from(self.is_set(x, row * 8 + bit)) << (7 - bit))
+                });
+                data.push(byte);
+            }
+        }
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn column_format() {
+        let glyph = Glyph::from_bitmap(&["#.", ".#", "", "", "", "", "", "", "##"]).unwrap();
+        assert_eq!((glyph.width(), glyph.height()), (2, 9));
+        assert_eq!(glyph.columns(3), vec![0x80, 0x80, 0x00, 0x40, 0x80, 0x00]);
+        assert!(Glyph::from_bitmap(&[]).is_err());
+    }
+}
@@ -1,9 +1,76 @@
 use std::iter::Iterator;
 use std::path;
+use std::str::FromStr;
 
 use image;
 use image::{error::ImageResult, DynamicImage, GenericImageView};
 
+use crate::printer::Error;
+
+/// Scaling used when printing a raster image with GS v 0
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum RasterMode {
+    #[default]
+    Normal,
+    DoubleWidth,
+    DoubleHeight,
+    /// Double width and double height
+    Quadruple,
+}
+
+impl FromStr for RasterMode {
+    type Err = Error;
+
+    /// Accepts `NORMAL`, `DW`, `DH` and `QD`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_ref() {
+            "NORMAL" => Ok(RasterMode::Normal),
+            "DW" => Ok(RasterMode::DoubleWidth),
+            "DH" => Ok(RasterMode::DoubleHeight),
+            "QD" => Ok(RasterMode::Quadruple),
+            _ => Err(Error::InvalidArgument),
+        }
+    }
+}
+
+/// Dot density used when printing a bit image with ESC *
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum BitImageDensity {
+    /// 8 dots single density, 102dpi
+    S8,
+    /// 8 dots double density, 203dpi
+    D8,
+    /// 24 dots single density, 102dpi
+    S24,
+    /// 24 dots double density, 203dpi
+    #[default]
+    D24,
+}
+
+impl BitImageDensity {
+    /// Number of vertical dots printed per line
+    pub fn dots(&self) -> u32 {
+        match self {
+            BitImageDensity::S8 | BitImageDensity::D8 => 8,
+            BitImageDensity::S24 | BitImageDensity::D24 => 24,
+        }
+    }
+}
+
+impl FromStr for BitImageDensity {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_ref() {
+            "S8" => Ok(BitImageDensity::S8),
+            "D8" => Ok(BitImageDensity::D8),
+            "S24" => Ok(BitImageDensity::S24),
+            "D24" => Ok(BitImageDensity::D24),
+            _ => Err(Error::InvalidArgument),
+        }
+    }
+}
+
 pub struct Image {
     pub width: u32,
     pub height: u32,
@@ -69,7 +136,7 @@ impl Image {
         pixel[3] == 0 || (pixel[0] & pixel[1] & pixel[2]) == 0xFF
     }
 
-    pub fn bitimage_lines(&self, density: u32) -> BitimageLines {
+    pub fn bitimage_lines(&self, density: u32) -> BitimageLines<'_> {
         BitimageLines {
             line: 0,
             density,
@@ -104,7 +171,7 @@ impl Image {
 
     #[allow(clippy::many_single_char_names)]
     pub fn get_raster(&self) -> Box<[u8]> {
-        let n = (self.width + 7) / 8; // Number of bytes per line
+        let n = self.width.div_ceil(8); // Number of bytes per line
         let mut data: Vec<u8> = vec![0; (n * self.height) as usize];
         for y in 0..self.height {
             for x in 0..n {
@@ -1,7 +1,22 @@
 //! posify - A ESC/POS driver for Rust
 
 pub mod barcode;
+pub mod cjk;
+pub mod code128;
+pub mod code2d;
+pub mod codepage;
 pub mod consts;
 pub mod device;
+pub mod document;
+pub mod glyph;
 pub mod img;
+pub mod markup;
+pub mod page;
 pub mod printer;
+pub mod profile;
+pub mod render;
+pub mod table;
+pub mod template;
+pub mod text;
+pub mod units;
+pub mod wrap;
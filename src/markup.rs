@@ -0,0 +1,663 @@
+//! Receipts written in a small XML-like markup
+//!
+//! Headers and footers can be kept as text and edited without rebuilding
+//! anything. [Markup::parse] checks the whole receipt up front and
+//! [crate::printer::Printer::print_markup] then runs it through the usual
+//! printer operations. Errors say where in the source they are.
+//!
+//! Text is printed exactly as written, line breaks included. `&lt;`, `&gt;`,
+//! `&amp;`, `&quot;`, `&apos;` and `&#N;` escape characters. The tags are
+//!
+//! | Tag | Does |
+//! |-----|------|
+//! | `<left>`, `<center>`, `<right>` | Aligns what's inside |
+//! | `<b>`, `<u>`, `<u2>` | Bold, underlined, 2 dot underlined |
+//! | `<reverse>` | White on black |
+//! | `<font name="b">` | Font A, B or C |
+//! | `<size width="2" height="2">` | Character size, 1-8 |
+//! | `<barcode type="code128" position="below" font="a" width="2" height="80">` | Barcode of the text inside |
+//! | `<image src="logo.png" mode="normal"/>` | Raster image from a file |
+//! | `<br/>`, `<hr/>`, `<feed lines="3"/>` | New line, horizontal rule, blank lines |
+//! | `<cut/>`, `<cut mode="partial"/>` | Full or partial cut |
+//! | `<cashdraw pin="2"/>` | Opens the cash drawer |
+//!
+//! # Example
+//! ```rust
+//! use posify::document::Document;
+//! use posify::markup::Markup;
+//! use posify::printer::SupportedPrinters;
+//!
+//! let markup = Markup::parse("<center><b>TOTAL</b></center>\n<cut/>").unwrap();
+//! let mut doc = Document::new(SupportedPrinters::SNBC);
+//! doc.print_markup(&markup).unwrap();
+//!
+//! let err = Markup::parse("<center>\n  <b>TOTAL</center>").err().unwrap();
+//! assert_eq!((err.line, err.column), (2, 11));
+//! ```
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::barcode::{BarcodeType, Font, TextPosition};
+use crate::device::Transport;
+use crate::img::{Image, RasterMode};
+use crate::printer::{Error, Printer};
+use crate::text::{Alignment, Emphasis, TextFont, Underline};
+
+/// Where in the markup something went wrong, counted from 1
+#[derive(thiserror::Error, Clone, Debug, PartialEq)]
+#[error("line {line}, column {column}: {message}{}", related_position(.related))]
+pub struct MarkupError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+    /// Line and column of another place the message is about, e.g. the tag
+    /// a closing tag doesn't match
+    pub related: Option<(usize, usize)>,
+}
+
+fn related_position(related: &Option<(usize, usize)>) -> String {
+    match related {
+        Some((line, column)) => format!(" from line {}, column {}", line, column),
+        None => String::new(),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Position {
+    line: usize,
+    column: usize,
+}
+
+impl Position {
+    fn error<S: Into<String>>(self, message: S) -> MarkupError {
+        MarkupError {
+            line: self.line,
+            column: self.column,
+            message: message.into(),
+            related: None,
+        }
+    }
+}
+
+/// Formatting that applies to everything between an opening and closing tag
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    Align(Alignment),
+    Bold(bool),
+    Underline(Underline),
+    Reverse(bool),
+    Font(TextFont),
+    Size(u8, u8),
+}
+
+enum Op {
+    Text(String),
+    Push(Format),
+    /// Goes back to the formatting before the matching push
+    Pop,
+    Barcode {
+        code: String,
+        kind: BarcodeType,
+        position: TextPosition,
+        font: Font,
+        width: u8,
+        height: u8,
+    },
+    Image(Image, RasterMode),
+    Newline,
+    Rule(Option<usize>),
+    Feed(usize),
+    Cut {
+        partial: bool,
+    },
+    Cashdraw(i32),
+}
+
+/// A parsed receipt, ready to be printed any number of times
+pub struct Markup {
+    ops: Vec<(Position, Op)>,
+}
+
+impl FromStr for Markup {
+    type Err = MarkupError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Markup::parse(s)
+    }
+}
+
+impl Markup {
+    /// Parses `source`, checking tags, attributes and image files
+    pub fn parse(source: &str) -> Result<Markup, MarkupError> {
+        Parser::new(source).parse()
+    }
+
+    pub(crate) fn print<T: Transport>(&self, printer: &mut Printer<T>) -> Result<usize, Error> {
+        let mut n = 0;
+        let mut emphasis = (false, Underline::Off);
+        let mut reverse = false;
+        let mut saved: Vec<Format> = Vec::new();
+        for (pos, op) in &self.ops {
+            let at = |err: Error| Error::Markup(pos.error(err.to_string()));
+            n += match op {
+                Op::Text(text) => printer.print(text).map_err(at)?,
+                Op::Push(format) => {
+                    let state = printer.text_state();
+                    saved.push(match format {
+                        Format::Align(_) => Format::Align(state.alignment),
+                        Format::Bold(_) => Format::Bold(emphasis.0),
+                        Format::Reverse(_) => Format::Reverse(reverse),
+                        Format::Underline(_) => Format::Underline(emphasis.1),
+                        Format::Font(_) => Format::Font(state.font),
+                        Format::Size(..) => Format::Size(state.width, state.height),
+                    });
+                    apply(printer, *format, &mut emphasis, &mut reverse).map_err(at)?
+                }
+                Op::Pop => match saved.pop() {
+                    Some(format) => {
+                        apply(printer, format, &mut emphasis, &mut reverse).map_err(at)?
+                    }
+                    None => 0,
+                },
+                Op::Barcode {
+                    code,
+                    kind,
+                    position,
+                    font,
+                    width,
+                    height,
+                } => printer
+                    .barcode(code, *kind, *position, *font, *width, *height)
+                    .map_err(at)?,
+                Op::Image(image, mode) => printer.raster(image, *mode).map_err(at)?,
+                Op::Newline => printer.print("\n").map_err(at)?,
+                Op::Rule(width) => {
+                    let width = match width {
+                        Some(width) => *width,
+                        None => printer.chars_per_line().map_err(at)?,
+                    };
+                    printer.hr(width).map_err(at)?
+                }
+                Op::Feed(lines) => printer.feed(*lines).map_err(at)?,
+                Op::Cut { partial: true } => printer.partial_cut().map_err(at)?,
+                Op::Cut { partial: false } => printer.full_cut().map_err(at)?,
+                Op::Cashdraw(pin) => printer.cashdraw(*pin).map_err(at)?,
+            };
+        }
+        Ok(n)
+    }
+}
+
+/// Sends `format`, keeping track of the bold, underline and reverse
+/// settings it changes
+fn apply<T: Transport>(
+    printer: &mut Printer<T>,
+    format: Format,
+    emphasis: &mut (bool, Underline),
+    reverse: &mut bool,
+) -> Result<usize, Error> {
+    match format {
+        Format::Align(alignment) => printer.align(alignment),
+        Format::Font(font) => printer.font(font),
+        Format::Reverse(on) => {
+            *reverse = on;
+            printer.reverse(on)
+        }
+        Format::Size(width, height) => printer.size(width, height),
+        Format::Bold(_) | Format::Underline(_) => {
+            match format {
+                Format::Bold(on) => emphasis.0 = on,
+                Format::Underline(mode) => emphasis.1 = mode,
+                _ => (),
+            }
+            printer.style(match *emphasis {
+                (false, Underline::Off) => Emphasis::Normal,
+                (true, Underline::Off) => Emphasis::Bold,
+                (false, Underline::On) => Emphasis::Underline,
+                (false, Underline::Thick) => Emphasis::Underline2,
+                (true, Underline::On) => Emphasis::BoldUnderline,
+                (true, Underline::Thick) => Emphasis::BoldUnderline2,
+            })
+        }
+    }
+}
+
+struct Tag {
+    name: String,
+    attrs: HashMap<String, (Position, String)>,
+    pos: Position,
+}
+
+impl Tag {
+    /// Parses attribute `name`, `None` if it isn't there
+    fn attr<V: FromStr>(&mut self, name: &str) -> Result<Option<V>, MarkupError> {
+        match self.attrs.remove(name) {
+            Some((pos, value)) => value.parse().map(Some).map_err(|_| {
+                pos.error(format!(
+                    "invalid value {:?} for {} on <{}>",
+                    value, name, self.name
+                ))
+            }),
+            None => Ok(None),
+        }
+    }
+
+    /// Fails on any attribute that hasn't been used
+    fn check_unused(&mut self) -> Result<(), MarkupError> {
+        match std::mem::take(&mut self.attrs)
+            .into_iter()
+            .min_by_key(|(_, (pos, _))| (pos.line, pos.column))
+        {
+            Some((name, (pos, _))) => {
+                Err(pos.error(format!("unknown attribute {} on <{}>", name, self.name)))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    offset: usize,
+    line: usize,
+    column: usize,
+    ops: Vec<(Position, Op)>,
+    /// Open tags, innermost last
+    open: Vec<Tag>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(source: &'a str) -> Parser<'a> {
+        Parser {
+            source,
+            offset: 0,
+            line: 1,
+            column: 1,
+            ops: Vec::new(),
+            open: Vec::new(),
+        }
+    }
+
+    fn pos(&self) -> Position {
+        Position {
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.source[self.offset..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.offset += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), MarkupError> {
+        let pos = self.pos();
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(pos.error(format!("expected {:?}, found {:?}", expected, c))),
+            None => Err(pos.error(format!("expected {:?}, found end of input", expected))),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.next();
+        }
+    }
+
+    fn name(&mut self) -> Result<String, MarkupError> {
+        let pos = self.pos();
+        let mut name = String::new();
+        while let Some(c) = self.peek() {
+            if !(c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+                break;
+            }
+            name.push(c);
+            self.next();
+        }
+        if name.is_empty() {
+            return Err(pos.error("expected a name"));
+        }
+        Ok(name.to_lowercase())
+    }
+
+    fn parse(mut self) -> Result<Markup, MarkupError> {
+        let mut text = String::new();
+        let mut text_pos = self.pos();
+        while let Some(c) = self.peek() {
+            match c {
+                '<' => {
+                    if !text.is_empty() {
+                        self.text(text_pos, std::mem::take(&mut text))?;
+                    }
+                    self.tag()?;
+                    text_pos = self.pos();
+                }
+                '&' => text.push(self.entity()?),
+                _ => {
+                    text.push(c);
+                    self.next();
+                }
+            }
+        }
+        if !text.is_empty() {
+            self.text(text_pos, text)?;
+        }
+        if let Some(tag) = self.open.pop() {
+            return Err(tag.pos.error(format!("<{}> is never closed", tag.name)));
+        }
+        Ok(Markup { ops: self.ops })
+    }
+
+    fn text(&mut self, pos: Position, text: String) -> Result<(), MarkupError> {
+        if let Some(tag) = self.open.last() {
+            if tag.name == "barcode" {
+                return match self.ops.last_mut() {
+                    Some((_, Op::Barcode { code, .. })) if code.is_empty() => {
+                        *code = text;
+                        Ok(())
+                    }
+                    _ => Err(pos.error("<barcode> can only hold text")),
+                };
+            }
+        }
+        self.ops.push((pos, Op::Text(text)));
+        Ok(())
+    }
+
+    fn entity(&mut self) -> Result<char, MarkupError> {
+        let pos = self.pos();
+        self.next();
+        let mut name = String::new();
+        loop {
+            match self.next() {
+                Some(';') => break,
+                Some(c) if c.is_ascii_alphanumeric() || c == '#' => name.push(c),
+                _ => return Err(pos.error("unterminated entity, write & as &amp;")),
+            }
+        }
+        let c = match name.as_ref() {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => match name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+                None => name
+                    .strip_prefix('#')
+                    .and_then(|n| n.parse().ok())
+                    .and_then(char::from_u32),
+            },
+        };
+        c.ok_or_else(|| pos.error(format!("unknown entity &{};", name)))
+    }
+
+    fn tag(&mut self) -> Result<(), MarkupError> {
+        let pos = self.pos();
+        self.next();
+        if self.peek() == Some('/') {
+            self.next();
+            let name = self.name()?;
+            self.skip_whitespace();
+            self.expect('>')?;
+            return self.close(pos, name);
+        }
+
+        let mut tag = Tag {
+            name: self.name()?,
+            attrs: HashMap::new(),
+            pos,
+        };
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('>') => {
+                    self.next();
+                    return self.open(tag, false);
+                }
+                Some('/') => {
+                    self.next();
+                    self.expect('>')?;
+                    return self.open(tag, true);
+                }
+                Some(_) => {
+                    let attr_pos = self.pos();
+                    let name = self.name()?;
+                    self.skip_whitespace();
+                    self.expect('=')?;
+                    self.skip_whitespace();
+                    let value = self.quoted()?;
+                    if tag.attrs.insert(name.clone(), (attr_pos, value)).is_some() {
+                        return Err(attr_pos.error(format!("{} is given twice", name)));
+                    }
+                }
+                None => return Err(self.pos().error("unexpected end of input in tag")),
+            }
+        }
+    }
+
+    fn quoted(&mut self) -> Result<String, MarkupError> {
+        let pos = self.pos();
+        let quote = match self.next() {
+            Some(q @ ('"' | '\'')) => q,
+            _ => return Err(pos.error("expected a quoted value")),
+        };
+        let mut value = String::new();
+        loop {
+            match self.peek() {
+                Some(c) if c == quote => {
+                    self.next();
+                    return Ok(value);
+                }
+                Some('&') => value.push(self.entity()?),
+                Some(c) => {
+                    value.push(c);
+                    self.next();
+                }
+                None => return Err(pos.error("unterminated value")),
+            }
+        }
+    }
+
+    fn open(&mut self, mut tag: Tag, empty: bool) -> Result<(), MarkupError> {
+        let pos = tag.pos;
+        let format = match tag.name.as_ref() {
+            "left" => Some(Format::Align(Alignment::Left)),
+            "center" => Some(Format::Align(Alignment::Center)),
+            "right" => Some(Format::Align(Alignment::Right)),
+            "b" => Some(Format::Bold(true)),
+            "reverse" => Some(Format::Reverse(true)),
+            "u" => Some(Format::Underline(Underline::On)),
+            "u2" => Some(Format::Underline(Underline::Thick)),
+            "font" => match tag.attr("name")? {
+                Some(font) => Some(Format::Font(font)),
+                None => return Err(pos.error("<font> needs a name")),
+            },
+            "size" => Some(Format::Size(
+                tag.attr("width")?.unwrap_or(1),
+                tag.attr("height")?.unwrap_or(1),
+            )),
+            _ => None,
+        };
+        if let Some(format) = format {
+            if empty {
+                return Err(pos.error(format!("<{}/> has nothing to format", tag.name)));
+            }
+            tag.check_unused()?;
+            self.ops.push((pos, Op::Push(format)));
+            self.open.push(tag);
+            return Ok(());
+        }
+
+        let op = match tag.name.as_ref() {
+            "barcode" => Op::Barcode {
+                code: String::new(),
+                kind: tag.attr("type")?.unwrap_or(BarcodeType::Code128),
+                position: tag.attr("position")?.unwrap_or(TextPosition::Below),
+                font: tag.attr("font")?.unwrap_or(Font::FontA),
+                width: tag.attr("width")?.unwrap_or(2),
+                height: tag.attr("height")?.unwrap_or(80),
+            },
+            "image" => {
+                let src: String = match tag.attr("src")? {
+                    Some(src) => src,
+                    None => return Err(pos.error("<image> needs a src")),
+                };
+                let mode = tag.attr("mode")?.unwrap_or_default();
+                let image = Image::new(&src)
+                    .map_err(|err| pos.error(format!("can't open {}: {}", src, err)))?;
+                Op::Image(image, mode)
+            }
+            "br" => Op::Newline,
+            "hr" => Op::Rule(tag.attr("width")?),
+            "feed" => Op::Feed(tag.attr("lines")?.unwrap_or(1)),
+            "cut" => {
+                let mode: String = tag.attr("mode")?.unwrap_or_else(|| "full".to_string());
+                match mode.as_ref() {
+                    "full" => Op::Cut { partial: false },
+                    "partial" => Op::Cut { partial: true },
+                    _ => return Err(pos.error(format!("invalid cut mode {:?}", mode))),
+                }
+            }
+            "cashdraw" => Op::Cashdraw(tag.attr("pin")?.unwrap_or(2)),
+            _ => return Err(pos.error(format!("unknown tag <{}>", tag.name))),
+        };
+        let container = matches!(op, Op::Barcode { .. });
+        if container && empty {
+            return Err(pos.error("<barcode> needs the code between its tags"));
+        }
+        if !container && !empty {
+            return Err(pos.error(format!("<{0}> has no content, write <{0}/>", tag.name)));
+        }
+        tag.check_unused()?;
+        self.ops.push((pos, op));
+        if container {
+            self.open.push(tag);
+        }
+        Ok(())
+    }
+
+    fn close(&mut self, pos: Position, name: String) -> Result<(), MarkupError> {
+        let tag = match self.open.pop() {
+            Some(tag) => tag,
+            None => return Err(pos.error(format!("</{}> doesn't close anything", name))),
+        };
+        if tag.name != name {
+            return Err(MarkupError {
+                related: Some((tag.pos.line, tag.pos.column)),
+                ..pos.error(format!("</{}> closes <{}>", name, tag.name))
+            });
+        }
+        if name == "barcode" {
+            match self.ops.last() {
+                Some((_, Op::Barcode { code, .. })) if !code.is_empty() => (),
+                _ => return Err(tag.pos.error("<barcode> is empty")),
+            }
+        } else {
+            self.ops.push((pos, Op::Pop));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::Document;
+    use crate::printer::SupportedPrinters;
+
+    fn compile(source: &str) -> Vec<u8> {
+        let mut doc = Document::new(SupportedPrinters::SNBC);
+        doc.print_markup(&Markup::parse(source).unwrap()).unwrap();
+        doc.into_bytes()
+    }
+
+    #[test]
+    fn compiles_markup() {
+        assert_eq!(
+            compile("<center><b>TOTAL &amp; TAX</b></center>\n<cut mode=\"partial\"/>"),
+            b"\x1b\x61\x01\x1b\x45\x01\x1b\x2d\x00TOTAL & TAX\x1b\x45\x00\x1b\x2d\x00\x1b\x61\x00\n\
+              \x0a\x0a\x0a\x1d\x56\x01"
+        );
+        assert_eq!(
+            compile("<size width='2' height='2'><u2>A</u2></size><feed lines='2'/>"),
+            b"\x1d\x21\x11\x1b\x45\x00\x1b\x2d\x02A\x1b\x45\x00\x1b\x2d\x00\x1d\x21\x00\n\n"
+        );
+        let barcode = compile("<barcode type=\"code128\" height=\"60\">1234</barcode>");
+        assert_eq!(&barcode[barcode.len() - 4..], &[0x7b, 0x43, 12, 34]);
+    }
+
+    #[test]
+    fn nested_formats() {
+        assert_eq!(
+            compile("<reverse>a<reverse>b</reverse>c</reverse>"),
+            b"\x1d\x42\x01a\x1d\x42\x01b\x1d\x42\x01c\x1d\x42\x00"
+        );
+        assert_eq!(
+            compile("<b>a<b>b</b>c</b>"),
+            b"\x1b\x45\x01\x1b\x2d\x00a\x1b\x45\x01\x1b\x2d\x00b\
+              \x1b\x45\x01\x1b\x2d\x00c\x1b\x45\x00\x1b\x2d\x00"
+        );
+    }
+
+    #[test]
+    fn error_positions() {
+        let cases = [
+            ("<b>bold", (1, 1), "<b> is never closed"),
+            ("ok\n  <blink>", (2, 3), "unknown tag <blink>"),
+            (
+                "<center>\n<b>x</center>",
+                (2, 5),
+                "</center> closes <b> from line 2, column 1",
+            ),
+            (
+                "<feed lines=\"many\"/>",
+                (1, 7),
+                "invalid value \"many\" for lines on <feed>",
+            ),
+            (
+                "<cut colour=\"red\"/>",
+                (1, 6),
+                "unknown attribute colour on <cut>",
+            ),
+            ("AT&T", (1, 3), "unterminated entity, write & as &amp;"),
+            ("<br>", (1, 1), "<br> has no content, write <br/>"),
+            ("</b>", (1, 1), "</b> doesn't close anything"),
+        ];
+        for (source, (line, column), message) in cases {
+            let err = Markup::parse(source).err().unwrap();
+            let shown = err.to_string();
+            assert_eq!(
+                (err.line, err.column, shown.split_once(": ").unwrap().1),
+                (line, column, message)
+            );
+        }
+
+        let err = Markup::parse("<center>\n<b>x</center>").err().unwrap();
+        assert_eq!(err.message, "</center> closes <b>");
+        assert_eq!(err.related, Some((2, 1)));
+    }
+
+    #[test]
+    fn printer_errors_have_positions() {
+        let markup = Markup::parse("Hello\n<barcode type=\"ean13\">12</barcode>").unwrap();
+        let mut doc = Document::new(SupportedPrinters::SNBC);
+        match doc.print_markup(&markup) {
+            Err(Error::Markup(err)) => assert_eq!((err.line, err.column), (2, 1)),
+            _ => panic!("expected a markup error"),
+        }
+    }
+}
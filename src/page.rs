@@ -0,0 +1,86 @@
+//! Page mode, for laying things out at fixed positions
+//!
+//! In standard mode the printer prints each line as it fills up. In page mode
+//! it collects text, barcodes and images in a print area instead, placed
+//! wherever [crate::printer::Printer::page_position] says, and only prints
+//! the whole page on [crate::printer::Printer::print_page] or
+//! [crate::printer::Printer::end_page]. Turning the page with
+//! [crate::printer::Printer::page_direction] prints everything rotated, e.g.
+//! text running up the side of a shelf label next to its barcode.
+//!
+//! # Example
+//! ```rust
+//! use posify::document::Document;
+//! use posify::page::PrintDirection;
+//! use posify::printer::SupportedPrinters;
+//! use posify::units::Length;
+//!
+//! let mut doc = Document::new(SupportedPrinters::SNBC);
+//! doc.page_mode()
+//!     .unwrap()
+//!     .page_area(Length::Dots(0), Length::Dots(0), Length::Mm(50.0), Length::Mm(30.0))
+//!     .unwrap()
+//!     .page_direction(PrintDirection::BottomToTop)
+//!     .unwrap()
+//!     .page_position(Length::Mm(2.0), Length::Mm(4.0))
+//!     .unwrap()
+//!     .print("2.99")
+//!     .unwrap()
+//!     .end_page()
+//!     .unwrap();
+//! ```
+
+use std::str::FromStr;
+
+use crate::printer::Error;
+
+/// ESC T n - Print direction in page mode, named after the way lines of
+/// text run across the paper as it comes out of the printer
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum PrintDirection {
+    /// Starting at the top left, the same as standard mode
+    #[default]
+    LeftToRight,
+    /// Starting at the bottom left, rotated 90° anticlockwise
+    BottomToTop,
+    /// Starting at the bottom right, upside down
+    RightToLeft,
+    /// Starting at the top right, rotated 90° clockwise
+    TopToBottom,
+}
+
+impl PrintDirection {
+    /// The value of `n` in ESC T n
+    pub fn value(&self) -> u8 {
+        match self {
+            PrintDirection::LeftToRight => 0,
+            PrintDirection::BottomToTop => 1,
+            PrintDirection::RightToLeft => 2,
+            PrintDirection::TopToBottom => 3,
+        }
+    }
+
+    /// Whether lines run along the paper rather than across it, so that
+    /// positions along the line are measured in vertical motion units
+    pub fn is_rotated(&self) -> bool {
+        matches!(
+            self,
+            PrintDirection::BottomToTop | PrintDirection::TopToBottom
+        )
+    }
+}
+
+impl FromStr for PrintDirection {
+    type Err = Error;
+
+    /// Accepts `0`-`3` as well as the names, e.g. `bottom-to-top`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('_', "-").as_ref() {
+            "0" | "left-to-right" => Ok(PrintDirection::LeftToRight),
+            "1" | "bottom-to-top" => Ok(PrintDirection::BottomToTop),
+            "2" | "right-to-left" => Ok(PrintDirection::RightToLeft),
+            "3" | "top-to-bottom" => Ok(PrintDirection::TopToBottom),
+            _ => Err(Error::InvalidArgument),
+        }
+    }
+}
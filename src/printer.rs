@@ -1,6 +1,6 @@
+use std::collections::HashSet;
 use std::io;
 
-use std::collections::VecDeque;
 use std::time::Duration;
 
 use byteorder::{LittleEndian, WriteBytesExt};
@@ -8,12 +8,39 @@ use encoding::all::UTF_8;
 use encoding::types::{EncoderTrap, EncodingRef};
 
 use crate::barcode::*;
+use crate::cjk::{self, CjkEncoding};
+use crate::code2d::{
+    AztecOptions, Code2dCommand, DataMatrixOptions, MaxiCodeOptions, Pdf417Options, QrModel,
+    QrOptions,
+};
+use crate::codepage::{self, CodePage, Fallback};
 use crate::consts;
-use crate::img::Image;
+use crate::device::{Transport, Usb};
+use crate::document::Document;
+use crate::glyph::{self, Glyph};
+use crate::img::{BitImageDensity, Image, RasterMode};
+use crate::markup::Markup;
+use crate::page::PrintDirection;
+use crate::profile::Profile;
+use crate::render::TrueTypeFont;
+use crate::table::Table;
+use crate::template::Template;
+use crate::text::{Alignment, Control, Emphasis, TextFont, TextMode, Underline};
+use crate::units::{self, Length};
+use crate::wrap;
 
 /// Timeout for sending/receiving USB messages
 pub const TIMEOUT: u64 = 400;
 
+/// Number of bytes [Printer] collects before sending them to the printer
+pub const BUFFER_SIZE: usize = 4096;
+
+/// How many times a transfer that timed out is retried before giving up
+pub const WRITE_RETRIES: usize = 10;
+
+/// Time given to the printer to drain its receive buffer between retries
+pub const RETRY_DELAY: u64 = 50;
+
 // SNBC
 // First Byte
 const OFFLINE_BIT: u8 = 3;
@@ -57,6 +84,9 @@ pub enum Error {
     #[error("IO error: {:?}", 0)]
     Io(std::io::Error),
 
+    #[error("Serial error: {0}")]
+    Serial(serialport::Error),
+
     #[error("Invalid device index")]
     InvalidIndex,
 
@@ -77,6 +107,18 @@ pub enum Error {
 
     #[error("Unsupported printer")]
     Unsupported,
+
+    #[error("Not a TrueType or OpenType font")]
+    InvalidFont,
+
+    #[error("Barcode error: {0}")]
+    Barcode(crate::barcode::BarcodeError),
+
+    #[error("Only available in page mode")]
+    NotInPageMode,
+
+    #[error("Markup error: {0}")]
+    Markup(crate::markup::MarkupError),
 }
 
 #[derive(std::cmp::Eq, thiserror::Error, Clone, Copy, Hash, Debug, PartialEq)]
@@ -112,18 +154,81 @@ pub enum StatusError {
     PaperEnd,
 }
 
+impl Error {
+    /// Whether the error means the printer didn't take the data in time, as
+    /// opposed to something being broken
+    pub fn is_timeout(&self) -> bool {
+        match self {
+            Error::Timeout => true,
+            Error::Usb(e) => *e == rusb::Error::Timeout,
+            Error::Io(e) => matches!(
+                e.kind(),
+                io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+            ),
+            _ => false,
+        }
+    }
+}
+
 impl From<std::io::Error> for Error {
     fn from(e: std::io::Error) -> Self {
         Error::Io(e)
     }
 }
 
+impl From<crate::barcode::BarcodeError> for Error {
+    fn from(e: crate::barcode::BarcodeError) -> Self {
+        Error::Barcode(e)
+    }
+}
+
 impl From<rusb::Error> for Error {
     fn from(e: rusb::Error) -> Self {
         Error::Usb(e)
     }
 }
 
+impl From<serialport::Error> for Error {
+    fn from(e: serialport::Error) -> Self {
+        Error::Serial(e)
+    }
+}
+
+/// Text formatting the printer has been told to use, tracked so text can be
+/// laid out to fit the line
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextState {
+    pub font: TextFont,
+    /// Character width magnification, 1-8
+    pub width: u8,
+    /// Character height magnification, 1-8
+    pub height: u8,
+    pub alignment: Alignment,
+    /// Left margin in dots
+    pub left_margin: u16,
+    /// Width of the print area in dots, `None` for the rest of the line
+    pub print_width: Option<u16>,
+    /// Space to the right of each character in dots, at 1x width
+    pub char_spacing: u16,
+    /// Horizontal and vertical motion units in 1/n inch, 0 for one dot
+    pub motion_units: (u8, u8),
+}
+
+impl Default for TextState {
+    fn default() -> Self {
+        TextState {
+            font: TextFont::A,
+            width: 1,
+            height: 1,
+            alignment: Alignment::Left,
+            left_margin: 0,
+            print_width: None,
+            char_spacing: 0,
+            motion_units: (0, 0),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct UsbInfo {
     /// vendor_id is the USB vendor id used when initializing the printer
@@ -140,27 +245,40 @@ pub struct UsbInfo {
     // pub serial: String,
 }
 
-/// Allows for printing to a [::device]
-pub struct Printer {
+/// Allows for printing to a [crate::device] through any [Transport]
+pub struct Printer<T: Transport = Usb> {
     codec: EncodingRef,
     trap: EncoderTrap,
     pub printer: SupportedPrinters,
-    device: rusb::Device<rusb::GlobalContext>,
-    handle: rusb::DeviceHandle<rusb::GlobalContext>,
-    descriptor: rusb::DeviceDescriptor,
+    profile: Profile,
+    state: TextState,
+    /// Code page text is encoded for, `None` to use `codec`
+    code_page: Option<CodePage>,
+    fallback: Fallback,
+    /// Encoding CJK text is sent in, `None` to leave it to `codec`
+    cjk: Option<CjkEncoding>,
+    /// Whether Kanji mode is on, `None` when it's not known
+    kanji_mode: Option<bool>,
+    /// Whether the printer is in page mode
+    page_mode: bool,
+    /// Print direction set for page mode
+    direction: PrintDirection,
+    /// User-defined characters uploaded for each font
+    user_chars: HashSet<(TextFont, u8)>,
+    /// Only `None` once [Printer::into_transport] has taken it
+    device: Option<T>,
     timeout: Duration,
-
-    /// USB Vendor ID
-    vid: u16,
-    /// USB Product ID
-    pid: u16,
-    /// USB Command Endpoint (output)
-    cmd_ep: u8,
-    /// USB Status Endpoint (input)
-    stat_ep: u8,
+    /// Commands waiting to be sent
+    buffer: Vec<u8>,
+    /// Size at which the buffer gets flushed on its own
+    buffer_size: usize,
+    /// Size of the printer's own receive buffer
+    receive_buffer: Option<usize>,
+    write_retries: usize,
+    retry_delay: Duration,
 }
 
-impl Printer {
+impl Printer<Usb> {
     pub fn get_mfg_info() -> Result<(SupportedPrinters, u16, u16), Box<dyn std::error::Error>> {
         for device in rusb::devices().unwrap().iter() {
             let timeout = Duration::from_millis(200);
@@ -206,6 +324,8 @@ impl Printer {
             "Error no supported printers found",
         )))
     }
+
+    /// Opens the USB printer identified by `vid` and `pid`
     pub fn new(
         codec: Option<EncodingRef>,
         trap: Option<EncoderTrap>,
@@ -213,151 +333,373 @@ impl Printer {
         vid: u16,
         pid: u16,
     ) -> Result<Self, Error> {
-        // Iterate over the devices to find the printer
-        let mut matches: VecDeque<_> = rusb::devices()?
+        let device = Usb::new(vid, pid)?;
+        Ok(Printer::with_transport(codec, trap, printer, device))
+    }
+
+    pub fn release(&mut self) -> Result<(), Error> {
+        self.transport_mut().release()
+    }
+
+    pub fn info(&mut self) -> Result<UsbInfo, Error> {
+        self.transport_mut().info()
+    }
+}
+
+impl<T: Transport> Printer<T> {
+    /// Creates a printer that sends its commands through `device`
+    ///
+    /// # Example
+    /// ```rust
+    /// use posify::device::File;
+    /// use posify::printer::{Printer, SupportedPrinters};
+    ///
+    /// let device = File::from(Vec::new());
+    /// let mut printer = Printer::with_transport(None, None, SupportedPrinters::P3, device);
+    /// printer.chain_hwinit().unwrap().chain_println("Hello").unwrap();
+    /// ```
+    pub fn with_transport(
+        codec: Option<EncodingRef>,
+        trap: Option<EncoderTrap>,
+        printer: SupportedPrinters,
+        device: T,
+    ) -> Self {
+        Printer {
+            codec: codec.unwrap_or(UTF_8 as EncodingRef),
+            trap: trap.unwrap_or(EncoderTrap::Replace),
+            printer,
+            receive_buffer: printer.profile().receive_buffer,
+            profile: printer.profile(),
+            state: TextState::default(),
+            code_page: None,
+            fallback: Fallback::default(),
+            cjk: None,
+            kanji_mode: None,
+            page_mode: false,
+            direction: PrintDirection::default(),
+            user_chars: HashSet::new(),
+            device: Some(device),
+            timeout: Duration::from_millis(TIMEOUT),
+            buffer: Vec::with_capacity(BUFFER_SIZE),
+            buffer_size: BUFFER_SIZE,
+            write_retries: WRITE_RETRIES,
+            retry_delay: Duration::from_millis(RETRY_DELAY),
+        }
+    }
+
+    /// Capabilities and paper geometry the printer is driven with
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    /// Replaces the profile that came with [SupportedPrinters], e.g. for a
+    /// printer loaded with narrower paper
+    pub fn set_profile(&mut self, profile: Profile) {
+        self.receive_buffer = profile.receive_buffer;
+        self.profile = profile;
+    }
+
+    /// Sets the code pages the printer has and their ESC t numbers, e.g.
+    /// [crate::codepage::EPSON_CODE_PAGES], in the order they're tried when
+    /// a character isn't in the current one
+    pub fn set_code_pages(&mut self, pages: &'static [(CodePage, u8)]) {
+        self.profile.code_pages = pages;
+    }
+
+    /// Formatting currently in effect on the printer
+    pub fn text_state(&self) -> TextState {
+        self.state
+    }
+
+    /// Sets how characters missing from the current code page are printed
+    pub fn set_fallback(&mut self, fallback: Fallback) {
+        self.fallback = fallback;
+    }
+
+    pub fn fallback(&self) -> &Fallback {
+        &self.fallback
+    }
+
+    /// Number of characters that fit on a line with the current font,
+    /// character width and spacing, between the margins. Fails with
+    /// [Error::Unsupported] if the profile doesn't know the paper width or
+    /// the size of the font.
+    pub fn chars_per_line(&self) -> Result<usize, Error> {
+        let metrics = self
+            .profile
+            .font(self.state.font)
+            .ok_or(Error::Unsupported)?;
+        let line = self
+            .profile
+            .dots_per_line
+            .ok_or(Error::Unsupported)?
+            .saturating_sub(self.state.left_margin);
+        let area = self.state.print_width.map_or(line, |w| w.min(line)) as usize;
+        let cell = (metrics.width as usize + self.state.char_spacing as usize)
+            * self.state.width.max(1) as usize;
+        Ok(area / cell)
+    }
+
+    /// Whether the printer is in page mode
+    pub fn in_page_mode(&self) -> bool {
+        self.page_mode
+    }
+
+    /// Codes of the user-defined characters uploaded for `font`, in order
+    pub fn defined_chars(&self, font: TextFont) -> Vec<u8> {
+        let mut codes: Vec<u8> = self
+            .user_chars
             .iter()
-            // Filter out the devices that match the vendor_id and product_id (should only be 1)
-            .filter_map(|d| {
-                let desc = match d.device_descriptor() {
-                    Ok(d) => d,
-                    Err(_) => {
-                        return None;
-                    }
-                };
-                if desc.vendor_id() == vid && desc.product_id() == pid {
-                    Some((d, desc))
-                } else {
-                    None
-                }
-            })
+            .filter(|(f, _)| *f == font)
+            .map(|(_, code)| *code)
             .collect();
-        let (device, descriptor) = match matches.pop_front() {
-            Some((device, descriptor)) => (device, descriptor),
-            None => return Err(Error::NotFound),
-        };
+        codes.sort_unstable();
+        codes
+    }
 
-        let mut handle = device.open()?;
+    /// Converts `length` to the current horizontal motion units
+    fn horizontal_units(&self, length: Length) -> i32 {
+        units::dots_to_units(
+            length.dots(self.profile.dots_per_mm),
+            self.state.motion_units.0,
+            self.profile.dots_per_mm,
+        )
+    }
 
-        let _ = handle.set_auto_detach_kernel_driver(true);
-        handle.claim_interface(0).expect("Cannot claim_interface");
+    /// Converts `length` to the current vertical motion units
+    fn vertical_units(&self, length: Length) -> i32 {
+        units::dots_to_units(
+            length.dots(self.profile.dots_per_mm),
+            self.state.motion_units.1,
+            self.profile.dots_per_mm,
+        )
+    }
 
-        let config_desc = match device.config_descriptor(0) {
-            Ok(v) => v,
-            Err(e) => {
-                return Err(e.into());
-            }
-        };
+    /// Converts a distance along the line to motion units. In page mode,
+    /// lines printed up or down the paper use the vertical unit.
+    fn line_units(&self, length: Length) -> i32 {
+        if self.page_mode && self.direction.is_rotated() {
+            self.vertical_units(length)
+        } else {
+            self.horizontal_units(length)
+        }
+    }
 
-        let interface = match config_desc.interfaces().next() {
-            Some(x) => x,
-            None => {
-                return Err(Error::InvalidEndpoints);
-            }
-        };
+    /// Returns the transport the printer is sending commands through
+    pub fn transport(&self) -> &T {
+        self.device.as_ref().expect("transport taken")
+    }
 
-        let (mut cmd_ep, mut stat_ep) = (None, None);
+    pub fn transport_mut(&mut self) -> &mut T {
+        self.device.as_mut().expect("transport taken")
+    }
 
-        for interface_desc in interface.descriptors() {
-            for endpoint_desc in interface_desc.endpoint_descriptors() {
-                match (endpoint_desc.transfer_type(), endpoint_desc.direction()) {
-                    (rusb::TransferType::Bulk, rusb::Direction::In) => {
-                        stat_ep = Some(endpoint_desc.address())
-                    }
-                    (rusb::TransferType::Bulk, rusb::Direction::Out) => {
-                        cmd_ep = Some(endpoint_desc.address())
-                    }
-                    (_, _) => continue,
-                }
-            }
+    /// Flushes anything still buffered and returns the transport
+    pub fn into_transport(mut self) -> Result<T, Error> {
+        self.flush()?;
+        Ok(self.device.take().expect("transport taken"))
+    }
+
+    /// Sets how many bytes of commands are collected before they're sent to
+    /// the printer without an explicit [Printer::flush]. A size of 0 sends
+    /// every command straight away.
+    pub fn set_buffer_size(&mut self, size: usize) -> Result<(), Error> {
+        self.buffer_size = size;
+        if self.buffer.len() >= size {
+            self.flush_buffer()?;
         }
+        Ok(())
+    }
 
-        let (cmd_ep, stat_ep) = match (cmd_ep, stat_ep) {
-            (Some(cmd), Some(stat)) => (cmd, stat),
-            _ => {
-                return Err(Error::InvalidEndpoints);
-            }
-        };
+    pub fn buffer_size(&self) -> usize {
+        self.buffer_size
+    }
 
-        match handle.kernel_driver_active(interface.number())? {
-            true => {
-                handle.detach_kernel_driver(interface.number())?;
-            }
-            false => {
-                log::trace!("Kernel driver inactive");
-            }
-        }
-        let _ = handle.claim_interface(interface.number());
+    /// Tells the printer how big the device's receive buffer is so transfers
+    /// and raster images are split into pieces it can take in
+    pub fn set_receive_buffer(&mut self, size: usize) {
+        self.receive_buffer = Some(size.max(1));
+    }
 
-        Ok(Printer {
-            // file,
-            codec: codec.unwrap_or(UTF_8 as EncodingRef),
-            trap: trap.unwrap_or(EncoderTrap::Replace),
-            printer,
-            device,
-            handle,
-            descriptor,
-            timeout: Duration::from_millis(TIMEOUT),
-            vid,
-            pid,
-            cmd_ep,
-            stat_ep,
-        })
+    /// Size of the device's receive buffer, `None` if it isn't known
+    pub fn receive_buffer(&self) -> Option<usize> {
+        self.receive_buffer
     }
 
-    pub fn release(&mut self) -> Result<(), Error> {
-        let config_desc = match self.device.config_descriptor(0) {
-            Ok(v) => v,
-            Err(e) => {
-                return Err(e.into());
-            }
-        };
+    /// Sets how many times a transfer that timed out or only went through
+    /// partially is retried, waiting `delay` before each retry
+    pub fn set_write_retries(&mut self, retries: usize, delay: Duration) {
+        self.write_retries = retries;
+        self.retry_delay = delay;
+    }
 
-        let interface = match config_desc.interfaces().next() {
-            Some(x) => x,
-            None => {
-                return Err(Error::InvalidEndpoints);
-            }
-        };
-        let _ = self.handle.release_interface(interface.number());
-        let _ = self.handle.release_interface(0);
-        Ok(())
+    /// Sets how long to wait for the printer to answer a query
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
     }
 
-    pub fn info(&mut self) -> Result<UsbInfo, Error> {
-        let languages = self.handle.read_languages(self.timeout)?;
-        let language = languages[0];
-
-        let manufacturer = self
-            .handle
-            .read_manufacturer_string(language, &self.descriptor, self.timeout)
-            .unwrap_or("".to_string());
-        let product = self
-            .handle
-            .read_product_string(language, &self.descriptor, self.timeout)
-            .unwrap_or("".to_string());
-        Ok(UsbInfo {
-            vendor_id: self.vid,
-            product_id: self.pid,
-            manufacturer,
-            product,
-        })
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    pub fn chain_asb(&mut self, n: u8) -> Result<&mut Self, Error> {
+        self.asb(n).map(|_| self)
+    }
+
+    /// GS a n - Enable/disable Automatic Status Back
+    ///
+    /// `n` picks the status changes the printer reports by itself, 0 turns
+    /// ASB off. The transport is told so it only looks for ASB messages in
+    /// what the printer sends while ASB is on.
+    ///
+    /// ASCII    GS   a  n
+    /// Hex      1d  61  n
+    /// Decimal  29  97  n
+    pub fn asb(&mut self, n: u8) -> Result<usize, Error> {
+        let n_bytes = self.write(&[0x1d, 0x61, n])?;
+        self.transport_mut().set_asb(n != 0);
+        Ok(n_bytes)
+    }
+
+    /// Returns the latest Automatic Status Back message pushed by the printer,
+    /// or `None` if there hasn't been one since the last call.
+    ///
+    /// ASB has to be enabled on the printer first with [Printer::asb].
+    pub fn asb_status(&mut self) -> Result<Option<[u8; 4]>, Error> {
+        self.flush()?;
+        let timeout = self.timeout;
+        self.transport_mut().read_asb(timeout)
+    }
+
+    /// Sends a [Document] that was built ahead of time in one go. Fails
+    /// with [Error::InvalidArgument] if the document was built for another
+    /// printer, as it may use commands this one doesn't take.
+    pub fn print_document(&mut self, document: &Document) -> Result<usize, Error> {
+        if document.printer() != self.printer {
+            return Err(Error::InvalidArgument);
+        }
+        self.print_document_unchecked(document)
+    }
+
+    /// Sends a [Document] in one go whichever printer it was built for, for
+    /// printers known to take the same commands
+    pub fn print_document_unchecked(&mut self, document: &Document) -> Result<usize, Error> {
+        let n = self.write(document.as_bytes())?;
+        self.flush()?;
+        Ok(n)
+    }
+
+    /// Flushes and closes the underlying transport
+    pub fn close(&mut self) -> Result<(), Error> {
+        self.flush()?;
+        self.transport_mut().close()
     }
 
     // --------------------------------------------------
 
-    fn encode(&mut self, content: &str) -> io::Result<Vec<u8>> {
-        self.codec
-            .encode(content, self.trap)
-            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))
+    fn encode(&mut self, content: &str) -> Result<Vec<u8>, Error> {
+        let encoding = match self.cjk {
+            Some(encoding) => encoding.encoding(),
+            None => return self.encode_single_byte(content),
+        };
+        let mut out = Vec::with_capacity(content.len());
+        for (cjk, run) in cjk::runs(content) {
+            if !run.is_ascii() && self.kanji_mode != Some(cjk) {
+                out.extend_from_slice(if cjk { &[0x1c, 0x26] } else { &[0x1c, 0x2e] });
+                self.kanji_mode = Some(cjk);
+            }
+            if cjk {
+                out.extend(
+                    encoding.encode(run, self.trap).map_err(|err| {
+                        io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+                    })?,
+                );
+            } else {
+                out.extend(self.encode_single_byte(run)?);
+            }
+        }
+        Ok(out)
+    }
+
+    fn encode_single_byte(&mut self, content: &str) -> Result<Vec<u8>, Error> {
+        match self.code_page.as_mut() {
+            Some(current) => codepage::encode(
+                content,
+                self.profile.code_pages,
+                current,
+                &self.fallback,
+                self.trap,
+            ),
+            None => Ok(self
+                .codec
+                .encode(content, self.trap)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?),
+        }
     }
 
+    /// Queues `buf` to be sent to the printer. Nothing is sent until the
+    /// buffer fills up or [Printer::flush] is called. Whatever is left is
+    /// sent when the printer is dropped, but errors are only logged then, so
+    /// flush first to find out if everything was printed.
     pub fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
-        let n_bytes = self.handle.write_bulk(self.cmd_ep, buf, self.timeout)?;
-        if n_bytes != buf.len() {
-            return Err(Error::Timeout);
+        self.buffer.extend_from_slice(buf);
+        if self.buffer.len() >= self.buffer_size {
+            self.flush_buffer()?;
         }
+        Ok(buf.len())
+    }
 
-        Ok(n_bytes)
+    /// Sends the buffered commands in chunks of the transport's max packet
+    /// size, never more than the printer's receive buffer at once.
+    ///
+    /// A printer with a full receive buffer stops accepting data, which shows
+    /// up as a timeout or a partial transfer. Those are retried after giving
+    /// the printer time to catch up. Anything that couldn't be sent stays in
+    /// the buffer.
+    fn flush_buffer(&mut self) -> Result<(), Error> {
+        let device = match self.device.as_mut() {
+            Some(device) => device,
+            None => return Ok(()),
+        };
+        let chunk_size = self
+            .receive_buffer
+            .map_or(device.max_packet_size(), |size| {
+                size.min(device.max_packet_size())
+            })
+            .max(1);
+        let mut sent = 0;
+        let mut retries = 0;
+        let mut res = Ok(());
+        while sent < self.buffer.len() {
+            let end = self.buffer.len().min(sent + chunk_size);
+            match device.write(&self.buffer[sent..end]) {
+                Ok(n) if n == end - sent => {
+                    sent += n;
+                    retries = 0;
+                    continue;
+                }
+                Ok(n) if n > 0 => {
+                    // The printer is still taking data, just slowly
+                    log::debug!("Partial transfer, {} of {} bytes", n, end - sent);
+                    sent += n;
+                    retries = 0;
+                }
+                Ok(_) => log::debug!("Nothing transferred"),
+                Err(e) if e.is_timeout() => log::debug!("Transfer timed out"),
+                Err(e) => {
+                    res = Err(e);
+                    break;
+                }
+            }
+            if retries >= self.write_retries {
+                res = Err(Error::Timeout);
+                break;
+            }
+            retries += 1;
+            device.pause(self.retry_delay);
+        }
+        self.buffer.drain(..sent);
+        res
     }
     // Old file based write
     // fn write2(&mut self, buf: &[u8]) -> io::Result<usize> {
@@ -377,10 +719,10 @@ impl Printer {
         self.write(wtr.as_slice())
     }
 
-    // Useful when using a file handler, probably not needed now
+    /// Sends everything buffered so far to the printer
     pub fn flush(&mut self) -> Result<(), Error> {
-        // self.file.flush()
-        Ok(())
+        self.flush_buffer()?;
+        self.transport_mut().flush()
     }
 
     /// ESC @ - Initialize printer, clear data in print buffer and set print mode
@@ -396,6 +738,22 @@ impl Printer {
     ///   - The macro definition is not cleared
     ///   - The NV bitmap data is not cleared (SNBC, not sure about P3)
     pub fn hwinit(&mut self) -> Result<usize, Error> {
+        self.state = TextState::default();
+        // Whether Kanji mode is on after initializing depends on the model
+        self.kanji_mode = None;
+        self.page_mode = false;
+        self.direction = PrintDirection::default();
+        self.user_chars.clear();
+        if self.code_page.is_some() {
+            // Initializing goes back to code page 0
+            self.code_page = self
+                .profile
+                .code_pages
+                .iter()
+                .find(|(_, n)| *n == 0)
+                .map(|(page, _)| *page)
+                .or(self.code_page);
+        }
         self.write(&[0x1b, 0x40])
     }
     pub fn chain_hwinit(&mut self) -> Result<&mut Self, Error> {
@@ -436,7 +794,6 @@ impl Printer {
     /// |     0x02     | Device disabled |
     ///
     /// Default: n = 0x01
-
     pub fn enable(&mut self) -> Result<usize, Error> {
         match self.printer {
             SupportedPrinters::SNBC => self.write(&[0x1b, 0x3d, 0x01]),
@@ -467,8 +824,10 @@ impl Printer {
     //     self.hwreset().map(|_| self)
     // }
 
+    /// Encodes `content` with the codec the printer was created with or, once
+    /// one has been selected with [Printer::code_page], for the printer's
+    /// code pages and sends it
     pub fn print(&mut self, content: &str) -> Result<usize, Error> {
-        // let rv = self.encode(content);
         let rv = self.encode(content)?;
         self.write(rv.as_slice())
     }
@@ -483,6 +842,67 @@ impl Printer {
         self.println(content).map(|_| self)
     }
 
+    /// Prints `content` word wrapped to [Printer::chars_per_line], one
+    /// [Printer::println] per line so the current alignment applies to each
+    pub fn print_wrapped(&mut self, content: &str) -> Result<usize, Error> {
+        let mut n_bytes = 0;
+        for line in wrap::wrap(content, self.chars_per_line()?)? {
+            n_bytes += self.println(&line)?;
+        }
+        Ok(n_bytes)
+    }
+    pub fn chain_print_wrapped(&mut self, content: &str) -> Result<&mut Self, Error> {
+        self.print_wrapped(content).map(|_| self)
+    }
+
+    /// Prints one row of `table` across [Printer::chars_per_line]
+    pub fn print_row(&mut self, table: &Table, cells: &[&str]) -> Result<usize, Error> {
+        let mut n_bytes = 0;
+        for line in table.layout(self.chars_per_line()?, cells)? {
+            n_bytes += self.println(&line)?;
+        }
+        Ok(n_bytes)
+    }
+    pub fn chain_print_row(&mut self, table: &Table, cells: &[&str]) -> Result<&mut Self, Error> {
+        self.print_row(table, cells).map(|_| self)
+    }
+
+    /// Prints `left` and `right` at either end of the line, wrapping `left`
+    /// if both don't fit
+    pub fn print_justified(&mut self, left: &str, right: &str) -> Result<usize, Error> {
+        self.print_row(&Table::justified(right), &[left, right])
+    }
+    pub fn chain_print_justified(&mut self, left: &str, right: &str) -> Result<&mut Self, Error> {
+        self.print_justified(left, right).map(|_| self)
+    }
+
+    /// Prints a receipt written in [crate::markup]. Errors from the printer
+    /// come back as [Error::Markup] with the position of the tag or text
+    /// that caused them.
+    pub fn print_markup(&mut self, markup: &Markup) -> Result<usize, Error> {
+        markup.print(self)
+    }
+    pub fn chain_print_markup(&mut self, markup: &Markup) -> Result<&mut Self, Error> {
+        self.print_markup(markup).map(|_| self)
+    }
+
+    /// Fills in `template` with `context` and prints it, with widths worked
+    /// out for the current font and character size
+    pub fn print_template(
+        &mut self,
+        template: &Template,
+        context: &serde_json::Value,
+    ) -> Result<usize, Error> {
+        template.print(self, context)
+    }
+    pub fn chain_print_template(
+        &mut self,
+        template: &Template,
+        context: &serde_json::Value,
+    ) -> Result<&mut Self, Error> {
+        self.print_template(template, context).map(|_| self)
+    }
+
     // TODO: This seems useless? just use print/println?
     pub fn text(&mut self, content: &str) -> Result<usize, Error> {
         self.println(content)
@@ -491,17 +911,37 @@ impl Printer {
         self.text(content).map(|_| self)
     }
 
-    pub fn underline_mode(&mut self, mode: Option<&str>) -> Result<usize, Error> {
-        let mode = mode.unwrap_or("OFF");
-        let mode_upper = mode.to_uppercase();
-        match mode_upper.as_ref() {
-            "OFF" => Ok(self.write(&[0x1b, 0x2d, 0x00])?),
-            "ON" => Ok(self.write(&[0x1b, 0x2d, 0x01])?),
-            "THICK" => Ok(self.write(&[0x1b, 0x2d, 0x02])?),
-            _ => Ok(self.write(&[0x1b, 0x2d, 0x00])?),
+    pub fn underline_mode(&mut self, mode: Underline) -> Result<usize, Error> {
+        match mode {
+            Underline::Off => self.write(consts::TXT_UNDERL_OFF),
+            Underline::On => self.write(consts::TXT_UNDERL_ON),
+            Underline::Thick => self.write(consts::TXT_UNDERL2_ON),
         }
     }
-    pub fn chain_underline_mode(&mut self, mode: Option<&str>) -> Result<&mut Self, Error> {
+    /// Renders `content` with `font` at `size` dots to the em and prints it
+    /// as a raster image, for text in scripts the printer has no code page
+    /// for. Anything wider than the paper is cut off. Fails with
+    /// [Error::Unsupported] if the profile doesn't know the paper width.
+    pub fn print_rendered(
+        &mut self,
+        content: &str,
+        font: &TrueTypeFont,
+        size: f32,
+    ) -> Result<usize, Error> {
+        let line = self.profile.dots_per_line.ok_or(Error::Unsupported)?;
+        let image = font.render(content, size, Some(line as u32))?;
+        self.raster(&image, RasterMode::Normal)
+    }
+    pub fn chain_print_rendered(
+        &mut self,
+        content: &str,
+        font: &TrueTypeFont,
+        size: f32,
+    ) -> Result<&mut Self, Error> {
+        self.print_rendered(content, font, size).map(|_| self)
+    }
+
+    pub fn chain_underline_mode(&mut self, mode: Underline) -> Result<&mut Self, Error> {
         self.underline_mode(mode).map(|_| self)
     }
 
@@ -519,12 +959,498 @@ impl Printer {
         Ok(n_bytes)
     }
 
-    pub fn chain_char_size(&mut self, height: u8) -> Result<&mut Self, Error> {
-        self.char_size(height).map(|_| self)
+    pub fn chain_code_page(&mut self, page: CodePage) -> Result<&mut Self, Error> {
+        self.code_page(page).map(|_| self)
+    }
+
+    /// ESC t n - Select character code table
+    ///
+    /// From here on text is encoded for the printer's code pages rather than
+    /// with the codec the printer was created with, starting with `page`.
+    /// When text has characters that `page` doesn't, the printer is switched
+    /// to another code page from its profile that has them, mid-line if need
+    /// be. Fails with [Error::Unsupported] if `page` isn't in the printer's
+    /// code pages, which are empty until [Printer::set_code_pages] is called.
+    ///
+    /// ASCII    ESC   t  n
+    /// Hex      1b   74  n
+    /// Decimal  27  116  n
+    pub fn code_page(&mut self, page: CodePage) -> Result<usize, Error> {
+        let n = match self.profile.code_pages.iter().find(|(p, _)| *p == page) {
+            Some((_, n)) => *n,
+            None => return Err(Error::Unsupported),
+        };
+        self.code_page = Some(page);
+        self.write(&[0x1b, 0x74, n])
+    }
+
+    pub fn chain_cjk_encoding(&mut self, encoding: CjkEncoding) -> Result<&mut Self, Error> {
+        self.cjk_encoding(encoding).map(|_| self)
+    }
+
+    /// FS C n - Select Kanji character code system
+    ///
+    /// From here on CJK characters are sent in `encoding`, and [Printer::print]
+    /// turns Kanji mode on before them and off again before other non-ASCII
+    /// text. Printers with the encoding fixed in firmware aren't sent
+    /// anything.
+    ///
+    /// ASCII    FS   C  n
+    /// Hex      1c  43  n
+    /// Decimal  28  67  n
+    pub fn cjk_encoding(&mut self, encoding: CjkEncoding) -> Result<usize, Error> {
+        let n = match self
+            .profile
+            .cjk_encodings
+            .iter()
+            .find(|(e, _)| *e == encoding)
+        {
+            Some((_, n)) => *n,
+            None => return Err(Error::Unsupported),
+        };
+        self.cjk = Some(encoding);
+        match n {
+            Some(n) => self.write(&[0x1c, 0x43, n]),
+            None => Ok(0),
+        }
+    }
+
+    pub fn chain_kanji_mode(&mut self, on: bool) -> Result<&mut Self, Error> {
+        self.kanji_mode(on).map(|_| self)
+    }
+
+    /// FS & - Select Kanji character mode
+    /// FS . - Cancel Kanji character mode
+    ///
+    /// Only needed for sending pre-encoded double-byte text with
+    /// [Printer::write], [Printer::print] switches on its own once
+    /// [Printer::cjk_encoding] has been called.
+    ///
+    /// ASCII    FS   &        FS   .
+    /// Hex      1c  26        1c  2e
+    /// Decimal  28  38        28  46
+    pub fn kanji_mode(&mut self, on: bool) -> Result<usize, Error> {
+        self.kanji_mode = Some(on);
+        if on {
+            self.write(&[0x1c, 0x26])
+        } else {
+            self.write(&[0x1c, 0x2e])
+        }
+    }
+
+    /// GS P x y - Set horizontal and vertical motion units
+    ///
+    /// Units are 1/x and 1/y inch, 0 goes back to the printer's default of
+    /// one dot. Distances passed to the positioning commands are converted
+    /// to these units.
+    ///
+    /// ASCII    GS   P  x  y
+    /// Hex      1d  50  x  y
+    /// Decimal  29  80  x  y
+    pub fn motion_units(&mut self, x: u8, y: u8) -> Result<usize, Error> {
+        self.state.motion_units = (x, y);
+        self.write(&[0x1d, 0x50, x, y])
+    }
+    pub fn chain_motion_units(&mut self, x: u8, y: u8) -> Result<&mut Self, Error> {
+        self.motion_units(x, y).map(|_| self)
+    }
+
+    /// GS L nL nH - Set left margin
+    ///
+    /// Only takes effect at the start of a line.
+    ///
+    /// ASCII    GS   L  nL  nH
+    /// Hex      1d  4c  nL  nH
+    /// Decimal  29  76  nL  nH
+    pub fn left_margin(&mut self, margin: Length) -> Result<usize, Error> {
+        let line = self.profile.dots_per_line.ok_or(Error::Unsupported)?;
+        let dots = margin.dots(self.profile.dots_per_mm);
+        if !(0..line as i32).contains(&dots) {
+            return Err(Error::InvalidArgument);
+        }
+        let units = self.horizontal_units(margin) as u16;
+        self.state.left_margin = dots as u16;
+        Ok(self.write(&[0x1d, 0x4c])? + self.write_u16le(units)?)
+    }
+    pub fn chain_left_margin(&mut self, margin: Length) -> Result<&mut Self, Error> {
+        self.left_margin(margin).map(|_| self)
+    }
+
+    /// GS W nL nH - Set print area width
+    ///
+    /// Only takes effect at the start of a line. The area is cut short if
+    /// it runs past the end of the line from the left margin.
+    ///
+    /// ASCII    GS   W  nL  nH
+    /// Hex      1d  57  nL  nH
+    /// Decimal  29  87  nL  nH
+    pub fn print_width(&mut self, width: Length) -> Result<usize, Error> {
+        let line = self.profile.dots_per_line.ok_or(Error::Unsupported)?;
+        let dots = width.dots(self.profile.dots_per_mm);
+        if !(1..=line as i32).contains(&dots) {
+            return Err(Error::InvalidArgument);
+        }
+        let units = self.horizontal_units(width) as u16;
+        self.state.print_width = Some(dots as u16);
+        Ok(self.write(&[0x1d, 0x57])? + self.write_u16le(units)?)
+    }
+    pub fn chain_print_width(&mut self, width: Length) -> Result<&mut Self, Error> {
+        self.print_width(width).map(|_| self)
+    }
+
+    /// ESC $ nL nH - Set absolute print position
+    ///
+    /// Moves to `position` from the left margin, or in page mode from the
+    /// start of the line in the print direction.
+    ///
+    /// ASCII    ESC   $  nL  nH
+    /// Hex      1b   24  nL  nH
+    /// Decimal  27   36  nL  nH
+    pub fn absolute_position(&mut self, position: Length) -> Result<usize, Error> {
+        let line = self.profile.dots_per_line.ok_or(Error::Unsupported)?;
+        let dots = position.dots(self.profile.dots_per_mm);
+        if dots < 0 || (!self.page_mode && dots >= line as i32) {
+            return Err(Error::InvalidArgument);
+        }
+        let units = u16::try_from(self.line_units(position)).map_err(|_| Error::InvalidArgument)?;
+        Ok(self.write(&[0x1b, 0x24])? + self.write_u16le(units)?)
+    }
+    pub fn chain_absolute_position(&mut self, position: Length) -> Result<&mut Self, Error> {
+        self.absolute_position(position).map(|_| self)
+    }
+
+    /// ESC \ nL nH - Set relative print position
+    ///
+    /// Moves `offset` right of the current position, or left if it's
+    /// negative.
+    ///
+    /// ASCII    ESC   \  nL  nH
+    /// Hex      1b   5c  nL  nH
+    /// Decimal  27   92  nL  nH
+    pub fn relative_position(&mut self, offset: Length) -> Result<usize, Error> {
+        let units = self.line_units(offset);
+        let units = i16::try_from(units).map_err(|_| Error::InvalidArgument)?;
+        Ok(self.write(&[0x1b, 0x5c])? + self.write_u16le(units as u16)?)
+    }
+    pub fn chain_relative_position(&mut self, offset: Length) -> Result<&mut Self, Error> {
+        self.relative_position(offset).map(|_| self)
+    }
+
+    /// ESC SP n - Set right-side character spacing
+    ///
+    /// The spacing is doubled along with the characters at 2x width.
+    ///
+    /// ASCII    ESC   SP  n
+    /// Hex      1b   20  n
+    /// Decimal  27   32  n
+    pub fn char_spacing(&mut self, spacing: Length) -> Result<usize, Error> {
+        let units =
+            u8::try_from(self.horizontal_units(spacing)).map_err(|_| Error::InvalidArgument)?;
+        self.state.char_spacing = units::units_to_dots(
+            units as i32,
+            self.state.motion_units.0,
+            self.profile.dots_per_mm,
+        ) as u16;
+        self.write(&[0x1b, 0x20, units])
+    }
+    pub fn chain_char_spacing(&mut self, spacing: Length) -> Result<&mut Self, Error> {
+        self.char_spacing(spacing).map(|_| self)
+    }
+
+    /// ESC D n1 ... nk NUL - Set horizontal tab positions
+    ///
+    /// Tab stops are given in characters from the start of the line, at
+    /// most 32 of them in ascending order. An empty list clears them all.
+    /// Move to the next one with [Control::HorizontalTab].
+    ///
+    /// ASCII    ESC   D  n1 ... nk  NUL
+    /// Hex      1b   44  n1 ... nk  00
+    /// Decimal  27   68  n1 ... nk  0
+    pub fn tab_stops(&mut self, columns: &[u8]) -> Result<usize, Error> {
+        let ascending = columns.windows(2).all(|pair| pair[0] < pair[1]);
+        if columns.len() > 32 || columns.contains(&0) || !ascending {
+            return Err(Error::InvalidArgument);
+        }
+        let mut cmd = vec![0x1b, 0x44];
+        cmd.extend_from_slice(columns);
+        cmd.push(0x00);
+        self.write(&cmd)
+    }
+    pub fn chain_tab_stops(&mut self, columns: &[u8]) -> Result<&mut Self, Error> {
+        self.tab_stops(columns).map(|_| self)
+    }
+
+    /// ESC L - Select page mode
+    ///
+    /// Everything printed from here on is collected in the print area set
+    /// with [Printer::page_area] until [Printer::print_page] or
+    /// [Printer::end_page].
+    ///
+    /// ASCII    ESC   L
+    /// Hex      1b   4c
+    /// Decimal  27   76
+    pub fn page_mode(&mut self) -> Result<usize, Error> {
+        self.page_mode = true;
+        self.write(&[0x1b, 0x4c])
+    }
+    pub fn chain_page_mode(&mut self) -> Result<&mut Self, Error> {
+        self.page_mode().map(|_| self)
+    }
+
+    /// ESC S - Select standard mode
+    ///
+    /// Goes back to standard mode without printing, throwing away anything
+    /// collected in page mode.
+    ///
+    /// ASCII    ESC   S
+    /// Hex      1b   53
+    /// Decimal  27   83
+    pub fn standard_mode(&mut self) -> Result<usize, Error> {
+        self.page_mode = false;
+        self.write(&[0x1b, 0x53])
+    }
+    pub fn chain_standard_mode(&mut self) -> Result<&mut Self, Error> {
+        self.standard_mode().map(|_| self)
+    }
+
+    /// ESC W xL xH yL yH dxL dxH dyL dyH - Set print area in page mode
+    ///
+    /// The area starts `x` across and `y` down the paper and is `width`
+    /// across and `height` down it, whatever the print direction.
+    ///
+    /// ASCII    ESC   W  xL xH yL yH dxL dxH dyL dyH
+    /// Hex      1b   57  xL xH yL yH dxL dxH dyL dyH
+    /// Decimal  27   87  xL xH yL yH dxL dxH dyL dyH
+    pub fn page_area(
+        &mut self,
+        x: Length,
+        y: Length,
+        width: Length,
+        height: Length,
+    ) -> Result<usize, Error> {
+        let dpmm = self.profile.dots_per_mm;
+        if x.dots(dpmm) < 0 || y.dots(dpmm) < 0 || width.dots(dpmm) < 1 || height.dots(dpmm) < 1 {
+            return Err(Error::InvalidArgument);
+        }
+        let area = [
+            self.horizontal_units(x),
+            self.vertical_units(y),
+            self.horizontal_units(width),
+            self.vertical_units(height),
+        ];
+        let mut n_bytes = self.write(&[0x1b, 0x57])?;
+        for units in area {
+            let units = u16::try_from(units).map_err(|_| Error::InvalidArgument)?;
+            n_bytes += self.write_u16le(units)?;
+        }
+        Ok(n_bytes)
+    }
+    pub fn chain_page_area(
+        &mut self,
+        x: Length,
+        y: Length,
+        width: Length,
+        height: Length,
+    ) -> Result<&mut Self, Error> {
+        self.page_area(x, y, width, height).map(|_| self)
+    }
+
+    /// ESC T n - Select print direction in page mode
+    ///
+    /// ASCII    ESC   T  n
+    /// Hex      1b   54  n
+    /// Decimal  27   84  n
+    pub fn page_direction(&mut self, direction: PrintDirection) -> Result<usize, Error> {
+        self.direction = direction;
+        self.write(&[0x1b, 0x54, direction.value()])
+    }
+    pub fn chain_page_direction(&mut self, direction: PrintDirection) -> Result<&mut Self, Error> {
+        self.page_direction(direction).map(|_| self)
+    }
+
+    /// GS $ nL nH - Set absolute vertical print position in page mode
+    ///
+    /// Moves to `position` from the start of the print area, at right angles
+    /// to the print direction.
+    ///
+    /// ASCII    GS   $  nL  nH
+    /// Hex      1d  24  nL  nH
+    /// Decimal  29  36  nL  nH
+    pub fn vertical_position(&mut self, position: Length) -> Result<usize, Error> {
+        if !self.page_mode {
+            return Err(Error::NotInPageMode);
+        }
+        let units = if self.direction.is_rotated() {
+            self.horizontal_units(position)
+        } else {
+            self.vertical_units(position)
+        };
+        let units = u16::try_from(units).map_err(|_| Error::InvalidArgument)?;
+        Ok(self.write(&[0x1d, 0x24])? + self.write_u16le(units)?)
+    }
+    pub fn chain_vertical_position(&mut self, position: Length) -> Result<&mut Self, Error> {
+        self.vertical_position(position).map(|_| self)
+    }
+
+    /// Moves to `x` along the line and `y` down from the start of the print
+    /// area, in the print direction, with [Printer::absolute_position] and
+    /// [Printer::vertical_position]. Text is printed with its baseline at
+    /// `y`, images and barcodes with their bottom edge there.
+    pub fn page_position(&mut self, x: Length, y: Length) -> Result<usize, Error> {
+        if !self.page_mode {
+            return Err(Error::NotInPageMode);
+        }
+        Ok(self.absolute_position(x)? + self.vertical_position(y)?)
+    }
+    pub fn chain_page_position(&mut self, x: Length, y: Length) -> Result<&mut Self, Error> {
+        self.page_position(x, y).map(|_| self)
+    }
+
+    /// ESC FF - Print data in page mode
+    ///
+    /// Prints the page and stays in page mode, with everything still in the
+    /// print area, e.g. to print several copies of a label.
+    ///
+    /// ASCII    ESC   FF
+    /// Hex      1b   0c
+    /// Decimal  27   12
+    pub fn print_page(&mut self) -> Result<usize, Error> {
+        if !self.page_mode {
+            return Err(Error::NotInPageMode);
+        }
+        self.write(&[0x1b, 0x0c])
+    }
+    pub fn chain_print_page(&mut self) -> Result<&mut Self, Error> {
+        self.print_page().map(|_| self)
+    }
+
+    /// FF - Print and return to standard mode
+    ///
+    /// ASCII    FF
+    /// Hex      0c
+    /// Decimal  12
+    pub fn end_page(&mut self) -> Result<usize, Error> {
+        if !self.page_mode {
+            return Err(Error::NotInPageMode);
+        }
+        self.page_mode = false;
+        self.write(consts::CTL_FF)
+    }
+    pub fn chain_end_page(&mut self) -> Result<&mut Self, Error> {
+        self.end_page().map(|_| self)
+    }
+
+    /// CAN - Cancel print data in page mode
+    ///
+    /// Clears the print area without printing it.
+    ///
+    /// ASCII    CAN
+    /// Hex      18
+    /// Decimal  24
+    pub fn cancel_page(&mut self) -> Result<usize, Error> {
+        if !self.page_mode {
+            return Err(Error::NotInPageMode);
+        }
+        self.write(&[0x18])
+    }
+    pub fn chain_cancel_page(&mut self) -> Result<&mut Self, Error> {
+        self.cancel_page().map(|_| self)
+    }
+
+    /// ESC & y c1 c2 [x d1 ... d(y * x)] ... - Define user-defined characters
+    ///
+    /// Uploads `glyphs` for the current font as the characters from `first`
+    /// on, which must all be between `' '` and `'~'`. Each glyph has to fit
+    /// the font's character cell and is sent as `y` bytes per column, where
+    /// `y` is the cell height in bytes. Fails with [Error::Unsupported] if the
+    /// profile doesn't know the size of the font.
+    ///
+    /// ASCII    ESC   &  y  c1  c2  [x  d1 ... d(y * x)] ...
+    /// Hex      1b   26  y  c1  c2  [x  d1 ... d(y * x)] ...
+    /// Decimal  27   38  y  c1  c2  [x  d1 ... d(y * x)] ...
+    pub fn define_chars(&mut self, first: u8, glyphs: &[Glyph]) -> Result<usize, Error> {
+        let font = self.state.font;
+        let metrics = self.profile.font(font).ok_or(Error::Unsupported)?;
+        let last = first as usize + glyphs.len();
+        if glyphs.is_empty() || first < glyph::FIRST_CHAR || last - 1 > glyph::LAST_CHAR as usize {
+            return Err(Error::InvalidArgument);
+        }
+        if glyphs
+            .iter()
+            .any(|g| g.width() > metrics.width || g.height() > metrics.height)
+        {
+            return Err(Error::InvalidArgument);
+        }
+        let rows = metrics.height.div_ceil(8);
+        let mut cmd = vec![0x1b, 0x26, rows, first, (last - 1) as u8];
+        for glyph in glyphs {
+            cmd.push(glyph.width());
+            cmd.extend(glyph.columns(rows));
+        }
+        let n_bytes = self.write(&cmd)?;
+        self.user_chars
+            .extend((first..=(last - 1) as u8).map(|code| (font, code)));
+        Ok(n_bytes)
+    }
+    pub fn chain_define_chars(&mut self, first: u8, glyphs: &[Glyph]) -> Result<&mut Self, Error> {
+        self.define_chars(first, glyphs).map(|_| self)
+    }
+
+    /// Uploads a single user-defined character for the current font, see
+    /// [Printer::define_chars]
+    pub fn define_char(&mut self, code: u8, glyph: &Glyph) -> Result<usize, Error> {
+        self.define_chars(code, std::slice::from_ref(glyph))
+    }
+    pub fn chain_define_char(&mut self, code: u8, glyph: &Glyph) -> Result<&mut Self, Error> {
+        self.define_char(code, glyph).map(|_| self)
+    }
+
+    /// ESC ? n - Cancel user-defined character
+    ///
+    /// Goes back to the built-in character for `code` in the current font.
+    ///
+    /// ASCII    ESC   ?  n
+    /// Hex      1b   3f  n
+    /// Decimal  27   63  n
+    pub fn cancel_char(&mut self, code: u8) -> Result<usize, Error> {
+        if !(glyph::FIRST_CHAR..=glyph::LAST_CHAR).contains(&code) {
+            return Err(Error::InvalidArgument);
+        }
+        self.user_chars.remove(&(self.state.font, code));
+        self.write(&[0x1b, 0x3f, code])
+    }
+    pub fn chain_cancel_char(&mut self, code: u8) -> Result<&mut Self, Error> {
+        self.cancel_char(code).map(|_| self)
+    }
+
+    /// ESC % n - Select/cancel user-defined character set
+    ///
+    /// While on, characters defined for the current font print as their
+    /// glyphs and all others as usual.
+    ///
+    /// ASCII    ESC   %  n
+    /// Hex      1b   25  n
+    /// Decimal  27   37  n
+    pub fn user_defined_chars(&mut self, on: bool) -> Result<usize, Error> {
+        self.write(&[0x1b, 0x25, u8::from(on)])
+    }
+    pub fn chain_user_defined_chars(&mut self, on: bool) -> Result<&mut Self, Error> {
+        self.user_defined_chars(on).map(|_| self)
     }
 
-    pub fn char_size(&mut self, height: u8) -> Result<usize, Error> {
-        self.write(&[0x1d, 0x21, height])
+    pub fn chain_char_size(&mut self, n: u8) -> Result<&mut Self, Error> {
+        self.char_size(n).map(|_| self)
+    }
+
+    /// GS ! n - Select character size from a raw byte
+    ///
+    /// Bits 4-6 are the width magnification minus one and bits 0-2 the height
+    /// magnification minus one, so 0x00 is normal size and 0x11 is double
+    /// width and height. Prefer [Printer::size].
+    pub fn char_size(&mut self, n: u8) -> Result<usize, Error> {
+        self.state.width = ((n >> 4) & 0x07) + 1;
+        self.state.height = (n & 0x07) + 1;
+        self.write(&[0x1d, 0x21, n])
     }
 
     /// ESC 2/ESC 3 n - Set line spacing
@@ -580,78 +1506,150 @@ impl Printer {
         self.feed(n).map(|_| self)
     }
 
-    pub fn chain_control(&mut self, ctrl: &str) -> Result<&mut Self, Error> {
+    pub fn chain_control(&mut self, ctrl: Control) -> Result<&mut Self, Error> {
         self.control(ctrl).map(|_| self)
     }
-    pub fn control(&mut self, ctrl: &str) -> Result<usize, Error> {
-        let ctrl_upper = ctrl.to_uppercase();
-        let ctrl_value = match ctrl_upper.as_ref() {
-            "LF" => consts::CTL_LF,
-            "FF" => consts::CTL_FF,
-            "CR" => consts::CTL_CR,
-            "HT" => consts::CTL_HT,
-            "VT" => consts::CTL_VT,
-            _ => return Err(Error::Unsupported),
+    pub fn control(&mut self, ctrl: Control) -> Result<usize, Error> {
+        let ctrl_value = match ctrl {
+            Control::LineFeed => consts::CTL_LF,
+            Control::FormFeed => consts::CTL_FF,
+            Control::CarriageReturn => consts::CTL_CR,
+            Control::HorizontalTab => consts::CTL_HT,
+            Control::VerticalTab => consts::CTL_VT,
         };
         self.write(ctrl_value)
     }
 
-    pub fn chain_align(&mut self, alignment: &str) -> Result<&mut Self, Error> {
+    pub fn chain_align(&mut self, alignment: Alignment) -> Result<&mut Self, Error> {
         self.align(alignment).map(|_| self)
     }
-    pub fn align(&mut self, alignment: &str) -> Result<usize, Error> {
-        let align_upper = alignment.to_uppercase();
-        let align_value = match align_upper.as_ref() {
-            "LT" => consts::TXT_ALIGN_LT,
-            "CT" => consts::TXT_ALIGN_CT,
-            "RT" => consts::TXT_ALIGN_RT,
-            _ => return Err(Error::InvalidArgument),
+    pub fn align(&mut self, alignment: Alignment) -> Result<usize, Error> {
+        self.state.alignment = alignment;
+        let align_value = match alignment {
+            Alignment::Left => consts::TXT_ALIGN_LT,
+            Alignment::Center => consts::TXT_ALIGN_CT,
+            Alignment::Right => consts::TXT_ALIGN_RT,
         };
         self.write(align_value)
     }
 
-    pub fn chain_font(&mut self, family: &str) -> Result<&mut Self, Error> {
+    pub fn chain_font(&mut self, family: TextFont) -> Result<&mut Self, Error> {
         self.font(family).map(|_| self)
     }
-    pub fn font(&mut self, family: &str) -> Result<usize, Error> {
-        let family_upper = family.to_uppercase();
-        let family_value = match family_upper.as_ref() {
-            "A" => consts::TXT_FONT_A,
-            "B" => consts::TXT_FONT_B,
-            "C" => consts::TXT_FONT_C,
-            _ => return Err(Error::InvalidArgument),
+    pub fn font(&mut self, family: TextFont) -> Result<usize, Error> {
+        self.state.font = family;
+        let family_value = match family {
+            TextFont::A => consts::TXT_FONT_A,
+            TextFont::B => consts::TXT_FONT_B,
+            TextFont::C => consts::TXT_FONT_C,
         };
         self.write(family_value)
     }
 
-    pub fn chain_style(&mut self, kind: &str) -> Result<&mut Self, Error> {
+    pub fn chain_style(&mut self, kind: Emphasis) -> Result<&mut Self, Error> {
         self.style(kind).map(|_| self)
     }
-    pub fn style(&mut self, kind: &str) -> Result<usize, Error> {
-        let kind_upper = kind.to_uppercase();
-        match kind_upper.as_ref() {
-            "B" => Ok(self.write(consts::TXT_UNDERL_OFF)? + self.write(consts::TXT_BOLD_ON)?),
-            "U" => Ok(self.write(consts::TXT_BOLD_OFF)? + self.write(consts::TXT_UNDERL_ON)?),
-            "U2" => Ok(self.write(consts::TXT_BOLD_OFF)? + self.write(consts::TXT_UNDERL2_ON)?),
-            "BU" => Ok(self.write(consts::TXT_BOLD_ON)? + self.write(consts::TXT_UNDERL_ON)?),
-            "BU2" => Ok(self.write(consts::TXT_BOLD_ON)? + self.write(consts::TXT_UNDERL2_ON)?),
-            // "NORMAL" | _ =>
-            _ => Ok(self.write(consts::TXT_BOLD_OFF)? + self.write(consts::TXT_UNDERL_OFF)?),
+    pub fn style(&mut self, kind: Emphasis) -> Result<usize, Error> {
+        let (bold, underline) = match kind {
+            Emphasis::Normal => (consts::TXT_BOLD_OFF, consts::TXT_UNDERL_OFF),
+            Emphasis::Bold => (consts::TXT_BOLD_ON, consts::TXT_UNDERL_OFF),
+            Emphasis::Underline => (consts::TXT_BOLD_OFF, consts::TXT_UNDERL_ON),
+            Emphasis::Underline2 => (consts::TXT_BOLD_OFF, consts::TXT_UNDERL2_ON),
+            Emphasis::BoldUnderline => (consts::TXT_BOLD_ON, consts::TXT_UNDERL_ON),
+            Emphasis::BoldUnderline2 => (consts::TXT_BOLD_ON, consts::TXT_UNDERL2_ON),
+        };
+        Ok(self.write(bold)? + self.write(underline)?)
+    }
+
+    /// Turns one of the [TextMode]s on or off, failing with
+    /// [Error::Unsupported] if the profile doesn't list it
+    pub fn text_mode(&mut self, mode: TextMode, on: bool) -> Result<usize, Error> {
+        if !self.profile.text_modes.contains(&mode) {
+            return Err(Error::Unsupported);
         }
+        let cmd = match (mode, on) {
+            (TextMode::Reverse, true) => consts::TXT_REVERSE_ON,
+            (TextMode::Reverse, false) => consts::TXT_REVERSE_OFF,
+            (TextMode::UpsideDown, true) => consts::TXT_UPSIDE_DOWN_ON,
+            (TextMode::UpsideDown, false) => consts::TXT_UPSIDE_DOWN_OFF,
+            (TextMode::Rotate, true) => consts::TXT_ROTATE_ON,
+            (TextMode::Rotate, false) => consts::TXT_ROTATE_OFF,
+            (TextMode::DoubleStrike, true) => consts::TXT_DOUBLE_STRIKE_ON,
+            (TextMode::DoubleStrike, false) => consts::TXT_DOUBLE_STRIKE_OFF,
+            (TextMode::Smoothing, true) => consts::TXT_SMOOTH_ON,
+            (TextMode::Smoothing, false) => consts::TXT_SMOOTH_OFF,
+        };
+        self.write(cmd)
+    }
+    pub fn chain_text_mode(&mut self, mode: TextMode, on: bool) -> Result<&mut Self, Error> {
+        self.text_mode(mode, on).map(|_| self)
+    }
+
+    /// GS B n - Turn white/black reverse print mode on/off
+    pub fn reverse(&mut self, on: bool) -> Result<usize, Error> {
+        self.text_mode(TextMode::Reverse, on)
+    }
+    pub fn chain_reverse(&mut self, on: bool) -> Result<&mut Self, Error> {
+        self.reverse(on).map(|_| self)
+    }
+
+    /// ESC { n - Turn upside-down print mode on/off
+    ///
+    /// Only takes effect at the start of a line
+    pub fn upside_down(&mut self, on: bool) -> Result<usize, Error> {
+        self.text_mode(TextMode::UpsideDown, on)
+    }
+    pub fn chain_upside_down(&mut self, on: bool) -> Result<&mut Self, Error> {
+        self.upside_down(on).map(|_| self)
+    }
+
+    /// ESC V n - Turn 90 degree clockwise rotation mode on/off
+    pub fn rotate(&mut self, on: bool) -> Result<usize, Error> {
+        self.text_mode(TextMode::Rotate, on)
+    }
+    pub fn chain_rotate(&mut self, on: bool) -> Result<&mut Self, Error> {
+        self.rotate(on).map(|_| self)
+    }
+
+    /// ESC G n - Turn double-strike mode on/off
+    pub fn double_strike(&mut self, on: bool) -> Result<usize, Error> {
+        self.text_mode(TextMode::DoubleStrike, on)
+    }
+    pub fn chain_double_strike(&mut self, on: bool) -> Result<&mut Self, Error> {
+        self.double_strike(on).map(|_| self)
+    }
+
+    /// GS b n - Turn smoothing mode on/off
+    ///
+    /// Smooths the edges of characters printed at 2x size and up
+    pub fn smoothing(&mut self, on: bool) -> Result<usize, Error> {
+        self.text_mode(TextMode::Smoothing, on)
+    }
+    pub fn chain_smoothing(&mut self, on: bool) -> Result<&mut Self, Error> {
+        self.smoothing(on).map(|_| self)
     }
 
-    pub fn chain_size(&mut self, width: usize, height: usize) -> Result<&mut Self, Error> {
+    pub fn chain_size(&mut self, width: u8, height: u8) -> Result<&mut Self, Error> {
         self.size(width, height).map(|_| self)
     }
-    pub fn size(&mut self, width: usize, height: usize) -> Result<usize, Error> {
-        let mut n = self.write(consts::TXT_NORMAL)?;
-        if width == 2 {
-            n += self.write(consts::TXT_2WIDTH)?;
-        }
-        if height == 2 {
-            n += self.write(consts::TXT_2HEIGHT)?;
+
+    /// GS ! n - Select character size
+    ///
+    /// Magnifies characters `width` times horizontally and `height` times
+    /// vertically, both from 1 up to the profile's `max_char_size`. Fails
+    /// with [Error::Unsupported] if the profile doesn't know how far the
+    /// printer magnifies. Use [Printer::chars_per_line] to find out how many
+    /// characters fit on a line afterwards.
+    ///
+    /// ASCII    GS   !  n
+    /// Hex      1d  21  n
+    /// Decimal  29  33  n
+    pub fn size(&mut self, width: u8, height: u8) -> Result<usize, Error> {
+        let max = self.profile.max_char_size.ok_or(Error::Unsupported)?;
+        if !(1..=max).contains(&width) || !(1..=max).contains(&height) {
+            return Err(Error::InvalidArgument);
         }
-        Ok(n)
+        self.char_size(((width - 1) << 4) | (height - 1))
     }
 
     pub fn chain_barcode(
@@ -675,116 +1673,243 @@ impl Printer {
         width: u8,
         height: u8,
     ) -> Result<usize, Error> {
-        let mut n = 0;
-        let mut bc = Barcode {
+        let barcode = Barcode {
             printer: self.printer,
             width,
             height,
             position,
             font,
             kind,
+            function: kind.default_function(),
+            check_digit: false,
         };
+        self.print_barcode(&barcode, code)
+    }
 
-        // Code128 requires the Code Set to be sent before the barcode text
-        //
-        // Currently we just default to Code B, but we might want to think about
-        // allowing the selection of the code set
-        //
-        // 128A (Code Set A) – ASCII characters 00 to 95 (0–9, A–Z and control codes), special characters, and FNC 1–4
-        // 128B (Code Set B) – ASCII characters 32 to 127 (0–9, A–Z, a–z), special characters, and FNC 1–4
-        // 128C (Code Set C) – 00–99 (encodes two digits with a single code point) and FNC1
-        // SNBC Also requires sending the number of bytes in the Code128 receipt
-        if kind == BarcodeType::Code128 && self.printer == SupportedPrinters::SNBC {
-            n += self.write(&bc.set_width()?)?;
-            n += self.write(&bc.set_height())?;
-            n += self.write(&bc.set_text_position())?;
-            n += self.write(&bc.set_font())?;
-            n += self.write(&bc.set_barcode_type())?;
-            let mut code128_bytes: Vec<u8> = vec![0x7b]; // Next byte will set the code set
-            if code.len() % 2 == 0 && code.chars().all(|x| x.is_ascii_digit()) {
-                // even number of chars and they are all numbers, we can use Code Set C
-                code128_bytes.push(0x43); // Codeset C
-                let mut converted: Vec<u8> = Barcode::to_codeset_c(code.to_string()).unwrap();
-                code128_bytes.append(&mut converted);
-            } else {
-                // otherwise we just push the characters which match up with Code Set B
-                code128_bytes.push(0x42); // Codeset B
-                for byte in code.as_bytes().iter() {
-                    code128_bytes.push(*byte);
-                }
-            }
-
-            let count = code128_bytes.len();
-            code128_bytes.insert(0, count as u8);
-            n += self.write(&code128_bytes)?;
-            return Ok(n);
-        } else if self.printer == SupportedPrinters::Epic {
-            n += self.write(&[
-                0x1D,
-                0x48,
-                0x02,
-                0x1D,
-                0x77,
-                0x02,
-                0x1D,
-                0x6B,
-                0x49,
-                code.len() as u8,
-            ])?;
-        } else {
+    pub fn chain_print_barcode(
+        &mut self,
+        barcode: &Barcode,
+        code: &str,
+    ) -> Result<&mut Self, Error> {
+        self.print_barcode(barcode, code).map(|_| self)
+    }
+    /// GS k - Print a 1D barcode set up by `barcode`, in the function A or B
+    /// form it asks for
+    ///
+    /// ASCII    GS   k  m  d1 ... dk  NUL   (function A)
+    /// Hex      1d  6b  m  d1 ... dk  00
+    /// Decimal  29 107  m  d1 ... dk  0
+    ///
+    /// ASCII    GS   k  m  n  d1 ... dn     (function B)
+    /// Hex      1d  6b  m  n  d1 ... dn
+    /// Decimal  29 107  m  n  d1 ... dn
+    pub fn print_barcode(&mut self, barcode: &Barcode, code: &str) -> Result<usize, Error> {
+        let mut bc = Barcode {
+            printer: self.printer,
+            ..*barcode
+        };
+        // Build everything first, so nothing is sent for a barcode that
+        // can't be printed
+        let mut cmd = bc.set_width()?.to_vec();
+        cmd.extend(bc.set_height());
+        cmd.extend(bc.set_text_position());
+        cmd.extend(bc.set_font());
+        cmd.extend(bc.set_barcode_type()?);
+        cmd.extend(bc.data(code)?);
+        self.write(&cmd)
+    }
+
+    /// Writes a GS ( k function for the symbology `cn`, with the parameter
+    /// length worked out
+    fn write_gs_k(&mut self, cn: u8, function: u8, params: &[u8]) -> Result<usize, Error> {
+        let length = u16::try_from(params.len() + 2).map_err(|_| Error::InvalidArgument)?;
+        let mut cmd = vec![0x1d, 0x28, 0x6b];
+        cmd.write_u16::<LittleEndian>(length)?;
+        cmd.extend([cn, function]);
+        cmd.extend_from_slice(params);
+        self.write(&cmd)
+    }
+
+    /// Sets up a 2D symbol with the GS ( k functions in `setup`, then stores
+    /// `data` with function 80 and prints it with function 81
+    fn write_symbol(
+        &mut self,
+        cn: u8,
+        setup: &[(u8, Vec<u8>)],
+        data: &[u8],
+    ) -> Result<usize, Error> {
+        if self.profile.code2d != Code2dCommand::GsParenK {
             return Err(Error::Unsupported);
         }
-
-        n += self.write(code.as_bytes())?;
-        n += self.write(&[0x00_u8])?; // Need to send NULL to finish
-
+        let mut n = 0;
+        for (function, params) in setup {
+            n += self.write_gs_k(cn, *function, params)?;
+        }
+        let mut store = vec![0x30];
+        store.extend_from_slice(data);
+        n += self.write_gs_k(cn, 0x50, &store)?;
+        n += self.write_gs_k(cn, 0x51, &[0x30])?;
         Ok(n)
     }
 
-    #[cfg(feature = "qrcode")]
-    pub fn chain_qrimage(&mut self) -> Result<&mut Self, Error> {
-        self.qrimage().map(|_| self)
+    #[cfg(feature = "qrcode_builder")]
+    pub fn chain_qrimage(&mut self, code: &str, width: u32) -> Result<&mut Self, Error> {
+        self.qrimage(code, width).map(|_| self)
     }
-    #[cfg(feature = "qrcode")]
-    pub fn qrimage(&mut self) -> Result<usize, Error> {
-        Ok(0)
+    /// Prints a QR Code drawn here as a raster image `width` dots wide, for
+    /// printers that can't make QR Codes themselves
+    #[cfg(feature = "qrcode_builder")]
+    pub fn qrimage(&mut self, code: &str, width: u32) -> Result<usize, Error> {
+        let image = Image::from_qr(code, width).map_err(|_| Error::InvalidArgument)?;
+        self.raster(&image, RasterMode::Normal)
+    }
+
+    pub fn chain_qrcode(&mut self, code: &str, options: &QrOptions) -> Result<&mut Self, Error> {
+        self.qrcode(code, options).map(|_| self)
+    }
+    /// Prints a QR Code, with the command the profile says the printer takes.
+    /// Codes are checked against what a QR Code can hold in their mode, from
+    /// [crate::code2d::QR_MAX_BYTES] bytes of text up to
+    /// [crate::code2d::QR_MAX_DATA] digits. A `version` in `options` is
+    /// only sent with ESC Z; GS ( k printers pick the version themselves and
+    /// fail with [Error::Unsupported] if one is given.
+    ///
+    /// GS ( k:
+    ///
+    /// ASCII    GS   (  k  pL pH cn fn  parameters
+    /// Hex      1d  28 6b  pL pH 31 fn  parameters
+    /// Decimal  29  40 107 pL pH 49 fn  parameters
+    ///
+    ///   - fn 65 (165): select the model
+    ///   - fn 67 (167): set the module size
+    ///   - fn 69 (169): select the error correction level
+    ///   - fn 80 (180): store the data
+    ///   - fn 81 (181): print the stored symbol
+    ///
+    /// ESC Z, after GS Z 2 selects QR Code:
+    ///
+    /// ASCII    ESC   Z  v  ecc  size  nL  nH  d1 ... dn
+    /// Hex      1b   5a  v  ecc  size  nL  nH  d1 ... dn
+    /// Decimal  27   90  v  ecc  size  nL  nH  d1 ... dn
+    pub fn qrcode(&mut self, code: &str, options: &QrOptions) -> Result<usize, Error> {
+        let data = options.data(code)?;
+        let mut n = 0;
+        match self.profile.code2d {
+            Code2dCommand::GsParenK => {
+                if options.version.is_some() {
+                    return Err(Error::Unsupported);
+                }
+                let setup = [
+                    (0x41, vec![options.model.value(), 0x00]),
+                    (0x43, vec![options.module_size]),
+                    (0x45, vec![options.error_correction.value()]),
+                ];
+                n += self.write_symbol(0x31, &setup, &data)?;
+            }
+            Code2dCommand::EscZ => {
+                if options.model != QrModel::Model2 {
+                    return Err(Error::Unsupported);
+                }
+                n += self.write(consts::TYPE_QR)?;
+                n += self.write(consts::CODE2D)?;
+                n += self.write(&options.esc_z())?;
+                n += self.write_u16le(data.len() as u16)?;
+                n += self.write(&data)?;
+            }
+        }
+        Ok(n)
     }
 
-    #[cfg(feature = "qrcode")]
-    pub fn chain_qrcode(
+    pub fn chain_pdf417(
         &mut self,
         code: &str,
-        version: Option<i32>,
-        level: &str,
-        size: Option<i32>,
+        options: &Pdf417Options,
     ) -> Result<&mut Self, Error> {
-        self.qrcode(code, version, level, size).map(|_| self)
+        self.pdf417(code, options).map(|_| self)
     }
-    #[cfg(feature = "qrcode")]
-    pub fn qrcode(
+    /// GS ( k cn=48 - Prints a PDF417 symbol
+    ///
+    /// ASCII    GS   (  k  pL pH cn fn  parameters
+    /// Hex      1d  28 6b  pL pH 30 fn  parameters
+    /// Decimal  29  40 107 pL pH 48 fn  parameters
+    ///
+    ///   - fn 65 (065): set the number of columns
+    ///   - fn 66 (066): set the number of rows
+    ///   - fn 67 (067): set the module width
+    ///   - fn 68 (068): set the row height
+    ///   - fn 69 (069): set the error correction level
+    ///   - fn 70 (070): select standard or truncated
+    ///   - fn 80 (080): store the data
+    ///   - fn 81 (081): print the stored symbol
+    pub fn pdf417(&mut self, code: &str, options: &Pdf417Options) -> Result<usize, Error> {
+        let setup = options.setup()?;
+        let data = options.data(code)?;
+        self.write_symbol(0x30, &setup, &data)
+    }
+
+    pub fn chain_maxicode(
         &mut self,
         code: &str,
-        version: Option<i32>,
-        level: &str,
-        size: Option<i32>,
-    ) -> Result<usize, Error> {
-        let level = level.to_uppercase();
-        let level_value = match level.as_ref() {
-            "M" => consts::QR_LEVEL_M,
-            "Q" => consts::QR_LEVEL_Q,
-            "H" => consts::QR_LEVEL_H,
-            // "L" | _ =>
-            _ => consts::QR_LEVEL_L,
-        };
-        let mut n = 0;
-        n += self.write(consts::TYPE_QR)?;
-        n += self.write(consts::CODE2D)?;
-        n += self.write_u8(version.unwrap_or(3) as u8)?;
-        n += self.write(level_value)?;
-        n += self.write_u8(size.unwrap_or(3) as u8)?;
-        n += self.write_u16le(code.len() as u16)?;
-        n += self.write(code.as_bytes())?;
-        Ok(n)
+        options: &MaxiCodeOptions,
+    ) -> Result<&mut Self, Error> {
+        self.maxicode(code, options).map(|_| self)
+    }
+    /// GS ( k cn=50 - Prints a MaxiCode symbol
+    ///
+    /// ASCII    GS   (  k  pL pH cn fn  parameters
+    /// Hex      1d  28 6b  pL pH 32 fn  parameters
+    /// Decimal  29  40 107 pL pH 50 fn  parameters
+    ///
+    ///   - fn 65 (265): select the mode
+    ///   - fn 80 (280): store the data
+    ///   - fn 81 (281): print the stored symbol
+    pub fn maxicode(&mut self, code: &str, options: &MaxiCodeOptions) -> Result<usize, Error> {
+        let setup = options.setup()?;
+        let data = options.data(code)?;
+        self.write_symbol(0x32, &setup, &data)
+    }
+
+    pub fn chain_aztec(&mut self, code: &str, options: &AztecOptions) -> Result<&mut Self, Error> {
+        self.aztec(code, options).map(|_| self)
+    }
+    /// GS ( k cn=53 - Prints an Aztec Code symbol
+    ///
+    /// ASCII    GS   (  k  pL pH cn fn  parameters
+    /// Hex      1d  28 6b  pL pH 35 fn  parameters
+    /// Decimal  29  40 107 pL pH 53 fn  parameters
+    ///
+    ///   - fn 66 (566): select full range or compact and the number of layers
+    ///   - fn 67 (567): set the module size
+    ///   - fn 69 (569): set the error correction level
+    ///   - fn 80 (580): store the data
+    ///   - fn 81 (581): print the stored symbol
+    pub fn aztec(&mut self, code: &str, options: &AztecOptions) -> Result<usize, Error> {
+        let setup = options.setup()?;
+        let data = options.data(code)?;
+        self.write_symbol(0x35, &setup, &data)
+    }
+
+    pub fn chain_datamatrix(
+        &mut self,
+        code: &str,
+        options: &DataMatrixOptions,
+    ) -> Result<&mut Self, Error> {
+        self.datamatrix(code, options).map(|_| self)
+    }
+    /// GS ( k cn=54 - Prints a DataMatrix symbol
+    ///
+    /// ASCII    GS   (  k  pL pH cn fn  parameters
+    /// Hex      1d  28 6b  pL pH 36 fn  parameters
+    /// Decimal  29  40 107 pL pH 54 fn  parameters
+    ///
+    ///   - fn 66 (666): select square or rectangle and the size
+    ///   - fn 67 (667): set the module size
+    ///   - fn 80 (680): store the data
+    ///   - fn 81 (681): print the stored symbol
+    pub fn datamatrix(&mut self, code: &str, options: &DataMatrixOptions) -> Result<usize, Error> {
+        let setup = options.setup()?;
+        let data = options.data(code)?;
+        self.write_symbol(0x36, &setup, &data)
     }
 
     pub fn chain_cashdraw(&mut self, pin: i32) -> Result<&mut Self, Error> {
@@ -826,7 +1951,8 @@ impl Printer {
             _ => Err(Error::Unsupported),
         };
         if self.printer == SupportedPrinters::Epic {
-            std::thread::sleep(std::time::Duration::new(3, 0));
+            self.flush()?;
+            self.transport_mut().pause(Duration::new(3, 0));
         }
         res
     }
@@ -834,28 +1960,21 @@ impl Printer {
     pub fn chain_bit_image(
         &mut self,
         image: &Image,
-        density: Option<&str>,
+        density: BitImageDensity,
     ) -> Result<&mut Self, Error> {
         self.bit_image(image, density).map(|_| self)
     }
-    pub fn bit_image(&mut self, image: &Image, density: Option<&str>) -> Result<usize, Error> {
-        let density = density.unwrap_or("d24");
-        let density_upper = density.to_uppercase();
-        let header = match density_upper.as_ref() {
-            "S8" => consts::BITMAP_S8,
-            "D8" => consts::BITMAP_D8,
-            "S24" => consts::BITMAP_S24,
-            // "D24" | _ =>
-            _ => consts::BITMAP_D24,
-        };
-        let n = if density == "s8" || density == "d8" {
-            1
-        } else {
-            3
+    pub fn bit_image(&mut self, image: &Image, density: BitImageDensity) -> Result<usize, Error> {
+        let header = match density {
+            BitImageDensity::S8 => consts::BITMAP_S8,
+            BitImageDensity::D8 => consts::BITMAP_D8,
+            BitImageDensity::S24 => consts::BITMAP_S24,
+            BitImageDensity::D24 => consts::BITMAP_D24,
         };
+        let n = density.dots() / 8;
         let mut n_bytes = 0;
         n_bytes += self.line_space(0)?;
-        for line in image.bitimage_lines(n * 8) {
+        for line in image.bitimage_lines(density.dots()) {
             n_bytes += self.write(header)?;
             n_bytes += self.write_u16le((line.len() / n as usize) as u16)?;
             n_bytes += self.write(line.as_ref())?;
@@ -864,26 +1983,30 @@ impl Printer {
         Ok(n_bytes)
     }
 
-    pub fn chain_raster(&mut self, image: &Image, mode: Option<&str>) -> Result<&mut Self, Error> {
+    pub fn chain_raster(&mut self, image: &Image, mode: RasterMode) -> Result<&mut Self, Error> {
         self.raster(image, mode).map(|_| self)
     }
-    pub fn raster(&mut self, image: &Image, mode: Option<&str>) -> Result<usize, Error> {
-        let mode_upper = mode.unwrap_or("NORMAL").to_uppercase();
-        let header = match mode_upper.as_ref() {
-            // Double Wide
-            "DW" => &[0x1d, 0x76, 0x30, 0x01],
-            // Double Height
-            "DH" => &[0x1d, 0x76, 0x30, 0x02],
-            // Quadruple
-            "QD" => &[0x1d, 0x76, 0x30, 0x03],
-            // "NORMAL" | _ =>
-            _ => &[0x1d, 0x76, 0x30, 0x00],
+    pub fn raster(&mut self, image: &Image, mode: RasterMode) -> Result<usize, Error> {
+        let header = match mode {
+            RasterMode::Normal => consts::S_RASTER_N,
+            RasterMode::DoubleWidth => consts::S_RASTER_2W,
+            RasterMode::DoubleHeight => consts::S_RASTER_2H,
+            RasterMode::Quadruple => consts::S_RASTER_Q,
         };
+        // Images bigger than the receive buffer are sent as a stack of bands
+        // so the printer never has to hold more than it has room for
+        let width = image.width.div_ceil(8) as usize;
+        let rows_per_band = self
+            .receive_buffer
+            .map_or(u16::MAX as usize, |size| size / width.max(1))
+            .clamp(1, u16::MAX as usize);
         let mut n_bytes = 0;
-        n_bytes += self.write(header)?;
-        n_bytes += self.write_u16le(((image.width + 7) / 8) as u16)?;
-        n_bytes += self.write_u16le(image.height as u16)?;
-        n_bytes += self.write(image.get_raster().as_ref())?;
+        for band in image.get_raster().chunks(width.max(1) * rows_per_band) {
+            n_bytes += self.write(header)?;
+            n_bytes += self.write_u16le(width as u16)?;
+            n_bytes += self.write_u16le((band.len() / width.max(1)) as u16)?;
+            n_bytes += self.write(band)?;
+        }
         Ok(n_bytes)
     }
 
@@ -892,9 +2015,7 @@ impl Printer {
             SupportedPrinters::P3 => {
                 self.write(&[0x1c, 0xea, 0x52])?;
                 let mut buffer = [0_u8; 16];
-                let _ = self
-                    .handle
-                    .read_bulk(self.stat_ep, &mut buffer, self.timeout)?;
+                self.read(&mut buffer)?;
                 let value = std::str::from_utf8(&buffer).unwrap();
                 Ok(value.to_string())
             }
@@ -903,22 +2024,18 @@ impl Printer {
     }
 
     pub fn get_cut_count(&mut self) -> Result<String, Error> {
-        self.write(&[0x1d, 0xe2]).unwrap();
+        self.write(&[0x1d, 0xe2])?;
         let mut buffer = [0_u8; 16]; // TODO: This is more than enough now... but what about as
                                      // cuts increase?
-        let _ = self
-            .handle
-            .read_bulk(self.stat_ep, &mut buffer, self.timeout)?;
+        self.read(&mut buffer)?;
         let value = std::str::from_utf8(&buffer).unwrap(); // This seems to trim the padding
         Ok(value.to_string())
     }
 
     pub fn get_rom_version(&mut self) -> Result<String, Error> {
-        self.write(&[0x1d, 0x49, 0x03]).unwrap();
+        self.write(&[0x1d, 0x49, 0x03])?;
         let mut buffer = [0_u8; 4];
-        let _ = self
-            .handle
-            .read_bulk(self.stat_ep, &mut buffer, self.timeout)?;
+        self.read(&mut buffer)?;
         let value = std::str::from_utf8(&buffer).unwrap();
         Ok(value.to_string())
     }
@@ -929,19 +2046,17 @@ impl Printer {
                 match self.write(&[0x1b, 0x7e, 0x5a]) {
                     Ok(_) => {
                         let mut buffer = [0_u8; 4];
-                        let _ = self
-                            .handle
-                            .read_bulk(self.stat_ep, &mut buffer, self.timeout)?;
+                        self.read(&mut buffer)?;
                         // Truncate the first two command bytes and read the remaining two as bits
                         let value = format!("{:b}{:b}", &buffer[2], &buffer[3]);
                         Ok(value.to_string())
-                    },
+                    }
                     e => {
                         println!("Error ecountered getting firmware_checksum: {:?}", e);
                         Err(Error::Timeout)
-                    },
+                    }
                 }
-            },
+            }
             _ => Err(Error::Unsupported),
         }
     }
@@ -952,50 +2067,42 @@ impl Printer {
                 match self.write(&[0x1b, 0x7e, 0x46]) {
                     Ok(_) => {
                         let mut buffer = [0_u8; 14];
-                        let _ = self
-                            .handle
-                            .read_bulk(self.stat_ep, &mut buffer, self.timeout)?;
+                        self.read(&mut buffer)?;
                         // Truncate the first two command bytes and terminator
-                        let firmware_id = &buffer[2..13]; 
-                        let value = std::str::from_utf8(&firmware_id).unwrap();
+                        let firmware_id = &buffer[2..13];
+                        let value = std::str::from_utf8(firmware_id).unwrap();
                         Ok(value.to_string())
-                    },
+                    }
                     e => {
                         println!("Error encountered getting firmware_id: {:?}", e);
                         Err(Error::Timeout)
                     }
                 }
-            },
+            }
             _ => Err(Error::Unsupported),
         }
     }
 
     pub fn get_power_count(&mut self) -> Result<String, Error> {
-        self.write(&[0x1d, 0xe5]).unwrap();
+        self.write(&[0x1d, 0xe5])?;
         let mut buffer = [0_u8; 8];
-        let _ = self
-            .handle
-            .read_bulk(self.stat_ep, &mut buffer, self.timeout)?;
+        self.read(&mut buffer)?;
         let value = std::str::from_utf8(&buffer).unwrap();
         Ok(value.to_string())
     }
 
     pub fn get_printed_length(&mut self) -> Result<String, Error> {
-        self.write(&[0x1d, 0xe3]).unwrap();
+        self.write(&[0x1d, 0xe3])?;
         let mut buffer = [0_u8; 8];
-        let _ = self
-            .handle
-            .read_bulk(self.stat_ep, &mut buffer, self.timeout)?;
+        self.read(&mut buffer)?;
         let value = std::str::from_utf8(&buffer).unwrap();
         Ok(value.to_string())
     }
 
     pub fn get_remaining_paper(&mut self) -> Result<String, Error> {
-        self.write(&[0x1d, 0xe1]).unwrap();
+        self.write(&[0x1d, 0xe1])?;
         let mut buffer = [0_u8; 8];
-        let _ = self
-            .handle
-            .read_bulk(self.stat_ep, &mut buffer, self.timeout)?;
+        self.read(&mut buffer)?;
         let value = std::str::from_utf8(&buffer).unwrap();
         Ok(value.to_string())
     }
@@ -1016,16 +2123,14 @@ impl Printer {
         // TODO: what should we pass in, length in meters and then calculate?
         let n_l: u8 = 0x00;
         let n_h: u8 = 0x00;
-        self.write(&[0x1d, 0xe6, n_h, n_l]).unwrap();
+        self.write(&[0x1d, 0xe6, n_h, n_l])?;
         Ok(())
     }
 
     pub fn paper_loaded(&mut self) -> Result<bool, Error> {
-        self.write(&[0x1d, 0x72, 0x01]).unwrap();
+        self.write(&[0x1d, 0x72, 0x01])?;
         let mut buffer = [0_u8; 1];
-        let _ = self
-            .handle
-            .read_bulk(self.stat_ep, &mut buffer, self.timeout)?;
+        self.read(&mut buffer)?;
         Ok(buffer[0] == 0x00_u8)
     }
 
@@ -1050,9 +2155,9 @@ impl Printer {
         match self.printer {
             SupportedPrinters::SNBC => {
                 if self.printer == SupportedPrinters::SNBC {
-                    match self.read(&mut buffer) {
-                        Ok(_) => (),
-                        Err(_) => {
+                    match self.asb_status() {
+                        Ok(Some(status)) => buffer[..4].copy_from_slice(&status),
+                        _ => {
                             errors.push(StatusError::Communication);
                             return Err(errors);
                         }
@@ -1097,15 +2202,11 @@ impl Printer {
                 let mut i: i32 = 0;
                 while i < 4 {
                     let cmd = [0x1B_u8, 0x40, 0x10, 0x04, (i + 1) as u8];
-                    match self.handle.write_bulk(self.cmd_ep, &cmd, self.timeout) {
+                    match self.write(&cmd).and_then(|_| self.flush()) {
                         Ok(_) => (),
                         Err(_) => errors.push(StatusError::Communication),
                     }
-                    match self.handle.read_bulk(
-                        self.stat_ep,
-                        &mut data_in[(i as usize)..],
-                        self.timeout,
-                    ) {
+                    match self.read(&mut data_in[(i as usize)..]) {
                         Ok(transferred) => {
                             if transferred != 1 {
                                 errors.push(StatusError::Communication);
@@ -1138,12 +2239,23 @@ impl Printer {
         Ok(())
     }
 
-    pub fn read(&mut self, buf: &mut [u8; 16]) -> Result<usize, Error> {
-        let transferred = self.handle.read_bulk(self.stat_ep, buf, self.timeout)?;
-        Ok(transferred)
+    /// Flushes any buffered commands and reads the printer's response
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.flush()?;
+        let timeout = self.timeout;
+        self.transport_mut().read(buf, timeout)
     }
 
     pub fn has_asb_capability(&self) -> bool {
         matches!(self.printer, SupportedPrinters::SNBC)
     }
 }
+
+impl<T: Transport> Drop for Printer<T> {
+    /// Sends anything still buffered, as nothing else would
+    fn drop(&mut self) {
+        if let Err(e) = self.flush_buffer() {
+            log::error!("Failed to send buffered commands: {}", e);
+        }
+    }
+}
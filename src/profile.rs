@@ -0,0 +1,196 @@
+//! Printer capabilities and paper geometry
+//!
+//! Every [SupportedPrinters] variant has a [Profile] describing how wide its
+//! paper is, the size of its fonts and what it can do. [crate::printer::Printer]
+//! uses it to validate arguments and to work out how many characters fit on
+//! a line. Override it with [crate::printer::Printer::set_profile], e.g. when
+//! a printer is loaded with 58mm paper.
+//!
+//! Values that haven't been checked against a model's manual are left as
+//! `None`, and whatever needs them fails with
+//! [crate::printer::Error::Unsupported] until they're filled in.
+
+use crate::cjk::{CjkEncoding, EPSON_CJK_ENCODINGS};
+use crate::code2d::Code2dCommand;
+use crate::codepage::CodePage;
+use crate::printer::SupportedPrinters;
+use crate::text::{TextFont, TextMode};
+
+/// Size of a single character cell in dots at 1x magnification
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FontMetrics {
+    pub width: u8,
+    pub height: u8,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Profile {
+    /// Printable width in dots, `None` if it isn't known
+    pub dots_per_line: Option<u16>,
+    /// Print resolution, 8 dots/mm is 203 dpi
+    pub dots_per_mm: u8,
+    /// `None` if the size of the font isn't known. It can still be selected,
+    /// but lines can't be measured in it and it can't have user-defined
+    /// characters.
+    pub font_a: Option<FontMetrics>,
+    pub font_b: Option<FontMetrics>,
+    pub font_c: Option<FontMetrics>,
+    /// Largest width/height magnification accepted by GS !, `None` if it
+    /// isn't known
+    pub max_char_size: Option<u8>,
+    /// Code pages the printer has and their ESC t numbers, in the order
+    /// they're tried when a character isn't in the current one. Empty in
+    /// every built-in profile as the numbers differ between vendors.
+    pub code_pages: &'static [(CodePage, u8)],
+    /// Text modes beyond bold and underline that the printer has
+    pub text_modes: &'static [TextMode],
+    /// Double-byte encodings the printer has and their FS C numbers
+    pub cjk_encodings: &'static [(CjkEncoding, Option<u8>)],
+    /// Command the printer takes for 2D symbols
+    pub code2d: Code2dCommand,
+    /// Size of the receive buffer in bytes. No single transfer or raster
+    /// band is made bigger than this. `None` if it isn't known, in which
+    /// case only the transport limits the size of a transfer.
+    pub receive_buffer: Option<usize>,
+}
+
+impl Profile {
+    pub fn font(&self, font: TextFont) -> Option<FontMetrics> {
+        match font {
+            TextFont::A => self.font_a,
+            TextFont::B => self.font_b,
+            TextFont::C => self.font_c,
+        }
+    }
+
+    /// Number of characters that fit on a line in `font` at `width`
+    /// magnification, `None` if the paper width or the size of the font
+    /// isn't known
+    pub fn chars_per_line(&self, font: TextFont, width: u8) -> Option<usize> {
+        let metrics = self.font(font)?;
+        let cell = metrics.width as usize * width.max(1) as usize;
+        Some(self.dots_per_line? as usize / cell)
+    }
+
+    /// Returns the same profile with the printable width set for a paper
+    /// roll that is `mm` wide
+    pub fn with_paper_width(&self, mm: u16) -> Profile {
+        let printable = match mm {
+            58 => 48,
+            80 => 72,
+            _ => mm.saturating_sub(8),
+        };
+        Profile {
+            dots_per_line: Some(printable * self.dots_per_mm as u16),
+            ..self.clone()
+        }
+    }
+}
+
+// The BTP-R880NPV takes 80mm paper with 72mm (576 dots) printable at 203 dpi,
+// and has 12x24 and 9x17 fonts that GS ! magnifies up to 8 times
+const SNBC_FONT_A: FontMetrics = FontMetrics {
+    width: 12,
+    height: 24,
+};
+const SNBC_FONT_B: FontMetrics = FontMetrics {
+    width: 9,
+    height: 17,
+};
+
+const ALL_TEXT_MODES: &[TextMode] = &[
+    TextMode::Reverse,
+    TextMode::UpsideDown,
+    TextMode::Rotate,
+    TextMode::DoubleStrike,
+    TextMode::Smoothing,
+];
+
+// Smoothing with GS b is an Epson command the Custom and TransAct command
+// references don't have
+const NO_SMOOTHING_TEXT_MODES: &[TextMode] = &[
+    TextMode::Reverse,
+    TextMode::UpsideDown,
+    TextMode::Rotate,
+    TextMode::DoubleStrike,
+];
+
+impl SupportedPrinters {
+    /// The profile the printer is driven with by default.
+    ///
+    /// The SNBC profile has the paper width, fonts and character sizes of
+    /// the BTP-R880NPV. The paper width, font sizes, character sizes and
+    /// receive buffer of the other printers haven't been checked, so they're
+    /// left unknown. Fill them in with [crate::printer::Printer::set_profile]
+    /// to measure and check text on those printers. None of the profiles
+    /// has code pages, see [crate::printer::Printer::set_code_pages].
+    pub fn profile(&self) -> Profile {
+        let profile = Profile {
+            dots_per_line: None,
+            dots_per_mm: 8,
+            font_a: None,
+            font_b: None,
+            font_c: None,
+            max_char_size: None,
+            code_pages: &[],
+            text_modes: ALL_TEXT_MODES,
+            cjk_encodings: EPSON_CJK_ENCODINGS,
+            code2d: Code2dCommand::GsParenK,
+            receive_buffer: None,
+        };
+        match self {
+            SupportedPrinters::SNBC => Profile {
+                dots_per_line: Some(576),
+                font_a: Some(SNBC_FONT_A),
+                font_b: Some(SNBC_FONT_B),
+                max_char_size: Some(8),
+                code2d: Code2dCommand::EscZ,
+                ..profile
+            },
+            SupportedPrinters::P3 | SupportedPrinters::Epic => Profile {
+                text_modes: NO_SMOOTHING_TEXT_MODES,
+                ..profile
+            },
+            SupportedPrinters::Unknown => profile,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chars_per_line() {
+        let profile = SupportedPrinters::SNBC.profile();
+        assert_eq!(profile.chars_per_line(TextFont::A, 1), Some(48));
+        assert_eq!(profile.chars_per_line(TextFont::B, 1), Some(64));
+        assert_eq!(profile.chars_per_line(TextFont::A, 2), Some(24));
+        assert_eq!(profile.chars_per_line(TextFont::C, 1), None);
+
+        let narrow = profile.with_paper_width(58);
+        assert_eq!(narrow.dots_per_line, Some(384));
+        assert_eq!(narrow.chars_per_line(TextFont::A, 1), Some(32));
+
+        // Nothing about the other printers' paper or fonts is assumed
+        for printer in [SupportedPrinters::P3, SupportedPrinters::Epic] {
+            let profile = printer.profile();
+            assert_eq!(profile.chars_per_line(TextFont::A, 1), None);
+            assert_eq!(profile.max_char_size, None);
+            assert_eq!(profile.receive_buffer, None);
+        }
+    }
+
+    #[test]
+    fn text_modes() {
+        assert!(SupportedPrinters::SNBC
+            .profile()
+            .text_modes
+            .contains(&TextMode::Smoothing));
+        for printer in [SupportedPrinters::P3, SupportedPrinters::Epic] {
+            let modes = printer.profile().text_modes;
+            assert!(modes.contains(&TextMode::Reverse));
+            assert!(!modes.contains(&TextMode::Smoothing));
+        }
+    }
+}
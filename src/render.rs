@@ -0,0 +1,173 @@
+//! Text rendered to a raster image with a TrueType font
+//!
+//! Scripts that no code page covers, such as Arabic, Hebrew, Thai or
+//! Devanagari, can still be printed by shaping the text with a TrueType or
+//! OpenType font and sending the result as a raster image. Fonts are loaded
+//! from local files or bytes, nothing is ever downloaded.
+//!
+//! # Example
+//! ```rust,no_run
+//! use posify::render::TrueTypeFont;
+//!
+//! let font = TrueTypeFont::from_path("/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf").unwrap();
+//! let image = font.render("שלום עולם", 32.0, Some(576)).unwrap();
+//! ```
+
+use std::fs;
+use std::path::Path;
+
+use ab_glyph::{Font, FontRef, GlyphId, PxScale, ScaleFont};
+use image::{DynamicImage, GrayImage, Luma};
+use rustybuzz::{Direction, UnicodeBuffer};
+use unicode_bidi::BidiInfo;
+
+use crate::img::Image;
+use crate::printer::Error;
+
+/// Coverage above which an anti-aliased pixel is printed
+const THRESHOLD: f32 = 0.5;
+
+/// A TrueType or OpenType font, checked when it's loaded
+pub struct TrueTypeFont {
+    data: Vec<u8>,
+}
+
+/// A glyph placed on a line, in dots from the start of the line
+struct Placed {
+    id: u16,
+    x: f32,
+    y: f32,
+}
+
+struct Line {
+    glyphs: Vec<Placed>,
+    width: f32,
+    rtl: bool,
+}
+
+impl TrueTypeFont {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<TrueTypeFont, Error> {
+        TrueTypeFont::from_bytes(fs::read(path)?)
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> Result<TrueTypeFont, Error> {
+        if FontRef::try_from_slice(&data).is_err()
+            || rustybuzz::Face::from_slice(&data, 0).is_none()
+        {
+            return Err(Error::InvalidFont);
+        }
+        Ok(TrueTypeFont { data })
+    }
+
+    /// Renders `text` with an em size of `size` dots. Each line of the text
+    /// is shaped and laid out in visual order, so right-to-left lines come
+    /// out right aligned and reading the right way. Lines wider than
+    /// `max_width` dots are cut off at the end, which for right-to-left
+    /// lines is the left side.
+    pub fn render(&self, text: &str, size: f32, max_width: Option<u32>) -> Result<Image, Error> {
+        if size <= 0.0 {
+            return Err(Error::InvalidArgument);
+        }
+        // Both parsed when the font was loaded
+        let font = FontRef::try_from_slice(&self.data).map_err(|_| Error::InvalidFont)?;
+        let face = rustybuzz::Face::from_slice(&self.data, 0).ok_or(Error::InvalidFont)?;
+
+        let units_per_em = face.units_per_em() as f32;
+        let to_dots = size / units_per_em;
+        // ab_glyph scales by the height from descender to ascender, not the em
+        let scale = PxScale::from(size * font.height_unscaled() / units_per_em);
+        let scaled = font.as_scaled(scale);
+        let line_height = (scaled.height() + scaled.line_gap()).ceil();
+
+        let lines = layout(&face, text, to_dots);
+        let widest = lines.iter().map(|l| l.width).fold(0.0, f32::max).ceil() as u32;
+        let width = match max_width {
+            Some(max) if widest > max => {
+                log::warn!("Rendered text is {} dots wide, cut off at {}", widest, max);
+                max
+            }
+            _ => widest,
+        };
+        let height = (line_height * lines.len() as f32).ceil() as u32;
+        if width == 0 || height == 0 {
+            return Err(Error::InvalidArgument);
+        }
+
+        let mut canvas = GrayImage::from_pixel(width, height, Luma([0xff]));
+        for (n, line) in lines.iter().enumerate() {
+            let baseline = line_height * n as f32 + scaled.ascent();
+            // Whole dots, so glyphs rasterize the same wherever they are.
+            // Right-to-left lines are lined up with the right edge of what's
+            // printed, so it's their ends that get cut off.
+            let start = if line.rtl {
+                width as f32 - line.width.ceil()
+            } else {
+                0.0
+            };
+            for placed in &line.glyphs {
+                let glyph = GlyphId(placed.id).with_scale_and_position(
+                    scale,
+                    ab_glyph::point(start + placed.x, baseline - placed.y),
+                );
+                let outline = match font.outline_glyph(glyph) {
+                    Some(outline) => outline,
+                    None => continue,
+                };
+                let bounds = outline.px_bounds();
+                outline.draw(|x, y, coverage| {
+                    let x = bounds.min.x as i32 + x as i32;
+                    let y = bounds.min.y as i32 + y as i32;
+                    if coverage > THRESHOLD
+                        && (0..width as i32).contains(&x)
+                        && (0..height as i32).contains(&y)
+                    {
+                        canvas.put_pixel(x as u32, y as u32, Luma([0]));
+                    }
+                });
+            }
+        }
+        Ok(Image::from(DynamicImage::ImageLuma8(canvas)))
+    }
+}
+
+/// Shapes each paragraph of `text` one directional run at a time, in the
+/// order the runs appear on paper
+fn layout(face: &rustybuzz::Face, text: &str, to_dots: f32) -> Vec<Line> {
+    let bidi = BidiInfo::new(text, None);
+    let mut lines = Vec::new();
+    for para in &bidi.paragraphs {
+        let range = para.range.start..para.range.end;
+        let (levels, runs) = bidi.visual_runs(para, range);
+        let mut line = Line {
+            glyphs: Vec::new(),
+            width: 0.0,
+            rtl: para.level.is_rtl(),
+        };
+        for run in runs {
+            let content = text[run.clone()].trim_end_matches(['\n', '\r']);
+            if content.is_empty() {
+                continue;
+            }
+            let mut buffer = UnicodeBuffer::new();
+            buffer.push_str(content);
+            buffer.guess_segment_properties();
+            buffer.set_direction(if levels[run.start].is_rtl() {
+                Direction::RightToLeft
+            } else {
+                Direction::LeftToRight
+            });
+            // Right-to-left glyphs come back in visual order already
+            let shaped = rustybuzz::shape(face, &[], buffer);
+            for (info, pos) in shaped.glyph_infos().iter().zip(shaped.glyph_positions()) {
+                line.glyphs.push(Placed {
+                    id: info.glyph_id as u16,
+                    x: line.width + pos.x_offset as f32 * to_dots,
+                    y: pos.y_offset as f32 * to_dots,
+                });
+                line.width += pos.x_advance as f32 * to_dots;
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
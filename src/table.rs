@@ -0,0 +1,284 @@
+//! Rows of text laid out in columns
+//!
+//! A [Table] describes the columns of a receipt section, such as the item,
+//! quantity and price of line items, and turns each row of cells into lines
+//! padded to fit. [crate::printer::Printer::print_row] lays rows out across
+//! [crate::printer::Printer::chars_per_line], so they follow the active font
+//! and character size.
+//!
+//! # Example
+//! ```rust
+//! use posify::table::{Column, ColumnWidth, Table};
+//! use posify::text::Alignment;
+//!
+//! let table = Table::new(vec![
+//!     Column::new(ColumnWidth::Remaining),
+//!     Column::new(ColumnWidth::Chars(3)).align(Alignment::Right),
+//!     Column::new(ColumnWidth::Chars(8)).align(Alignment::Right),
+//! ]);
+//! let lines = table.layout(24, &["Coffee", "2", "7.00"]).unwrap();
+//! assert_eq!(lines, vec!["Coffee        2     7.00"]);
+//! ```
+
+use crate::printer::Error;
+use crate::text::Alignment;
+use crate::wrap::{self, char_width, text_width};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColumnWidth {
+    /// A fixed number of characters
+    Chars(usize),
+    /// A share of the line, after the gaps between columns
+    Percent(u8),
+    /// An equal share of whatever the other columns leave
+    Remaining,
+}
+
+/// What happens to cell text longer than the column is wide
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Overflow {
+    /// Continue on as many lines as it takes
+    #[default]
+    Wrap,
+    /// Cut off at the column width
+    Truncate,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Column {
+    pub width: ColumnWidth,
+    pub align: Alignment,
+    pub overflow: Overflow,
+}
+
+impl Column {
+    /// A left aligned column that wraps its text
+    pub fn new(width: ColumnWidth) -> Column {
+        Column {
+            width,
+            align: Alignment::Left,
+            overflow: Overflow::Wrap,
+        }
+    }
+
+    pub fn align(mut self, align: Alignment) -> Column {
+        self.align = align;
+        self
+    }
+
+    pub fn overflow(mut self, overflow: Overflow) -> Column {
+        self.overflow = overflow;
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Table {
+    pub columns: Vec<Column>,
+    /// Number of characters between columns
+    pub gap: usize,
+    /// Character the first line of a row is padded with, e.g. `.` for
+    /// "Item ........ 12.50" lines. Any further lines are padded with spaces.
+    pub fill: char,
+}
+
+impl Table {
+    pub fn new(columns: Vec<Column>) -> Table {
+        Table {
+            columns,
+            gap: 1,
+            fill: ' ',
+        }
+    }
+
+    /// Two columns, the left one taking what the right one doesn't need and
+    /// wrapping, the right one as wide as `right` and aligned right
+    pub fn justified(right: &str) -> Table {
+        Table::new(vec![
+            Column::new(ColumnWidth::Remaining),
+            Column::new(ColumnWidth::Chars(text_width(right))).align(Alignment::Right),
+        ])
+    }
+
+    pub fn gap(mut self, gap: usize) -> Table {
+        self.gap = gap;
+        self
+    }
+
+    pub fn fill(mut self, fill: char) -> Table {
+        self.fill = fill;
+        self
+    }
+
+    /// Works out the width of each column on a line `total` characters wide.
+    /// Fails if the fixed and percentage widths add up to more than there is.
+    pub fn widths(&self, total: usize) -> Result<Vec<usize>, Error> {
+        let gaps = self.gap * self.columns.len().saturating_sub(1);
+        let available = total.checked_sub(gaps).ok_or(Error::InvalidArgument)?;
+        let mut widths = Vec::with_capacity(self.columns.len());
+        let mut used = 0;
+        let mut remaining = 0;
+        for column in &self.columns {
+            let width = match column.width {
+                ColumnWidth::Chars(n) => n,
+                ColumnWidth::Percent(p) => available * p as usize / 100,
+                ColumnWidth::Remaining => {
+                    remaining += 1;
+                    0
+                }
+            };
+            used += width;
+            widths.push(width);
+        }
+        let left = available.checked_sub(used).ok_or(Error::InvalidArgument)?;
+        let mut shares = remaining;
+        let mut rest = left;
+        for (width, column) in widths.iter_mut().zip(&self.columns) {
+            if column.width == ColumnWidth::Remaining {
+                *width = rest / shares;
+                rest -= *width;
+                shares -= 1;
+            }
+        }
+        Ok(widths)
+    }
+
+    /// Lays out one row of `cells` on lines `total` characters wide. Missing
+    /// cells are left empty and extra ones are an error, as is a wrapped
+    /// column too narrow for one of its characters. Truncated columns leave
+    /// out characters that don't fit.
+    pub fn layout(&self, total: usize, cells: &[&str]) -> Result<Vec<String>, Error> {
+        if cells.len() > self.columns.len() {
+            return Err(Error::InvalidArgument);
+        }
+        let widths = self.widths(total)?;
+        let contents: Vec<Vec<String>> = self
+            .columns
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(n, (column, &width))| {
+                let cell = cells.get(n).copied().unwrap_or("");
+                match column.overflow {
+                    _ if width == 0 => Ok(vec![String::new()]),
+                    Overflow::Wrap => wrap::wrap(cell, width),
+                    Overflow::Truncate => {
+                        Ok(vec![truncate(cell.lines().next().unwrap_or(""), width)])
+                    }
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        let rows = contents.iter().map(Vec::len).max().unwrap_or(0).max(1);
+
+        let mut lines = Vec::with_capacity(rows);
+        for row in 0..rows {
+            let fill = if row == 0 { self.fill } else { ' ' };
+            let mut line = String::new();
+            for (n, (column, &width)) in self.columns.iter().zip(&widths).enumerate() {
+                if n > 0 {
+                    line.extend(std::iter::repeat_n(fill, self.gap));
+                }
+                let text = contents[n].get(row).map(String::as_str).unwrap_or("");
+                line.push_str(&pad(text, width, column.align, fill));
+            }
+            lines.push(line.trim_end_matches(' ').to_string());
+        }
+        Ok(lines)
+    }
+}
+
+/// Cuts `text` down to `width` characters
+fn truncate(text: &str, width: usize) -> String {
+    let mut used = 0;
+    text.chars()
+        .take_while(|&c| {
+            used += char_width(c);
+            used <= width
+        })
+        .collect()
+}
+
+/// Pads `text` out to `width` characters with `fill`
+fn pad(text: &str, width: usize, align: Alignment, fill: char) -> String {
+    let padding = width.saturating_sub(text_width(text));
+    let (before, after) = match align {
+        Alignment::Left => (0, padding),
+        Alignment::Center => (padding / 2, padding - padding / 2),
+        Alignment::Right => (padding, 0),
+    };
+    let mut padded = String::with_capacity(text.len() + padding);
+    padded.extend(std::iter::repeat_n(fill, before));
+    padded.push_str(text);
+    padded.extend(std::iter::repeat_n(fill, after));
+    padded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn column_widths() {
+        let table = Table::new(vec![
+            Column::new(ColumnWidth::Percent(50)),
+            Column::new(ColumnWidth::Remaining),
+            Column::new(ColumnWidth::Chars(6)),
+            Column::new(ColumnWidth::Remaining),
+        ]);
+        assert_eq!(table.widths(43).unwrap(), vec![20, 7, 6, 7]);
+        assert!(table.widths(10).is_err());
+    }
+
+    #[test]
+    fn row_layout() {
+        let table = Table::new(vec![
+            Column::new(ColumnWidth::Chars(10)),
+            Column::new(ColumnWidth::Chars(4))
+                .align(Alignment::Center)
+                .overflow(Overflow::Truncate),
+            Column::new(ColumnWidth::Remaining).align(Alignment::Right),
+        ]);
+        assert_eq!(
+            table
+                .layout(24, &["Chocolate cake slice", "x12345", "4.50"])
+                .unwrap(),
+            vec!["Chocolate  x123     4.50", "cake slice"]
+        );
+        assert_eq!(
+            table.layout(24, &["拿铁咖啡", "2"]).unwrap(),
+            vec!["拿铁咖啡    2"]
+        );
+        assert!(table.layout(24, &["a", "b", "c", "d"]).is_err());
+    }
+
+    #[test]
+    fn narrow_columns() {
+        let table = Table::new(vec![
+            Column::new(ColumnWidth::Chars(1)),
+            Column::new(ColumnWidth::Chars(1)).overflow(Overflow::Truncate),
+        ]);
+        assert_eq!(table.layout(3, &["ab", "cd"]).unwrap(), vec!["a c", "b"]);
+        // Wide characters take two cells, so they can't be wrapped into one
+        // and are left out when truncated
+        assert!(matches!(
+            table.layout(3, &["合计", "x"]),
+            Err(Error::InvalidArgument)
+        ));
+        assert_eq!(table.layout(3, &["a", "合计"]).unwrap(), vec!["a"]);
+    }
+
+    #[test]
+    fn justified_lines() {
+        let table = Table::justified("12.50").fill('.');
+        assert_eq!(
+            table.layout(20, &["Item", "12.50"]).unwrap(),
+            vec!["Item...........12.50"]
+        );
+        assert_eq!(
+            Table::justified("3.00")
+                .layout(16, &["Extra large oat milk", "3.00"])
+                .unwrap(),
+            vec!["Extra large 3.00", "oat milk"]
+        );
+    }
+}
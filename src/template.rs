@@ -0,0 +1,957 @@
+//! Receipt templates filled in from a JSON context
+//!
+//! A template is [crate::markup] with `{{ }}` placeholders, in the style of
+//! Handlebars:
+//!
+//! - `{{ customer.name }}` prints a value from the context, escaped so it
+//!   can't be mistaken for markup. `this` is the current item of a loop, and
+//!   `@index`, `@first` and `@last` tell where in the loop it is.
+//! - `{{#each items}} ... {{/each}}` repeats for every item of an array.
+//! - `{{#if paid}} ... {{else}} ... {{/if}}` picks a branch. `null`, `false`,
+//!   `0`, `""`, `[]` and `{}` count as false.
+//! - `{{ number total 2 }}` formats a number with 2 decimals and
+//!   `{{ currency total "$" }}` as money, e.g. `$1,234.50`. Numbers are
+//!   rounded half away from zero as they're written, so `2.675` is `2.68`.
+//! - `{{ pad name 12 }}` and `{{ lpad price 8 }}` pad to a width and
+//!   `{{ columns name price }}` justifies two values across the line, or
+//!   `{{ columns name price "." }}` with a leader. A helper's name on its
+//!   own, e.g. `{{ number }}`, is a value from the context.
+//!
+//! Block tags on lines of their own don't leave blank lines behind. Widths
+//! come from the printer profile the template is rendered for, at the
+//! printer's current font and character size.
+//!
+//! # Example
+//! ```rust
+//! use posify::printer::SupportedPrinters;
+//! use posify::template::Template;
+//! use serde_json::json;
+//!
+//! let template = Template::parse("{{#each items}}\n{{ columns name price }}\n{{/each}}").unwrap();
+//! let context = json!({ "items": [{ "name": "Tea", "price": "2.50" }] });
+//! let text = template.expand(&context, 16).unwrap();
+//! assert_eq!(text, "Tea         2.50\n");
+//!
+//! let doc = template.render(&context, SupportedPrinters::SNBC).unwrap();
+//! ```
+
+use serde_json::Value;
+
+use crate::device::Transport;
+use crate::document::Document;
+use crate::markup::{Markup, MarkupError};
+use crate::printer::{Error, Printer, SupportedPrinters};
+use crate::table::Table;
+use crate::wrap::text_width;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Arg {
+    Literal(Value),
+    Path(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Text {
+        offset: usize,
+        text: String,
+    },
+    Expr {
+        offset: usize,
+        helper: Option<String>,
+        args: Vec<Arg>,
+    },
+    Each {
+        offset: usize,
+        items: Arg,
+        body: Vec<Node>,
+    },
+    If {
+        condition: Arg,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+}
+
+enum Token {
+    Text { offset: usize, text: String },
+    Tag { offset: usize, content: String },
+}
+
+impl Token {
+    fn is_block(&self) -> bool {
+        match self {
+            Token::Tag { content, .. } => {
+                content.starts_with('#') || content.starts_with('/') || content == "else"
+            }
+            Token::Text { .. } => false,
+        }
+    }
+}
+
+/// A parsed template, ready to be rendered with any number of contexts
+#[derive(Clone, Debug, PartialEq)]
+pub struct Template {
+    source: String,
+    nodes: Vec<Node>,
+}
+
+impl Template {
+    /// Parses `source`, checking that blocks are closed and tags make sense
+    pub fn parse(source: &str) -> Result<Template, MarkupError> {
+        let mut tokens = tokenize(source)?.into_iter();
+        let (nodes, end) = parse_nodes(source, &mut tokens)?;
+        if let Some((offset, tag)) = end {
+            return Err(error_at(
+                source,
+                offset,
+                format!("{{{{{}}}}} without a block", tag),
+            ));
+        }
+        Ok(Template {
+            source: source.to_string(),
+            nodes,
+        })
+    }
+
+    /// Fills in the template with `context`, giving markup for lines
+    /// `chars_per_line` characters wide
+    pub fn expand(&self, context: &Value, chars_per_line: usize) -> Result<String, MarkupError> {
+        self.expand_mapped(context, Some(chars_per_line))
+            .map(|map| map.expanded)
+    }
+
+    fn expand_mapped(
+        &self,
+        context: &Value,
+        chars_per_line: Option<usize>,
+    ) -> Result<SourceMap<'_>, MarkupError> {
+        let mut out = String::new();
+        let mut expander = Expander {
+            source: &self.source,
+            chars_per_line,
+            scopes: vec![Scope {
+                value: context.clone(),
+                position: None,
+            }],
+            segments: Vec::new(),
+        };
+        expander.nodes(&self.nodes, &mut out)?;
+        Ok(SourceMap {
+            source: &self.source,
+            expanded: out,
+            segments: expander.segments,
+        })
+    }
+
+    /// Renders the template for `printer` into a [Document]. Errors in the
+    /// markup it expands to are given at the place in the template it came
+    /// from.
+    pub fn render(&self, context: &Value, printer: SupportedPrinters) -> Result<Document, Error> {
+        let mut document = Document::new(printer);
+        let map = self
+            .expand_mapped(context, document.chars_per_line().ok())
+            .map_err(Error::Markup)?;
+        let markup = map.parse()?;
+        document
+            .print_markup(&markup)
+            .map_err(|err| map.error(err))?;
+        Ok(document)
+    }
+
+    pub(crate) fn print<T: Transport>(
+        &self,
+        printer: &mut Printer<T>,
+        context: &Value,
+    ) -> Result<usize, Error> {
+        let map = self
+            .expand_mapped(context, printer.chars_per_line().ok())
+            .map_err(Error::Markup)?;
+        let markup = map.parse()?;
+        printer.print_markup(&markup).map_err(|err| map.error(err))
+    }
+}
+
+/// Where a piece of the expanded text came from in the template
+struct Segment {
+    /// Byte offset in the expanded text
+    expanded: usize,
+    /// Byte offset in the template
+    source: usize,
+    /// Whether it's template text copied as it is, rather than what a tag
+    /// expanded to
+    copied: bool,
+}
+
+/// Expanded text that knows where each part of it came from
+struct SourceMap<'a> {
+    source: &'a str,
+    expanded: String,
+    segments: Vec<Segment>,
+}
+
+impl SourceMap<'_> {
+    fn parse(&self) -> Result<Markup, Error> {
+        Markup::parse(&self.expanded).map_err(|err| self.error(Error::Markup(err)))
+    }
+
+    /// Moves the position of a markup error from the expanded text to the
+    /// template, along with its related position
+    fn error(&self, err: Error) -> Error {
+        let err = match err {
+            Error::Markup(err) => err,
+            err => return err,
+        };
+        let (line, column) = self.locate(err.line, err.column);
+        Error::Markup(MarkupError {
+            line,
+            column,
+            related: err.related.map(|(line, column)| self.locate(line, column)),
+            ..err
+        })
+    }
+
+    /// Line and column in the template of a line and column in the expanded
+    /// text. Anything a tag expanded to is placed at the tag.
+    fn locate(&self, line: usize, column: usize) -> (usize, usize) {
+        let line_start = self
+            .expanded
+            .split_inclusive('\n')
+            .take(line.saturating_sub(1))
+            .map(str::len)
+            .sum::<usize>();
+        let offset = self.expanded[line_start..]
+            .char_indices()
+            .nth(column.saturating_sub(1))
+            .map_or(self.expanded.len(), |(i, _)| line_start + i);
+        let offset = match self.segments.iter().rev().find(|s| s.expanded <= offset) {
+            Some(s) if s.copied => s.source + (offset - s.expanded),
+            Some(s) => s.source,
+            None => 0,
+        };
+        let err = error_at(self.source, offset.min(self.source.len()), String::new());
+        (err.line, err.column)
+    }
+}
+
+fn error_at(source: &str, offset: usize, message: String) -> MarkupError {
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    MarkupError {
+        line,
+        column,
+        message,
+        related: None,
+    }
+}
+
+/// Splits `source` into text and tags, dropping the rest of the line around
+/// block tags that are on their own
+fn tokenize(source: &str) -> Result<Vec<Token>, MarkupError> {
+    let mut tokens = Vec::new();
+    let mut rest = 0;
+    while let Some(start) = source[rest..].find("{{").map(|i| rest + i) {
+        let end = match source[start..].find("}}") {
+            Some(i) => start + i,
+            None => return Err(error_at(source, start, "{{ is never closed".to_string())),
+        };
+        if start > rest {
+            tokens.push(Token::Text {
+                offset: rest,
+                text: source[rest..start].to_string(),
+            });
+        }
+        tokens.push(Token::Tag {
+            offset: start,
+            content: source[start + 2..end].trim().to_string(),
+        });
+        rest = end + 2;
+    }
+    if rest < source.len() {
+        tokens.push(Token::Text {
+            offset: rest,
+            text: source[rest..].to_string(),
+        });
+    }
+
+    // Decided before anything is trimmed, so that block tags on consecutive
+    // lines are all seen as on their own
+    let standalone: Vec<bool> = (0..tokens.len())
+        .map(|i| tokens[i].is_block() && alone_on_line(&tokens, i))
+        .collect();
+    for (i, _) in standalone.iter().enumerate().filter(|(_, alone)| **alone) {
+        if let Some(Token::Text { text, .. }) = i.checked_sub(1).map(|p| &mut tokens[p]) {
+            let keep = text.trim_end_matches([' ', '\t']).len();
+            text.truncate(keep);
+        }
+        if let Some(Token::Text { offset, text }) = tokens.get_mut(i + 1) {
+            let skip = text.find('\n').map_or(text.len(), |n| n + 1);
+            *offset += skip;
+            text.drain(..skip);
+        }
+    }
+    Ok(tokens)
+}
+
+/// Whether there's nothing but whitespace around tag `i` on its line
+fn alone_on_line(tokens: &[Token], i: usize) -> bool {
+    let line_start = match i.checked_sub(1).map(|p| &tokens[p]) {
+        None => true,
+        Some(Token::Text { text, .. }) => {
+            let tail = text.rsplit('\n').next().unwrap_or("");
+            tail.trim().is_empty() && (text.contains('\n') || i == 1)
+        }
+        Some(Token::Tag { .. }) => false,
+    };
+    let line_end = match tokens.get(i + 1) {
+        None => true,
+        Some(Token::Text { text, .. }) => {
+            let head = text.split('\n').next().unwrap_or("");
+            head.trim().is_empty() && (text.contains('\n') || i + 2 == tokens.len())
+        }
+        Some(Token::Tag { .. }) => false,
+    };
+    line_start && line_end
+}
+
+/// Offset and content of the tag that ended a block
+type Closing = (usize, String);
+
+/// Parses nodes up to the end of the input or a closing tag, which is
+/// returned along with its offset
+fn parse_nodes(
+    source: &str,
+    tokens: &mut std::vec::IntoIter<Token>,
+) -> Result<(Vec<Node>, Option<Closing>), MarkupError> {
+    let mut nodes = Vec::new();
+    while let Some(token) = tokens.next() {
+        let (offset, content) = match token {
+            Token::Text { offset, text } => {
+                if !text.is_empty() {
+                    nodes.push(Node::Text { offset, text });
+                }
+                continue;
+            }
+            Token::Tag { offset, content } => (offset, content),
+        };
+        if content == "else" || content.starts_with('/') {
+            return Ok((nodes, Some((offset, content))));
+        }
+        if let Some(block) = content.strip_prefix('#') {
+            let mut args = parse_args(source, offset, block)?;
+            let name = match args.first() {
+                Some(Arg::Path(name)) => name.clone(),
+                _ => {
+                    return Err(error_at(
+                        source,
+                        offset,
+                        "expected a block name".to_string(),
+                    ))
+                }
+            };
+            args.remove(0);
+            if args.len() != 1 {
+                return Err(error_at(
+                    source,
+                    offset,
+                    format!("{{{{#{}}}}} takes one argument", name),
+                ));
+            }
+            let arg = args.remove(0);
+            let (body, end) = parse_nodes(source, tokens)?;
+            let (mut end_offset, mut end_tag) = end.ok_or_else(|| {
+                error_at(source, offset, format!("{{{{#{}}}}} is never closed", name))
+            })?;
+            let mut otherwise = Vec::new();
+            if end_tag == "else" && name == "if" {
+                let (nodes, end) = parse_nodes(source, tokens)?;
+                otherwise = nodes;
+                (end_offset, end_tag) = end.ok_or_else(|| {
+                    error_at(source, offset, format!("{{{{#{}}}}} is never closed", name))
+                })?;
+            }
+            if end_tag != format!("/{}", name) {
+                return Err(error_at(
+                    source,
+                    end_offset,
+                    format!("{{{{{}}}}} doesn't close {{{{#{}}}}}", end_tag, name),
+                ));
+            }
+            nodes.push(match name.as_ref() {
+                "each" => Node::Each {
+                    offset,
+                    items: arg,
+                    body,
+                },
+                "if" => Node::If {
+                    condition: arg,
+                    then: body,
+                    otherwise,
+                },
+                _ => {
+                    return Err(error_at(
+                        source,
+                        offset,
+                        format!("unknown block {{{{#{}}}}}", name),
+                    ))
+                }
+            });
+            continue;
+        }
+
+        let mut args = parse_args(source, offset, &content)?;
+        let helper = match args.first() {
+            // A name on its own is a value, even if a helper has the name
+            Some(Arg::Path(name)) if args.len() > 1 => {
+                if !HELPERS.contains(&name.as_str()) {
+                    return Err(error_at(source, offset, format!("unknown helper {}", name)));
+                }
+                Some(name.clone())
+            }
+            Some(_) if args.len() == 1 => None,
+            _ => return Err(error_at(source, offset, "expected a value".to_string())),
+        };
+        if helper.is_some() {
+            args.remove(0);
+        }
+        nodes.push(Node::Expr {
+            offset,
+            helper,
+            args,
+        });
+    }
+    Ok((nodes, None))
+}
+
+const HELPERS: &[&str] = &["number", "currency", "pad", "lpad", "columns"];
+
+/// Splits the inside of a tag into paths, quoted strings and numbers
+fn parse_args(source: &str, offset: usize, content: &str) -> Result<Vec<Arg>, MarkupError> {
+    let mut args = Vec::new();
+    let mut chars = content.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' || c == '\'' {
+            chars.next();
+            let mut s = String::new();
+            loop {
+                match chars.next() {
+                    Some(q) if q == c => break,
+                    Some(ch) => s.push(ch),
+                    None => {
+                        return Err(error_at(source, offset, "unterminated string".to_string()))
+                    }
+                }
+            }
+            args.push(Arg::Literal(Value::String(s)));
+        } else {
+            let mut word = String::new();
+            while let Some(&ch) = chars.peek() {
+                if ch.is_whitespace() {
+                    break;
+                }
+                if !(ch.is_alphanumeric() || "._-@/".contains(ch)) {
+                    return Err(error_at(source, offset, format!("unexpected {:?}", ch)));
+                }
+                word.push(ch);
+                chars.next();
+            }
+            args.push(match word.parse::<f64>() {
+                Ok(_) => Arg::Literal(serde_json::from_str(&word).unwrap_or(Value::Null)),
+                Err(_) => Arg::Path(word),
+            });
+        }
+    }
+    Ok(args)
+}
+
+struct Scope {
+    value: Value,
+    /// Index and length of the loop the value is an item of
+    position: Option<(usize, usize)>,
+}
+
+struct Expander<'a> {
+    source: &'a str,
+    /// `None` if the profile can't measure lines, which only `columns`
+    /// needs
+    chars_per_line: Option<usize>,
+    scopes: Vec<Scope>,
+    segments: Vec<Segment>,
+}
+
+impl Expander<'_> {
+    fn nodes(&mut self, nodes: &[Node], out: &mut String) -> Result<(), MarkupError> {
+        for node in nodes {
+            match node {
+                Node::Text { offset, text } => {
+                    self.segments.push(Segment {
+                        expanded: out.len(),
+                        source: *offset,
+                        copied: true,
+                    });
+                    out.push_str(text);
+                }
+                Node::Expr {
+                    offset,
+                    helper,
+                    args,
+                } => {
+                    self.segments.push(Segment {
+                        expanded: out.len(),
+                        source: *offset,
+                        copied: false,
+                    });
+                    let values: Vec<Value> = args.iter().map(|arg| self.value(arg)).collect();
+                    let text = match helper {
+                        Some(helper) => self.helper(helper, &values),
+                        None => display(&values[0]),
+                    }
+                    .map_err(|message| error_at(self.source, *offset, message))?;
+                    out.push_str(&escape(&text));
+                }
+                Node::Each {
+                    offset,
+                    items,
+                    body,
+                } => {
+                    let items = match self.value(items) {
+                        Value::Array(items) => items,
+                        Value::Null => Vec::new(),
+                        _ => {
+                            return Err(error_at(
+                                self.source,
+                                *offset,
+                                "{{#each}} needs an array".to_string(),
+                            ))
+                        }
+                    };
+                    let len = items.len();
+                    for (index, value) in items.into_iter().enumerate() {
+                        self.scopes.push(Scope {
+                            value,
+                            position: Some((index, len)),
+                        });
+                        let result = self.nodes(body, out);
+                        self.scopes.pop();
+                        result?;
+                    }
+                }
+                Node::If {
+                    condition,
+                    then,
+                    otherwise,
+                    ..
+                } => {
+                    if truthy(&self.value(condition)) {
+                        self.nodes(then, out)?;
+                    } else {
+                        self.nodes(otherwise, out)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Looks `arg` up, `null` if it isn't in the context
+    fn value(&self, arg: &Arg) -> Value {
+        let path = match arg {
+            Arg::Literal(value) => return value.clone(),
+            Arg::Path(path) => path.as_str(),
+        };
+        let mut depth = self.scopes.len() - 1;
+        let mut path = path;
+        while let Some(rest) = path.strip_prefix("../") {
+            depth = depth.saturating_sub(1);
+            path = rest;
+        }
+        let scope = &self.scopes[depth];
+        if let Some(meta) = path.strip_prefix('@') {
+            return match (meta, scope.position) {
+                ("index", Some((index, _))) => Value::from(index),
+                ("first", Some((index, _))) => Value::from(index == 0),
+                ("last", Some((index, len))) => Value::from(index + 1 == len),
+                _ => Value::Null,
+            };
+        }
+        let mut segments = path.split('.');
+        let first = segments.next().unwrap_or("");
+        let mut value = if first == "this" {
+            Some(&scope.value)
+        } else {
+            // Names are looked for in the innermost scope that has them
+            self.scopes[..=depth]
+                .iter()
+                .rev()
+                .find_map(|scope| scope.value.get(first))
+        };
+        for segment in segments {
+            value = value.and_then(|v| match segment.parse::<usize>() {
+                Ok(index) => v.get(index),
+                Err(_) => v.get(segment),
+            });
+        }
+        value.cloned().unwrap_or(Value::Null)
+    }
+
+    fn helper(&self, name: &str, args: &[Value]) -> Result<String, String> {
+        let arg = |n: usize| args.get(n).unwrap_or(&Value::Null);
+        let count = |n: usize| -> Result<usize, String> {
+            arg(n)
+                .as_u64()
+                .map(|n| n as usize)
+                .ok_or_else(|| format!("{} needs a whole number as argument {}", name, n + 1))
+        };
+        match name {
+            "number" => {
+                let decimals = if args.len() > 1 { count(1)? } else { 0 };
+                Ok(number(arg(0))?.round(decimals).to_string())
+            }
+            "currency" => {
+                let symbol = display(arg(1))?;
+                let decimals = if args.len() > 2 { count(2)? } else { 2 };
+                Ok(currency(&number(arg(0))?.round(decimals), &symbol))
+            }
+            "pad" | "lpad" => {
+                let text = display(arg(0))?;
+                let padding = " ".repeat(count(1)?.saturating_sub(text_width(&text)));
+                Ok(if name == "pad" {
+                    text + &padding
+                } else {
+                    padding + &text
+                })
+            }
+            "columns" => {
+                let left = display(arg(0))?;
+                let right = display(arg(1))?;
+                let mut table = Table::justified(&right);
+                if args.len() > 2 {
+                    table = table.fill(display(arg(2))?.chars().next().unwrap_or(' '));
+                }
+                let width = self
+                    .chars_per_line
+                    .ok_or("the printer's line width isn't known")?;
+                let lines = table
+                    .layout(width, &[&left, &right])
+                    .map_err(|_| "the columns don't fit on the line".to_string())?;
+                Ok(lines.join("\n"))
+            }
+            _ => Err(format!("unknown helper {}", name)),
+        }
+    }
+}
+
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64() != Some(0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(a) => !a.is_empty(),
+        Value::Object(o) => !o.is_empty(),
+    }
+}
+
+fn display(value: &Value) -> Result<String, String> {
+    match value {
+        Value::Null => Ok(String::new()),
+        Value::Bool(b) => Ok(b.to_string()),
+        Value::Number(n) => Ok(n.to_string()),
+        Value::String(s) => Ok(s.clone()),
+        Value::Array(_) | Value::Object(_) => Err("can't print a list or an object".to_string()),
+    }
+}
+
+/// Numbers can also be given as strings, as prices often are
+fn number(value: &Value) -> Result<Decimal, String> {
+    match value {
+        Value::Number(n) => Decimal::parse(&n.to_string()),
+        Value::String(s) => Decimal::parse(s.trim()),
+        _ => None,
+    }
+    .ok_or_else(|| format!("{} isn't a number", value))
+}
+
+/// A number kept as the decimal digits it was written with, so amounts
+/// like `2.675` round the way they read rather than the way the nearest
+/// `f64` does
+struct Decimal {
+    negative: bool,
+    /// Digits before the point, without leading zeros
+    whole: String,
+    fraction: String,
+}
+
+impl Decimal {
+    /// Reads numbers like `-12`, `2.675` or `1e21`
+    fn parse(text: &str) -> Option<Decimal> {
+        let (negative, text) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        let (mantissa, exponent) = match text.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, exponent.parse::<i32>().ok()?),
+            None => (text, 0),
+        };
+        let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let digits = format!("{}{}", whole, fraction);
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        // Anything bigger than an f64 can hold isn't a price
+        if exponent.unsigned_abs() > 400 {
+            return None;
+        }
+        let point = whole.len() as i64 + exponent as i64;
+        let (whole, fraction) = if point <= 0 {
+            (String::new(), "0".repeat(-point as usize) + &digits)
+        } else if point as usize >= digits.len() {
+            (
+                digits.clone() + &"0".repeat(point as usize - digits.len()),
+                String::new(),
+            )
+        } else {
+            let (whole, fraction) = digits.split_at(point as usize);
+            (whole.to_string(), fraction.to_string())
+        };
+        Some(Decimal {
+            negative,
+            whole: whole.trim_start_matches('0').to_string(),
+            fraction,
+        })
+    }
+
+    /// Rounds half away from zero to `decimals` places
+    fn round(&self, decimals: usize) -> Decimal {
+        let mut fraction = self.fraction.clone();
+        let round_up = fraction
+            .as_bytes()
+            .get(decimals)
+            .is_some_and(|&b| b >= b'5');
+        fraction.truncate(decimals);
+        fraction.extend(std::iter::repeat_n('0', decimals - fraction.len()));
+        let mut digits = (self.whole.clone() + &fraction).into_bytes();
+        if round_up {
+            let mut carry = true;
+            for digit in digits.iter_mut().rev() {
+                if *digit == b'9' {
+                    *digit = b'0';
+                } else {
+                    *digit += 1;
+                    carry = false;
+                    break;
+                }
+            }
+            if carry {
+                digits.insert(0, b'1');
+            }
+        }
+        let digits = String::from_utf8(digits).unwrap_or_default();
+        let (whole, fraction) = digits.split_at(digits.len() - decimals);
+        Decimal {
+            negative: self.negative,
+            whole: whole.trim_start_matches('0').to_string(),
+            fraction: fraction.to_string(),
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.whole.is_empty() && self.fraction.bytes().all(|b| b == b'0')
+    }
+
+    fn sign(&self) -> &'static str {
+        if self.negative && !self.is_zero() {
+            "-"
+        } else {
+            ""
+        }
+    }
+
+    fn whole(&self) -> &str {
+        if self.whole.is_empty() {
+            "0"
+        } else {
+            &self.whole
+        }
+    }
+}
+
+impl std::fmt::Display for Decimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.sign(), self.whole())?;
+        if !self.fraction.is_empty() {
+            write!(f, ".{}", self.fraction)?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats `amount` with thousands separators, e.g. `-$1,234.50`
+fn currency(amount: &Decimal, symbol: &str) -> String {
+    let whole = amount.whole();
+    let mut grouped = String::new();
+    for (i, c) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    if amount.fraction.is_empty() {
+        format!("{}{}{}", amount.sign(), symbol, grouped)
+    } else {
+        format!("{}{}{}.{}", amount.sign(), symbol, grouped, amount.fraction)
+    }
+}
+
+/// Escapes text so the markup prints it as it is
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn expands_templates() {
+        let template = Template::parse(
+            "<center>{{ shop.name }}</center>\n\
+             {{#each items}}\n\
+             {{ columns name price \".\" }}\n\
+             {{#if note}}\n  {{ note }}\n{{/if}}\n\
+             {{/each}}\n\
+             {{#if paid}}PAID {{ currency total \"$\" }}{{else}}DUE{{/if}}\n",
+        )
+        .unwrap();
+        let context = json!({
+            "shop": { "name": "Fish & Chips" },
+            "items": [
+                { "name": "Cod", "price": "8.50", "note": "no salt" },
+                { "name": "Chips", "price": "3.00" },
+            ],
+            "paid": true,
+            "total": 1234.5,
+        });
+        assert_eq!(
+            template.expand(&context, 16).unwrap(),
+            "<center>Fish &amp; Chips</center>\n\
+             Cod.........8.50\n  no salt\n\
+             Chips.......3.00\n\
+             PAID $1,234.50\n"
+        );
+    }
+
+    #[test]
+    fn helpers() {
+        let template = Template::parse(
+            "{{ number n 1 }}|{{ currency m \"€\" 0 }}|{{ lpad n 5 }}|{{ pad s 4 }}|",
+        )
+        .unwrap();
+        let context = json!({ "n": 2.26, "m": -1999999.6, "s": "ab" });
+        assert_eq!(
+            template.expand(&context, 48).unwrap(),
+            "2.3|-€2,000,000| 2.26|ab  |"
+        );
+    }
+
+    #[test]
+    fn numbers_round_as_written() {
+        let template = Template::parse(
+            "{{ number a 2 }}|{{ currency b \"$\" }}|{{ number c 1 }}|{{ number d }}",
+        )
+        .unwrap();
+        let context = json!({ "a": "2.675", "b": 1.005, "c": "-0.04", "d": "9.5" });
+        assert_eq!(template.expand(&context, 48).unwrap(), "2.68|$1.01|0.0|10");
+
+        let template = Template::parse("{{ currency a \"$\" }}|{{ number b 2 }}").unwrap();
+        let context = json!({ "a": "-999999.995", "b": "1.5e3" });
+        assert_eq!(
+            template.expand(&context, 48).unwrap(),
+            "-$1,000,000.00|1500.00"
+        );
+        let context = json!({ "a": "2.6.7", "b": 1 });
+        assert!(template.expand(&context, 48).is_err());
+    }
+
+    #[test]
+    fn template_errors() {
+        let cases = [
+            (
+                "{{#each items}}\n{{ name }}",
+                (1, 1),
+                "{{#each}} is never closed",
+            ),
+            (
+                "ok\n{{#if a}}{{/each}}",
+                (2, 10),
+                "{{/each}} doesn't close {{#if}}",
+            ),
+            ("{{ shout name }}", (1, 1), "unknown helper shout"),
+            ("{{ name", (1, 1), "{{ is never closed"),
+        ];
+        for (source, (line, column), message) in cases {
+            let err = Template::parse(source).unwrap_err();
+            assert_eq!(
+                (err.line, err.column, err.message.as_ref()),
+                (line, column, message)
+            );
+        }
+
+        let template = Template::parse("\n  {{ number price }}").unwrap();
+        let err = template
+            .expand(&json!({ "price": "free" }), 48)
+            .unwrap_err();
+        assert_eq!((err.line, err.column), (2, 3));
+    }
+
+    #[test]
+    fn values_named_like_helpers() {
+        let template = Template::parse("{{ number }} {{ pad }}").unwrap();
+        let context = json!({ "number": 5, "pad": "x" });
+        assert_eq!(template.expand(&context, 48).unwrap(), "5 x");
+    }
+
+    #[test]
+    fn markup_errors_in_template() {
+        let context = json!({ "title": "A\nB" });
+        let cases = [
+            ("{{ title }}\n  <blink>", (2, 3), "unknown tag <blink>"),
+            (
+                "{{ title }}<center>\n<b>x</center>",
+                (2, 5),
+                "</center> closes <b> from line 2, column 1",
+            ),
+            ("x{{ title }}<b>", (1, 13), "<b> is never closed"),
+        ];
+        for (source, (line, column), message) in cases {
+            let template = Template::parse(source).unwrap();
+            match template.render(&context, SupportedPrinters::SNBC) {
+                Err(Error::Markup(err)) => assert_eq!(
+                    (
+                        err.line,
+                        err.column,
+                        err.to_string().split_once(": ").unwrap().1
+                    ),
+                    (line, column, message)
+                ),
+                _ => panic!("expected a markup error for {:?}", source),
+            }
+        }
+    }
+
+    #[test]
+    fn render_for_profile() {
+        let template = Template::parse("{{ columns a b }}").unwrap();
+        let context = json!({ "a": "Left", "b": "Right" });
+        let doc = template.render(&context, SupportedPrinters::SNBC).unwrap();
+        assert_eq!(doc.as_bytes().len(), 48);
+        // The P3 profile doesn't know how wide the paper is
+        assert!(template.render(&context, SupportedPrinters::P3).is_err());
+    }
+}
@@ -0,0 +1,194 @@
+//! Typed arguments for the text formatting commands on
+//! [crate::printer::Printer]
+//!
+//! Each type can also be parsed from the short string forms the printer
+//! methods used to take, e.g. `"ct".parse::<Alignment>()`.
+
+use std::str::FromStr;
+
+use crate::printer::Error;
+
+/// ESC a n - Justification
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Alignment {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+impl FromStr for Alignment {
+    type Err = Error;
+
+    /// Accepts `LT`, `CT` and `RT` as well as the full names
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_ref() {
+            "LT" | "LEFT" => Ok(Alignment::Left),
+            "CT" | "CENTER" => Ok(Alignment::Center),
+            "RT" | "RIGHT" => Ok(Alignment::Right),
+            _ => Err(Error::InvalidArgument),
+        }
+    }
+}
+
+/// ESC M n - Character font
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum TextFont {
+    #[default]
+    A,
+    B,
+    C,
+}
+
+impl FromStr for TextFont {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_ref() {
+            "A" => Ok(TextFont::A),
+            "B" => Ok(TextFont::B),
+            "C" => Ok(TextFont::C),
+            _ => Err(Error::InvalidArgument),
+        }
+    }
+}
+
+/// ESC - n - Underline mode
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Underline {
+    #[default]
+    Off,
+    /// 1 dot thick
+    On,
+    /// 2 dots thick
+    Thick,
+}
+
+impl FromStr for Underline {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_ref() {
+            "OFF" => Ok(Underline::Off),
+            "ON" => Ok(Underline::On),
+            "THICK" => Ok(Underline::Thick),
+            _ => Err(Error::InvalidArgument),
+        }
+    }
+}
+
+/// Combination of bold (ESC E) and underline (ESC -) set by
+/// [crate::printer::Printer::style]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Emphasis {
+    #[default]
+    Normal,
+    Bold,
+    Underline,
+    /// 2 dot underline
+    Underline2,
+    BoldUnderline,
+    /// Bold with a 2 dot underline
+    BoldUnderline2,
+}
+
+impl FromStr for Emphasis {
+    type Err = Error;
+
+    /// Accepts `B`, `U`, `U2`, `BU`, `BU2` and `NORMAL`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_ref() {
+            "NORMAL" => Ok(Emphasis::Normal),
+            "B" => Ok(Emphasis::Bold),
+            "U" => Ok(Emphasis::Underline),
+            "U2" => Ok(Emphasis::Underline2),
+            "BU" => Ok(Emphasis::BoldUnderline),
+            "BU2" => Ok(Emphasis::BoldUnderline2),
+            _ => Err(Error::InvalidArgument),
+        }
+    }
+}
+
+/// Text modes that not every printer has, listed in
+/// [crate::profile::Profile::text_modes]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TextMode {
+    /// GS B - White on black
+    Reverse,
+    /// ESC { - Upside-down
+    UpsideDown,
+    /// ESC V - 90 degree clockwise rotation
+    Rotate,
+    /// ESC G - Double-strike
+    DoubleStrike,
+    /// GS b - Smoothing of enlarged characters
+    Smoothing,
+}
+
+impl FromStr for TextMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().replace(['-', '_'], "").as_ref() {
+            "REVERSE" => Ok(TextMode::Reverse),
+            "UPSIDEDOWN" => Ok(TextMode::UpsideDown),
+            "ROTATE" => Ok(TextMode::Rotate),
+            "DOUBLESTRIKE" => Ok(TextMode::DoubleStrike),
+            "SMOOTHING" => Ok(TextMode::Smoothing),
+            _ => Err(Error::InvalidArgument),
+        }
+    }
+}
+
+/// Feed control sequences sent by [crate::printer::Printer::control]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Control {
+    /// Print and line feed
+    LineFeed,
+    /// Form feed
+    FormFeed,
+    /// Carriage return
+    CarriageReturn,
+    /// Horizontal tab
+    HorizontalTab,
+    /// Vertical tab
+    VerticalTab,
+}
+
+impl FromStr for Control {
+    type Err = Error;
+
+    /// Accepts `LF`, `FF`, `CR`, `HT` and `VT`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_ref() {
+            "LF" => Ok(Control::LineFeed),
+            "FF" => Ok(Control::FormFeed),
+            "CR" => Ok(Control::CarriageReturn),
+            "HT" => Ok(Control::HorizontalTab),
+            "VT" => Ok(Control::VerticalTab),
+            _ => Err(Error::Unsupported),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_strings() {
+        assert_eq!("ct".parse::<Alignment>().unwrap(), Alignment::Center);
+        assert_eq!("b".parse::<TextFont>().unwrap(), TextFont::B);
+        assert_eq!("thick".parse::<Underline>().unwrap(), Underline::Thick);
+        assert_eq!("BU2".parse::<Emphasis>().unwrap(), Emphasis::BoldUnderline2);
+        assert_eq!("ht".parse::<Control>().unwrap(), Control::HorizontalTab);
+        assert_eq!(
+            "upside-down".parse::<TextMode>().unwrap(),
+            TextMode::UpsideDown
+        );
+
+        assert!("middle".parse::<Alignment>().is_err());
+        assert!("thik".parse::<Underline>().is_err());
+        assert!("bold".parse::<Emphasis>().is_err());
+    }
+}
@@ -0,0 +1,57 @@
+//! Distances given in dots or millimetres
+//!
+//! Positioning commands take distances in the printer's motion units, see
+//! [crate::printer::Printer::motion_units]. A [Length] is converted to dots
+//! with the profile's resolution first and from there to motion units, so
+//! layouts can be written in millimetres and still line up on every printer.
+
+/// A horizontal or vertical distance
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Length {
+    Dots(i32),
+    Mm(f32),
+}
+
+impl Length {
+    /// The length in dots at `dots_per_mm`, rounded to the nearest dot
+    pub fn dots(&self, dots_per_mm: u8) -> i32 {
+        match *self {
+            Length::Dots(dots) => dots,
+            Length::Mm(mm) => (mm * dots_per_mm as f32).round() as i32,
+        }
+    }
+}
+
+/// Converts `dots` to motion units of 1/`unit` inch, where a `unit` of 0
+/// stands for the printer's default of one dot
+pub(crate) fn dots_to_units(dots: i32, unit: u8, dots_per_mm: u8) -> i32 {
+    if unit == 0 {
+        return dots;
+    }
+    let dots_per_inch = dots_per_mm as f32 * 25.4;
+    (dots as f32 * unit as f32 / dots_per_inch).round() as i32
+}
+
+/// Converts motion units of 1/`unit` inch back to dots
+pub(crate) fn units_to_dots(units: i32, unit: u8, dots_per_mm: u8) -> i32 {
+    if unit == 0 {
+        return units;
+    }
+    let dots_per_inch = dots_per_mm as f32 * 25.4;
+    (units as f32 * dots_per_inch / unit as f32).round() as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions() {
+        assert_eq!(Length::Mm(10.0).dots(8), 80);
+        assert_eq!(Length::Dots(-12).dots(8), -12);
+        assert_eq!(dots_to_units(203, 0, 8), 203);
+        // 1/180 inch units on a 203 dpi printer
+        assert_eq!(dots_to_units(203, 180, 8), 180);
+        assert_eq!(units_to_dots(180, 180, 8), 203);
+    }
+}
@@ -0,0 +1,152 @@
+//! Word wrapping to the number of characters that fit on a line
+//!
+//! Widths are counted in character cells of the current font, the way the
+//! printer lays text out: CJK and other full-width characters take two cells
+//! and combining marks none. [crate::printer::Printer::print_wrapped] wraps
+//! to [crate::printer::Printer::chars_per_line], which accounts for the font,
+//! the character width and the paper.
+
+use unicode_width::UnicodeWidthChar;
+
+use crate::cjk::is_cjk;
+use crate::printer::Error;
+
+/// Number of character cells `c` takes up
+pub fn char_width(c: char) -> usize {
+    c.width().unwrap_or(0)
+}
+
+/// Number of character cells `text` takes up on a line
+pub fn text_width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+/// Breaks `text` into lines no wider than `width` cells.
+///
+/// Lines are broken between words, and anywhere between CJK characters,
+/// which aren't separated by spaces. Words too long for a line of their own
+/// are hyphenated. Line breaks already in the text are kept. Fails with
+/// [Error::InvalidArgument] if a character is wider than the line, e.g. a
+/// CJK character on a line one cell wide.
+pub fn wrap(text: &str, width: usize) -> Result<Vec<String>, Error> {
+    let width = width.max(1);
+    if text.chars().any(|c| char_width(c) > width) {
+        return Err(Error::InvalidArgument);
+    }
+    let mut lines = Vec::new();
+    for para in text.lines() {
+        let mut line = String::new();
+        let mut line_width = 0;
+        for word in para.split_whitespace() {
+            for (n, piece) in pieces(word).into_iter().enumerate() {
+                let spaced = n == 0 && !line.is_empty();
+                let piece_width = text_width(piece);
+                let needed = piece_width + usize::from(spaced);
+                if line_width + needed <= width {
+                    if spaced {
+                        line.push(' ');
+                    }
+                    line.push_str(piece);
+                    line_width += needed;
+                    continue;
+                }
+                if !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                }
+                let (broken, rest) = hyphenate(piece, width);
+                lines.extend(broken);
+                line_width = text_width(&rest);
+                line = rest;
+            }
+        }
+        lines.push(line);
+    }
+    Ok(lines)
+}
+
+/// Splits a word into the parts a line may be broken between: each CJK
+/// character on its own and the runs of other characters between them
+fn pieces(word: &str) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    for (i, c) in word.char_indices() {
+        if is_cjk(c) {
+            if start < i {
+                pieces.push(&word[start..i]);
+            }
+            let end = i + c.len_utf8();
+            pieces.push(&word[i..end]);
+            start = end;
+        }
+    }
+    if start < word.len() {
+        pieces.push(&word[start..]);
+    }
+    pieces
+}
+
+/// Breaks `word` into full lines ending in a hyphen, returned along with
+/// whatever is left over to start the next line with
+fn hyphenate(word: &str, width: usize) -> (Vec<String>, String) {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut line_width = 0;
+    // Leave room for the hyphen, unless there's no room for anything else
+    let limit = if width > 1 { width - 1 } else { width };
+    let mut chars = word.chars();
+    while let Some(c) = chars.next() {
+        let w = char_width(c);
+        if line_width + w > limit && !line.is_empty() {
+            let rest_width: usize = chars.clone().map(char_width).sum::<usize>() + w;
+            if line_width + rest_width <= width {
+                // The rest fits where the hyphen would have gone
+                line.push(c);
+                line_width += w;
+                continue;
+            }
+            if width > 1 {
+                line.push('-');
+            }
+            lines.push(std::mem::take(&mut line));
+            line_width = 0;
+        }
+        line.push(c);
+        line_width += w;
+    }
+    (lines, line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrap_words() {
+        assert_eq!(
+            wrap("The quick brown fox jumps over the lazy dog", 16).unwrap(),
+            vec!["The quick brown", "fox jumps over", "the lazy dog"]
+        );
+        assert_eq!(
+            wrap("Item: Supercalifragilistic\n\nTotal", 10).unwrap(),
+            vec!["Item:", "Supercali-", "fragilist-", "ic", "", "Total"]
+        );
+        assert_eq!(wrap("exactly10!", 10).unwrap(), vec!["exactly10!"]);
+        assert_eq!(wrap("", 10).unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn wrap_wide_characters() {
+        assert_eq!(text_width("敏捷的狐狸"), 10);
+        assert_eq!(text_width("e\u{301}"), 1);
+        assert_eq!(
+            wrap("敏捷的棕色狐狸跳过懒狗", 8).unwrap(),
+            vec!["敏捷的棕", "色狐狸跳", "过懒狗"]
+        );
+        assert_eq!(wrap("Total 合计", 8).unwrap(), vec!["Total 合", "计"]);
+
+        // Two cells is the narrowest line a wide character fits on
+        assert_eq!(wrap("合计", 2).unwrap(), vec!["合", "计"]);
+        assert!(matches!(wrap("合计", 1), Err(Error::InvalidArgument)));
+        assert_eq!(wrap("ab", 1).unwrap(), vec!["a", "b"]);
+    }
+}
@@ -0,0 +1,102 @@
+extern crate posify;
+
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread;
+use std::time::Duration;
+
+use posify::device::{Network, NetworkSettings};
+use posify::printer::{Printer, SupportedPrinters};
+
+#[test]
+fn network_status_and_asb() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut command = [0_u8; 3];
+        stream.read_exact(&mut command).unwrap();
+        assert_eq!(command, [0x1d, 0x61, 0x02]);
+        // ASB message pushed once it's enabled, then the paper sensor
+        // response once it's asked for
+        stream.write_all(&[0x18, 0x00, 0x00, 0x00]).unwrap();
+        stream.read_exact(&mut command).unwrap();
+        assert_eq!(command, [0x1d, 0x72, 0x01]);
+        stream.write_all(&[0x00]).unwrap();
+        // Hold the connection open until the client is done
+        let _ = stream.read(&mut command);
+    });
+
+    let settings = NetworkSettings {
+        connect_timeout: Some(Duration::from_secs(1)),
+        keepalive: Some(Duration::from_secs(10)),
+    };
+    let device = Network::with_settings("127.0.0.1", port, settings).unwrap();
+    let mut printer = Printer::with_transport(None, None, SupportedPrinters::SNBC, device);
+    printer.set_timeout(Duration::from_millis(500));
+
+    printer.asb(0x02).unwrap();
+    assert_eq!(
+        printer.asb_status().unwrap(),
+        Some([0x18, 0x00, 0x00, 0x00])
+    );
+    assert!(printer.paper_loaded().unwrap());
+    assert_eq!(printer.asb_status().unwrap(), None);
+
+    printer.close().unwrap();
+    server.join().unwrap();
+}
+
+#[test]
+fn network_responses_are_not_taken_for_asb() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut command = [0_u8; 6];
+        stream.read_exact(&mut command).unwrap();
+        assert_eq!(command, [0x1d, 0x61, 0x02, 0x1d, 0x49, 0x03]);
+        // Starts like an ASB message and ends with one that's incomplete
+        stream.write_all(b"P3-1.0").unwrap();
+        let _ = stream.read(&mut command);
+    });
+
+    let device = Network::new("127.0.0.1", port).unwrap();
+    let mut printer = Printer::with_transport(None, None, SupportedPrinters::P3, device);
+    printer.set_timeout(Duration::from_millis(500));
+
+    printer.asb(0x02).unwrap();
+    printer.write(&[0x1d, 0x49, 0x03]).unwrap();
+    let mut reply = [0_u8; 16];
+    let n = printer.read(&mut reply).unwrap();
+    assert_eq!(&reply[..n], b"P3-1.0");
+    assert_eq!(printer.asb_status().unwrap(), None);
+
+    printer.close().unwrap();
+    server.join().unwrap();
+}
+
+#[test]
+fn network_read_timeout() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buf = [0_u8; 16];
+        while stream.read(&mut buf).unwrap_or(0) > 0 {}
+    });
+
+    let device = Network::new("127.0.0.1", port).unwrap();
+    let mut printer = Printer::with_transport(None, None, SupportedPrinters::SNBC, device);
+    printer.set_timeout(Duration::from_millis(50));
+
+    assert!(matches!(
+        printer.get_rom_version(),
+        Err(posify::printer::Error::Timeout)
+    ));
+
+    printer.close().unwrap();
+    server.join().unwrap();
+}
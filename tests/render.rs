@@ -0,0 +1,82 @@
+extern crate posify;
+
+use posify::document::Document;
+use posify::printer::{Error, SupportedPrinters};
+use posify::render::TrueTypeFont;
+
+/// DejaVu Sans with only the Latin, Hebrew and Arabic glyphs kept, see
+/// tests/fonts/LICENSE-DejaVu.txt
+const FONT: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fonts/DejaVuSans-subset.ttf"
+);
+
+fn font() -> TrueTypeFont {
+    TrueTypeFont::from_path(FONT).unwrap()
+}
+
+#[test]
+fn rejects_invalid_fonts() {
+    assert!(matches!(
+        TrueTypeFont::from_bytes(b"not a font".to_vec()),
+        Err(Error::InvalidFont)
+    ));
+    assert!(TrueTypeFont::from_path("/nonexistent.ttf").is_err());
+}
+
+#[test]
+fn right_to_left_order() {
+    let font = font();
+    let alef = font.render("א", 24.0, None).unwrap();
+    let bet = font.render("ב", 24.0, None).unwrap();
+    let word = font.render("אב", 24.0, None).unwrap();
+
+    // Bet comes after alef, so it's printed on the left
+    assert!(word.width <= alef.width + bet.width);
+    for y in 0..word.height {
+        for x in 0..bet.width {
+            assert_eq!(word.is_blank_pixel(x, y), bet.is_blank_pixel(x, y));
+        }
+    }
+}
+
+#[test]
+fn right_to_left_cut_off_at_the_end() {
+    let font = font();
+    let word = font.render("אב", 24.0, None).unwrap();
+    let alef = font.render("א", 24.0, None).unwrap();
+    let cut = font.render("אב", 24.0, Some(alef.width)).unwrap();
+
+    // Alef starts the word on the right, so that's the part that's kept
+    let shift = word.width - cut.width;
+    assert!(shift > 0);
+    for y in 0..word.height {
+        for x in 0..cut.width {
+            assert_eq!(cut.is_blank_pixel(x, y), word.is_blank_pixel(x + shift, y));
+        }
+    }
+}
+
+#[test]
+fn shapes_arabic() {
+    let font = font();
+    // Joined letters are narrower than the isolated forms side by side
+    let joined = font.render("سلام", 24.0, None).unwrap();
+    let isolated: u32 = "سلام"
+        .chars()
+        .map(|c| font.render(&c.to_string(), 24.0, None).unwrap().width)
+        .sum();
+    assert!(joined.width < isolated);
+}
+
+#[test]
+fn print_rendered_lines() {
+    let font = font();
+    let image = font.render("Hello\nשלום", 24.0, Some(40)).unwrap();
+    assert_eq!(image.width, 40);
+    assert!(image.height > 24 * 2);
+
+    let mut doc = Document::new(SupportedPrinters::SNBC);
+    doc.print_rendered("สวัสดี", &font, 24.0).unwrap();
+    assert_eq!(&doc.as_bytes()[..4], &[0x1d, 0x76, 0x30, 0x00]);
+}
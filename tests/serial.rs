@@ -0,0 +1,95 @@
+#![cfg(target_os = "linux")]
+
+extern crate posify;
+
+use std::io::{Read, Write};
+use std::time::Duration;
+
+use posify::device::{FlowControl, Parity, Serial, SerialSettings, StopBits};
+use posify::printer::{Printer, SupportedPrinters};
+use serialport::{SerialPort, TTYPort};
+
+#[test]
+fn serial_write_and_status() {
+    let (mut master, slave) = TTYPort::pair().unwrap();
+    let device = Serial::from_port(Box::new(slave));
+    let mut printer = Printer::with_transport(None, None, SupportedPrinters::SNBC, device);
+
+    printer.chain_hwinit().unwrap().chain_print("Hi").unwrap();
+    printer.flush().unwrap();
+
+    master.set_timeout(Duration::from_millis(500)).unwrap();
+    let mut sent = [0_u8; 4];
+    master.read_exact(&mut sent).unwrap();
+    assert_eq!(&sent, b"\x1b\x40Hi");
+
+    // Paper sensor status answers 0x00 when paper is present
+    master.write_all(&[0x00]).unwrap();
+    assert!(printer.paper_loaded().unwrap());
+
+    let mut query = [0_u8; 3];
+    master.read_exact(&mut query).unwrap();
+    assert_eq!(query, [0x1d, 0x72, 0x01]);
+}
+
+#[test]
+fn serial_settings() {
+    let (_master, slave) = TTYPort::pair().unwrap();
+    let path = slave.name().unwrap();
+    drop(slave);
+
+    let settings = SerialSettings {
+        baud_rate: 19200,
+        parity: Parity::Even,
+        stop_bits: StopBits::Two,
+        flow_control: FlowControl::Software,
+        ..SerialSettings::default()
+    };
+    let device = Serial::with_settings(&path, settings).unwrap();
+    // The pty driver always forces 8 data bits without parity, so that's the
+    // one setting that can't be read back here
+    assert_eq!(
+        device.settings().unwrap(),
+        SerialSettings {
+            parity: Parity::None,
+            ..settings
+        }
+    );
+}
+
+#[test]
+fn serial_partial_writes_are_not_repeated() {
+    use std::os::unix::io::AsRawFd;
+
+    let (mut master, mut slave) = TTYPort::pair().unwrap();
+    slave.set_timeout(Duration::from_millis(20)).unwrap();
+    // Without O_NONBLOCK a pty write waits for all of it to fit, where a
+    // port held by flow control takes what it can and then times out
+    unsafe {
+        let fd = slave.as_raw_fd();
+        let flags = libc::fcntl(fd, libc::F_GETFL);
+        libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK);
+    }
+    let device = Serial::from_port(Box::new(slave));
+    let mut printer = Printer::with_transport(None, None, SupportedPrinters::SNBC, device);
+    printer.set_write_retries(50, Duration::from_millis(10));
+
+    // A reader that keeps stalling for longer than the write timeout
+    let data: Vec<u8> = (b'a'..=b'z').cycle().take(40_000).collect();
+    let expected = data.len();
+    let reader = std::thread::spawn(move || {
+        master.set_timeout(Duration::from_millis(1000)).unwrap();
+        let mut received = Vec::new();
+        let mut buf = [0_u8; 2000];
+        while received.len() < expected {
+            std::thread::sleep(Duration::from_millis(40));
+            let n = master.read(&mut buf).unwrap();
+            received.extend_from_slice(&buf[..n]);
+        }
+        received
+    });
+
+    printer.write(&data).unwrap();
+    printer.flush().unwrap();
+    assert_eq!(reader.join().unwrap(), data);
+}
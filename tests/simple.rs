@@ -4,6 +4,7 @@ extern crate posify;
 
 use posify::barcode::{BarcodeType, Font, TextPosition};
 use posify::printer::{Printer, SupportedPrinters};
+use posify::text::{Alignment, Underline};
 
 #[test]
 fn simple() {
@@ -15,13 +16,13 @@ fn simple() {
     let _ = printer
         .chain_hwinit()
         .unwrap()
-        .chain_align("ct")
+        .chain_align(Alignment::Center)
         .unwrap()
-        .chain_underline_mode(Some("thick"))
+        .chain_underline_mode(Underline::Thick)
         .unwrap()
         .chain_text("Code128")
         .unwrap()
-        .chain_underline_mode(Some("off"))
+        .chain_underline_mode(Underline::Off)
         .unwrap()
         .chain_feed(1)
         .unwrap()
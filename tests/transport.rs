@@ -0,0 +1,230 @@
+extern crate posify;
+
+use posify::device::File;
+use posify::printer::{Printer, SupportedPrinters};
+use posify::text::Alignment;
+
+#[test]
+fn file_transport() {
+    let device = File::from(Vec::new());
+    let mut printer = Printer::with_transport(None, None, SupportedPrinters::SNBC, device);
+
+    printer
+        .chain_hwinit()
+        .unwrap()
+        .chain_align(Alignment::Center)
+        .unwrap()
+        .chain_text("Hello")
+        .unwrap()
+        .chain_partial_cut()
+        .unwrap();
+
+    let sent = printer.into_transport().unwrap().into_inner();
+    assert_eq!(
+        sent,
+        b"\x1b\x40\x1b\x61\x01Hello\n\x0a\x0a\x0a\x1d\x56\x01".to_vec()
+    );
+}
+
+#[test]
+fn file_transport_cannot_read() {
+    let device = File::from(Vec::new());
+    let mut printer = Printer::with_transport(None, None, SupportedPrinters::P3, device);
+
+    assert!(printer.paper_loaded().is_err());
+}
+
+#[test]
+fn print_document() {
+    let mut doc = posify::document::Document::new(SupportedPrinters::SNBC);
+    doc.hwinit().unwrap().text("Hello").unwrap();
+
+    let mut printer =
+        Printer::with_transport(None, None, SupportedPrinters::SNBC, File::from(Vec::new()));
+    printer.print_document(&doc).unwrap();
+
+    assert_eq!(
+        printer.into_transport().unwrap().into_inner(),
+        doc.into_bytes()
+    );
+}
+
+#[test]
+fn print_document_for_another_printer() {
+    let mut doc = posify::document::Document::new(SupportedPrinters::SNBC);
+    doc.text("Hello").unwrap();
+
+    let mut printer =
+        Printer::with_transport(None, None, SupportedPrinters::P3, File::from(Vec::new()));
+    assert!(matches!(
+        printer.print_document(&doc),
+        Err(posify::printer::Error::InvalidArgument)
+    ));
+    printer.print_document_unchecked(&doc).unwrap();
+
+    assert_eq!(
+        printer.into_transport().unwrap().into_inner(),
+        doc.into_bytes()
+    );
+}
+
+/// Records every transfer so the buffering can be checked
+#[derive(Default)]
+struct Recorder {
+    transfers: Vec<Vec<u8>>,
+}
+
+impl posify::device::Transport for Recorder {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, posify::printer::Error> {
+        self.transfers.push(buf.to_vec());
+        Ok(buf.len())
+    }
+
+    fn max_packet_size(&self) -> usize {
+        8
+    }
+}
+
+#[test]
+fn buffered_writes() {
+    let mut printer =
+        Printer::with_transport(None, None, SupportedPrinters::SNBC, Recorder::default());
+
+    printer
+        .chain_hwinit()
+        .unwrap()
+        .chain_feed(3)
+        .unwrap()
+        .chain_println("Hello world")
+        .unwrap();
+    assert!(printer.transport().transfers.is_empty());
+
+    printer.flush().unwrap();
+    let transfers = &printer.transport().transfers;
+    assert_eq!(
+        transfers.iter().map(|t| t.len()).collect::<Vec<_>>(),
+        vec![8, 8, 1]
+    );
+    assert_eq!(transfers.concat(), b"\x1b\x40\n\n\nHello world\n".to_vec());
+}
+
+#[test]
+fn buffer_threshold() {
+    let mut printer =
+        Printer::with_transport(None, None, SupportedPrinters::SNBC, Recorder::default());
+    printer.set_buffer_size(10).unwrap();
+
+    printer.print("12345").unwrap();
+    assert!(printer.transport().transfers.is_empty());
+    printer.print("67890").unwrap();
+    assert_eq!(
+        printer.transport().transfers.concat(),
+        b"1234567890".to_vec()
+    );
+}
+
+/// Shares what it was sent, so it can be checked after the printer is gone
+#[derive(Clone, Default)]
+struct Shared(std::rc::Rc<std::cell::RefCell<Vec<u8>>>);
+
+impl posify::device::Transport for Shared {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, posify::printer::Error> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+}
+
+#[test]
+fn flushes_on_drop() {
+    let sent = Shared::default();
+    let mut printer = Printer::with_transport(None, None, SupportedPrinters::SNBC, sent.clone());
+
+    printer
+        .chain_hwinit()
+        .unwrap()
+        .chain_println("Hello")
+        .unwrap();
+    assert!(sent.0.borrow().is_empty());
+    drop(printer);
+    assert_eq!(*sent.0.borrow(), b"\x1b\x40Hello\n".to_vec());
+}
+
+/// Times out on the first transfer and then only takes half of each chunk,
+/// like a printer whose receive buffer is filling up
+#[derive(Default)]
+struct Congested {
+    received: Vec<u8>,
+    calls: usize,
+}
+
+impl posify::device::Transport for Congested {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, posify::printer::Error> {
+        self.calls += 1;
+        if self.calls == 1 {
+            return Err(posify::printer::Error::Timeout);
+        }
+        let n = buf.len().div_ceil(2);
+        self.received.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+
+    fn pause(&mut self, _duration: std::time::Duration) {}
+}
+
+#[test]
+fn retries_partial_transfers() {
+    let mut printer =
+        Printer::with_transport(None, None, SupportedPrinters::SNBC, Congested::default());
+    let data: Vec<u8> = (0..=255).cycle().take(10_000).collect();
+
+    printer.write(&data).unwrap();
+    printer.flush().unwrap();
+    assert_eq!(printer.transport().received, data);
+}
+
+#[test]
+fn gives_up_after_retries() {
+    struct Stuck;
+    impl posify::device::Transport for Stuck {
+        fn write(&mut self, _buf: &[u8]) -> Result<usize, posify::printer::Error> {
+            Ok(0)
+        }
+        fn pause(&mut self, _duration: std::time::Duration) {}
+    }
+
+    let mut printer = Printer::with_transport(None, None, SupportedPrinters::SNBC, Stuck);
+    printer.print("Hello").unwrap();
+    assert!(printer.flush().unwrap_err().is_timeout());
+}
+
+/// Answers every read with a two byte status
+struct ShortStatus;
+
+impl posify::device::Transport for ShortStatus {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, posify::printer::Error> {
+        Ok(buf.len())
+    }
+
+    fn read(
+        &mut self,
+        buf: &mut [u8],
+        _timeout: std::time::Duration,
+    ) -> Result<usize, posify::printer::Error> {
+        buf[..2].copy_from_slice(&[0x00, 0x00]);
+        Ok(2)
+    }
+}
+
+#[test]
+fn short_status_reply() {
+    let mut printer = Printer::with_transport(None, None, SupportedPrinters::SNBC, ShortStatus);
+
+    assert_eq!(
+        printer.asb_status().unwrap(),
+        Some([0x00, 0x00, 0x00, 0x00])
+    );
+    assert_eq!(
+        printer.get_status(),
+        Err(vec![posify::printer::StatusError::Online])
+    );
+}